@@ -0,0 +1,24 @@
+use std::fmt::Write;
+use std::time::Instant;
+
+use rpython::Compiler;
+
+/// Compiles a generated script with many distinct names and constants.
+fn main() {
+    let mut source = String::new();
+    for i in 0..20_000 {
+        writeln!(source, "v{} = {} + {}.5 + len('s{}')", i, i, i, i).unwrap();
+    }
+
+    let started = Instant::now();
+    let code = Compiler::default().compile(&source).unwrap();
+    let elapsed = started.elapsed();
+
+    println!(
+        "compiled {} lines ({} names, {} consts) in {:?}",
+        source.lines().count(),
+        code.names.len(),
+        code.consts.len(),
+        elapsed
+    );
+}
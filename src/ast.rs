@@ -1,101 +1,300 @@
 use crate::bytecode::*;
 use crate::object::*;
 use crate::opcode::*;
-use std::collections::BTreeMap;
+use std::collections::{HashMap, HashSet};
 
 use ruff_python_ast::{self as ast, Mod};
 use ruff_python_parser::{Mode, ParseOptions, parse};
+use ruff_text_size::Ranged;
+
+/// Constants are keyed by variant as well as value: `1`, `1.0` and `True`
+/// compare and hash equal, but each literal must load as itself.
+type ConstKey = (std::mem::Discriminant<PyObject>, PyObject);
+
+type Scope = (
+    HashMap<String, usize>,
+    HashMap<ConstKey, usize>,
+    HashSet<String>,
+    bool,
+    Blocks,
+);
+
+/// The block nesting the VM will see at the code being compiled, so that
+/// `return`, `break` and `continue` can run the `finally` bodies they leave.
+#[derive(Default)]
+struct Blocks {
+    // entries on the VM's handler stack
+    handlers: usize,
+    loops: usize,
+    // enclosing `finally` bodies, with the handler and loop depth at `try`
+    finally: Vec<(Vec<ast::Stmt>, usize, usize)>,
+}
 
 #[derive(Default)]
 pub struct Compiler {
-    #[allow(dead_code)]
-    pub strings: BTreeMap<String, usize>,
+    // indices into the names/consts of the code object being compiled
+    pub strings: HashMap<String, usize>,
+    consts: HashMap<ConstKey, usize>,
+    // names declared `global` in the code object being compiled
+    globals: HashSet<String>,
+    in_function: bool,
+    blocks: Blocks,
+    line_starts: Vec<usize>,
+    line: usize,
 }
 
 impl Compiler {
     pub fn compile(&mut self, source: &str) -> Result<CodeObject, String> {
-        let module = parse(source, ParseOptions::from(Mode::Module)).map_err(|e| e.to_string())?;
-        let module = match module.syntax() {
-            Mod::Module(module) => module,
-            Mod::Expression(_) => return Err("Invalid syntax".to_string()),
-        };
-        let module = ruff_python_ast::Mod::Module(module.clone());
+        self.compile_source(source, Mode::Module)
+    }
+
+    /// Compiles a single expression whose value the code object returns.
+    pub fn compile_expression(&mut self, source: &str) -> Result<CodeObject, String> {
+        self.compile_source(source, Mode::Expression)
+    }
+
+    fn compile_source(&mut self, source: &str, mode: Mode) -> Result<CodeObject, String> {
+        let parsed = parse(source, ParseOptions::from(mode))
+            .map_err(|e| format!("SyntaxError: {}", e.error))?;
+        let module: &Mod = parsed.syntax();
+        self.line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        self.line = 1;
+        self.strings.clear();
+        self.consts.clear();
+        self.globals.clear();
+        self.in_function = false;
+        self.blocks = Blocks::default();
         let mut code = CodeObject::default();
-        self.compile_body(&module, &mut code)?;
+        self.compile_body(module, &mut code)?;
         Ok(code)
     }
 
     fn name_index(&mut self, code: &mut CodeObject, name: &str) -> usize {
-        if let Some((i, _)) = code.names.iter().enumerate().find(|(_, n)| n == &name) {
-            i
-        } else {
-            code.names.push(name.to_string());
-            code.names.len() - 1
+        if let Some(&i) = self.strings.get(name) {
+            return i;
         }
+
+        code.names.push(name.to_string());
+        self.strings.insert(name.to_string(), code.names.len() - 1);
+        code.names.len() - 1
     }
 
     fn const_index(&mut self, code: &mut CodeObject, obj: PyObject) -> usize {
-        if let Some((i, _)) = code.consts.iter().enumerate().find(|(_, v)| *v == &obj) {
-            i
-        } else {
+        if !obj.is_hashable() {
+            if let Some(i) = code.consts.iter().position(|v| v == &obj) {
+                return i;
+            }
             code.consts.push(obj);
-            code.consts.len() - 1
+            return code.consts.len() - 1;
+        }
+
+        let key = (std::mem::discriminant(&obj), obj.clone());
+        if let Some(&i) = self.consts.get(&key) {
+            return i;
         }
+
+        code.consts.push(obj);
+        self.consts.insert(key, code.consts.len() - 1);
+        code.consts.len() - 1
+    }
+
+    /// Starts fresh name and constant indices for a nested code object,
+    /// returning the enclosing ones to hand back to `leave_scope`.
+    fn enter_scope(&mut self, in_function: bool) -> Scope {
+        (
+            std::mem::take(&mut self.strings),
+            std::mem::take(&mut self.consts),
+            std::mem::take(&mut self.globals),
+            std::mem::replace(&mut self.in_function, in_function),
+            std::mem::take(&mut self.blocks),
+        )
+    }
+
+    fn leave_scope(&mut self, saved: Scope) {
+        (
+            self.strings,
+            self.consts,
+            self.globals,
+            self.in_function,
+            self.blocks,
+        ) = saved;
+    }
+
+    /// Emits the `finally` bodies an early exit jumps out of, innermost first,
+    /// popping the handlers set up since each `try`. A `break` or `continue`
+    /// only leaves those inside the innermost loop.
+    fn compile_finally_exits(
+        &mut self,
+        loop_exit: bool,
+        code: &mut CodeObject,
+    ) -> Result<(), String> {
+        let finally = std::mem::take(&mut self.blocks.finally);
+        let (handlers, loops) = (self.blocks.handlers, self.blocks.loops);
+
+        let mut result = Ok(());
+        for (i, (body, depth, loop_depth)) in finally.iter().enumerate().rev() {
+            if loop_exit && *loop_depth != loops {
+                break;
+            }
+
+            for _ in *depth..self.blocks.handlers {
+                code.instructions.push(Op::PopExcept);
+            }
+            self.blocks.handlers = *depth;
+            self.blocks.finally = finally[..i].to_vec();
+
+            result = body
+                .iter()
+                .try_for_each(|stmt| self.compile_stmt(stmt, code));
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.blocks.finally = finally;
+        self.blocks.handlers = handlers;
+        result
+    }
+
+    fn load_name(&mut self, code: &mut CodeObject, name: &str) {
+        let idx = self.name_index(code, name);
+        if self.globals.contains(name) {
+            code.instructions.push(Op::LoadGlobal(idx));
+        } else {
+            code.instructions.push(Op::LoadName(idx));
+        }
+    }
+
+    fn store_name(&mut self, code: &mut CodeObject, name: &str) {
+        let idx = self.name_index(code, name);
+        if self.globals.contains(name) {
+            code.instructions.push(Op::StoreGlobal(idx));
+        } else {
+            code.instructions.push(Op::StoreName(idx));
+        }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    fn mark_lines(&self, code: &mut CodeObject) {
+        code.lines.resize(code.instructions.len(), self.line);
     }
 
     fn compile_body(&mut self, module: &ast::Mod, code: &mut CodeObject) -> Result<(), String> {
         match module {
             ast::Mod::Module(ast::ModModule { body, .. }) => {
-                for stmt in body {
+                // like a REPL, a program evaluates to its trailing expression,
+                // so that one statement keeps its value on the stack
+                let trailing = match body.last() {
+                    Some(ast::Stmt::Expr(e)) => Some(e),
+                    _ => None,
+                };
+
+                for stmt in &body[..body.len() - trailing.map_or(0, |_| 1)] {
                     self.compile_stmt(stmt, code)?;
                 }
 
-                if body.is_empty() {
+                if let Some(e) = trailing {
+                    self.with_line(e.range(), code, |this, code| {
+                        this.compile_expr(&e.value, code)
+                    })?;
+                } else {
                     let none_idx = self.const_index(code, PyObject::None);
                     code.instructions.push(Op::LoadConst(none_idx));
                 }
 
                 code.instructions.push(Op::Return);
+                self.mark_lines(code);
+                Ok(())
+            }
+            ast::Mod::Expression(ast::ModExpression { body, .. }) => {
+                self.with_line(body.range(), code, |this, code| {
+                    this.compile_expr(body, code)
+                })?;
+                code.instructions.push(Op::Return);
+                self.mark_lines(code);
                 Ok(())
             }
-            _ => Err("unsupported mode".to_string()),
         }
     }
 
     fn compile_stmt(&mut self, stmt: &ast::Stmt, code: &mut CodeObject) -> Result<(), String> {
+        self.with_line(stmt.range(), code, |this, code| {
+            this.compile_stmt_kind(stmt, code)
+        })
+    }
+
+    /// Runs `f`, attributing the instructions it emits to the line `range`
+    /// starts on.
+    fn with_line(
+        &mut self,
+        range: ruff_text_size::TextRange,
+        code: &mut CodeObject,
+        f: impl FnOnce(&mut Self, &mut CodeObject) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.mark_lines(code);
+        let line = self.line_of(range.start().to_usize());
+        let outer = std::mem::replace(&mut self.line, line);
+        let result = f(self, code);
+        self.mark_lines(code);
+        self.line = outer;
+        result
+    }
+
+    fn compile_stmt_kind(&mut self, stmt: &ast::Stmt, code: &mut CodeObject) -> Result<(), String> {
         match stmt {
             ast::Stmt::Assign(a) => {
                 if a.targets.len() != 1 {
                     return Err("unsupported assignment".to_string());
                 }
 
-                self.compile_expr(&a.value, code)?;
-
-                match &a.targets[0] {
-                    ast::Expr::Name(n) => {
-                        let idx = self.name_index(code, n.id.as_str());
-                        code.instructions.push(Op::StoreName(idx));
-                        Ok(())
-                    }
-                    ast::Expr::Subscript(sub) => {
-                        self.compile_expr(&sub.value, code)?;
-                        self.compile_expr(&sub.slice, code)?;
-                        self.compile_expr(&a.value, code)?;
-                        code.instructions.push(Op::StoreIndex);
-                        Ok(())
-                    }
-                    ast::Expr::Attribute(attr) => {
-                        self.compile_expr(&attr.value, code)?;
-                        let attr_idx = self.name_index(code, attr.attr.as_str());
-                        self.compile_expr(&a.value, code)?;
-                        code.instructions.push(Op::StoreAttr(attr_idx));
-                        Ok(())
-                    }
-                    _ => Err("unsupported assignment target".to_string()),
+                self.compile_assign(&a.targets[0], &a.value, code)
+            }
+            ast::Stmt::AnnAssign(ann) => {
+                // annotations are not evaluated or stored at runtime
+                match &ann.value {
+                    Some(value) => self.compile_assign(&ann.target, value, code),
+                    None => Ok(()),
                 }
             }
+            ast::Stmt::AugAssign(aug) => match &*aug.target {
+                ast::Expr::Name(n) => {
+                    self.load_name(code, n.id.as_str());
+                    self.compile_expr(&aug.value, code)?;
+                    code.instructions.push(Self::binop(aug.op)?);
+                    self.store_name(code, n.id.as_str());
+                    Ok(())
+                }
+                // the container and index are evaluated once and kept for the store
+                ast::Expr::Subscript(sub) => {
+                    self.compile_expr(&sub.value, code)?;
+                    self.compile_expr(&sub.slice, code)?;
+                    code.instructions.push(Op::DupTop2);
+                    code.instructions.push(Op::LoadIndex);
+                    self.compile_expr(&aug.value, code)?;
+                    code.instructions.push(Self::binop(aug.op)?);
+                    code.instructions.push(Op::StoreIndex);
+                    Ok(())
+                }
+                ast::Expr::Attribute(attr) => {
+                    self.compile_expr(&attr.value, code)?;
+                    code.instructions.push(Op::Dup);
+                    let attr_idx = self.name_index(code, attr.attr.as_str());
+                    code.instructions.push(Op::LoadAttr(attr_idx));
+                    self.compile_expr(&aug.value, code)?;
+                    code.instructions.push(Self::binop(aug.op)?);
+                    code.instructions.push(Op::StoreAttr(attr_idx));
+                    Ok(())
+                }
+                _ => Err("unsupported augmented assignment target".to_string()),
+            },
             ast::Stmt::Expr(e) => {
                 self.compile_expr(&e.value, code)?;
+                code.instructions.push(Op::Pop);
                 Ok(())
             }
             ast::Stmt::If(if_stmt) => {
@@ -131,58 +330,71 @@ impl Compiler {
             }
             ast::Stmt::While(while_stmt) => {
                 let loop_start = code.instructions.len();
-                code.instructions.push(Op::SetupLoop(0));
+                code.instructions.push(Op::SetupLoop { cont: 0, exit: 0 });
 
                 let test_start = code.instructions.len();
                 self.compile_expr(&while_stmt.test, code)?;
                 let exit_jump = code.instructions.len();
                 code.instructions.push(Op::JumpIfFalse(0));
 
+                self.blocks.loops += 1;
                 for stmt in &while_stmt.body {
                     self.compile_stmt(stmt, code)?;
                 }
+                self.blocks.loops -= 1;
 
                 code.instructions.push(Op::Jump(test_start));
                 let loop_end = code.instructions.len();
                 code.instructions.push(Op::PopBlock);
 
-                code.instructions[loop_start] = Op::SetupLoop(loop_end);
+                code.instructions[loop_start] = Op::SetupLoop {
+                    cont: test_start,
+                    exit: loop_end,
+                };
                 code.instructions[exit_jump] = Op::JumpIfFalse(loop_end);
 
                 Ok(())
             }
             ast::Stmt::Break(_) => {
+                self.compile_finally_exits(true, code)?;
                 code.instructions.push(Op::Break);
                 Ok(())
             }
             ast::Stmt::Continue(_) => {
+                self.compile_finally_exits(true, code)?;
                 code.instructions.push(Op::Continue);
                 Ok(())
             }
             ast::Stmt::For(for_stmt) => {
-                if let ast::Expr::Name(target) = &*for_stmt.target {
+                if let ast::Expr::Name(_) | ast::Expr::Tuple(_) | ast::Expr::List(_) =
+                    &*for_stmt.target
+                {
+                    let loop_start = code.instructions.len();
+                    code.instructions.push(Op::SetupLoop { cont: 0, exit: 0 });
+
                     self.compile_expr(&for_stmt.iter, code)?;
                     code.instructions.push(Op::GetIter);
 
-                    let loop_start = code.instructions.len();
-                    code.instructions.push(Op::SetupLoop(0));
-
                     let for_iter_pos = code.instructions.len();
                     code.instructions.push(Op::ForIter(0));
 
-                    let target_idx = self.name_index(code, target.id.as_str());
-                    code.instructions.push(Op::StoreName(target_idx));
+                    self.compile_store(&for_stmt.target, code)?;
 
+                    self.blocks.loops += 1;
                     for stmt in &for_stmt.body {
                         self.compile_stmt(stmt, code)?;
                     }
+                    self.blocks.loops -= 1;
 
                     code.instructions.push(Op::Jump(for_iter_pos));
 
                     let loop_end = code.instructions.len();
                     code.instructions.push(Op::PopBlock);
 
-                    code.instructions[loop_start] = Op::SetupLoop(loop_end);
+                    code.instructions[loop_start] = Op::SetupLoop {
+                        cont: for_iter_pos,
+                        exit: loop_end,
+                    };
                     code.instructions[for_iter_pos] = Op::ForIter(loop_end);
 
                     Ok(())
@@ -193,6 +405,22 @@ impl Compiler {
             ast::Stmt::FunctionDef(fd) => {
                 let mut fcode = CodeObject::default();
                 let mut arg_names = Vec::new();
+                let mut defaults = 0;
+
+                // decorators are evaluated before the function is defined
+                for decorator in &fd.decorator_list {
+                    self.compile_expr(&decorator.expression, code)?;
+                }
+
+                // defaults are evaluated once, in the defining scope
+                for arg in &fd.parameters.args {
+                    if let Some(default) = &arg.default {
+                        self.compile_expr(default, code)?;
+                        defaults += 1;
+                    }
+                }
+
+                let outer = self.enter_scope(true);
 
                 for arg in &fd.parameters.args {
                     arg_names.push(arg.parameter.name.to_string());
@@ -202,13 +430,23 @@ impl Compiler {
                     self.name_index(&mut fcode, a);
                 }
 
-                for s in &fd.body {
+                let body = match Self::docstring(&fd.body) {
+                    Some(doc) => {
+                        fcode.doc = Some(doc);
+                        &fd.body[1..]
+                    }
+                    None => &fd.body[..],
+                };
+
+                for s in body {
                     self.compile_stmt(s, &mut fcode)?;
                 }
 
                 let none_idx = self.const_index(&mut fcode, PyObject::None);
                 fcode.instructions.push(Op::LoadConst(none_idx));
-                // fcode.instructions.push(Op::Return);
+                fcode.instructions.push(Op::Return);
+                self.leave_scope(outer);
+
                 let code_idx = code.nested.len();
                 code.nested.push(fcode);
                 let name_idx = self.name_index(code, fd.name.as_str());
@@ -216,10 +454,23 @@ impl Compiler {
                 code.instructions.push(Op::Def {
                     name: name_idx,
                     arity,
+                    defaults,
                     code_idx,
                 });
+
+                // and applied innermost first, rebinding the name to the result
+                if !fd.decorator_list.is_empty() {
+                    self.load_name(code, fd.name.as_str());
+                    for _ in &fd.decorator_list {
+                        code.instructions.push(Op::Call(1));
+                    }
+                    self.store_name(code, fd.name.as_str());
+                }
                 Ok(())
             }
+            ast::Stmt::Return(_) if !self.in_function => {
+                Err("SyntaxError: 'return' outside function".to_string())
+            }
             ast::Stmt::Return(ret) => {
                 if let Some(value) = &ret.value {
                     self.compile_expr(value, code)?;
@@ -228,18 +479,31 @@ impl Compiler {
                     code.instructions.push(Op::LoadConst(none_idx));
                 }
 
+                self.compile_finally_exits(false, code)?;
                 code.instructions.push(Op::Return);
                 Ok(())
             }
             ast::Stmt::ClassDef(cd) => {
                 let mut class_code = CodeObject::default();
+                let outer = self.enter_scope(false);
+
+                let body = match Self::docstring(&cd.body) {
+                    Some(doc) => {
+                        let idx = self.const_index(&mut class_code, PyObject::Str(doc));
+                        class_code.instructions.push(Op::LoadConst(idx));
+                        self.store_name(&mut class_code, "__doc__");
+                        &cd.body[1..]
+                    }
+                    None => &cd.body[..],
+                };
 
-                for stmt in &cd.body {
+                for stmt in body {
                     self.compile_stmt(stmt, &mut class_code)?;
                 }
 
                 let none_idx = self.const_index(&mut class_code, PyObject::None);
                 class_code.instructions.push(Op::LoadConst(none_idx));
+                self.leave_scope(outer);
 
                 let code_idx = code.nested.len();
                 code.nested.push(class_code);
@@ -252,12 +516,80 @@ impl Compiler {
 
                 Ok(())
             }
+            ast::Stmt::Try(try_stmt) => {
+                if try_stmt.finalbody.is_empty() {
+                    return self.compile_try_except(try_stmt, code);
+                }
+
+                let setup = code.instructions.len();
+                code.instructions.push(Op::SetupExcept(0));
+
+                let depth = self.blocks.handlers;
+                self.blocks.handlers += 1;
+                self.blocks
+                    .finally
+                    .push((try_stmt.finalbody.clone(), depth, self.blocks.loops));
+                self.compile_try_except(try_stmt, code)?;
+                self.blocks.finally.pop();
+                self.blocks.handlers = depth;
+
+                code.instructions.push(Op::PopExcept);
+                for stmt in &try_stmt.finalbody {
+                    self.compile_stmt(stmt, code)?;
+                }
+                let end_jump = code.instructions.len();
+                code.instructions.push(Op::Jump(0));
+
+                code.instructions[setup] = Op::SetupExcept(code.instructions.len());
+                for stmt in &try_stmt.finalbody {
+                    self.compile_stmt(stmt, code)?;
+                }
+                code.instructions.push(Op::Reraise);
+
+                code.instructions[end_jump] = Op::Jump(code.instructions.len());
+                Ok(())
+            }
+            ast::Stmt::Raise(raise) => {
+                if let Some(exc) = &raise.exc {
+                    self.compile_expr(exc, code)?;
+                    code.instructions.push(Op::Raise(1));
+                } else {
+                    code.instructions.push(Op::Raise(0));
+                }
+
+                Ok(())
+            }
             ast::Stmt::Pass(_) => Ok(()),
+            ast::Stmt::Nonlocal(_) if !self.in_function => {
+                Err("SyntaxError: nonlocal declaration not allowed at module level".to_string())
+            }
+            ast::Stmt::Global(global) => {
+                self.globals
+                    .extend(global.names.iter().map(|n| n.to_string()));
+                Ok(())
+            }
             ast::Stmt::Import(import) => {
                 for alias in &import.names {
                     let name = alias.name.as_str();
                     let idx = self.name_index(code, name);
                     code.instructions.push(Op::Import(idx));
+
+                    let mut parts = name.split('.');
+                    let top = parts.next().unwrap_or_default();
+
+                    let bound = match &alias.asname {
+                        Some(asname) => {
+                            // `import a.b as m` binds the submodule itself
+                            for part in parts {
+                                let attr_idx = self.name_index(code, part);
+                                code.instructions.push(Op::LoadAttr(attr_idx));
+                            }
+                            asname.as_str()
+                        }
+                        None => top,
+                    };
+
+                    self.store_name(code, bound);
                 }
 
                 Ok(())
@@ -273,7 +605,9 @@ impl Compiler {
 
                         for alias in &import.names {
                             let name_idx = self.name_index(code, alias.name.as_str());
-                            name_indices.push(name_idx);
+                            let bound = alias.asname.as_ref().unwrap_or(&alias.name);
+                            let bound_idx = self.name_index(code, bound.as_str());
+                            name_indices.push((name_idx, bound_idx));
                         }
 
                         code.instructions.push(Op::ImportFrom {
@@ -291,6 +625,305 @@ impl Compiler {
         }
     }
 
+    fn compile_assign(
+        &mut self,
+        target: &ast::Expr,
+        value: &ast::Expr,
+        code: &mut CodeObject,
+    ) -> Result<(), String> {
+        self.compile_expr(value, code)?;
+        self.compile_store(target, code)
+    }
+
+    fn compile_try_except(
+        &mut self,
+        try_stmt: &ast::StmtTry,
+        code: &mut CodeObject,
+    ) -> Result<(), String> {
+        if try_stmt.handlers.is_empty() {
+            for stmt in try_stmt.body.iter().chain(&try_stmt.orelse) {
+                self.compile_stmt(stmt, code)?;
+            }
+
+            return Ok(());
+        }
+
+        let setup = code.instructions.len();
+        code.instructions.push(Op::SetupExcept(0));
+
+        self.blocks.handlers += 1;
+        for stmt in &try_stmt.body {
+            self.compile_stmt(stmt, code)?;
+        }
+        self.blocks.handlers -= 1;
+
+        code.instructions.push(Op::PopExcept);
+
+        for stmt in &try_stmt.orelse {
+            self.compile_stmt(stmt, code)?;
+        }
+
+        let mut end_jumps = vec![code.instructions.len()];
+        code.instructions.push(Op::Jump(0));
+
+        code.instructions[setup] = Op::SetupExcept(code.instructions.len());
+
+        for handler in &try_stmt.handlers {
+            let ast::ExceptHandler::ExceptHandler(handler) = handler;
+
+            let skip_jump = if let Some(type_) = &handler.type_ {
+                let names: Vec<&ast::Expr> = match &**type_ {
+                    ast::Expr::Tuple(t) => t.elts.iter().collect(),
+                    other => vec![other],
+                };
+
+                let mut match_jumps = Vec::new();
+
+                for name in names {
+                    let ast::Expr::Name(n) = name else {
+                        return Err("unsupported exception type in except clause".to_string());
+                    };
+                    let name_idx = self.name_index(code, n.id.as_str());
+                    match_jumps.push(code.instructions.len());
+                    code.instructions.push(Op::ExceptMatch {
+                        name: name_idx,
+                        target: 0,
+                    });
+                }
+
+                let skip_jump = code.instructions.len();
+                code.instructions.push(Op::Jump(0));
+
+                let body_start = code.instructions.len();
+                for jump in match_jumps {
+                    if let Op::ExceptMatch { name, .. } = code.instructions[jump] {
+                        code.instructions[jump] = Op::ExceptMatch {
+                            name,
+                            target: body_start,
+                        };
+                    }
+                }
+
+                Some(skip_jump)
+            } else {
+                None
+            };
+
+            if let Some(name) = &handler.name {
+                self.store_name(code, name.as_str());
+            } else {
+                code.instructions.push(Op::Pop);
+            }
+
+            for stmt in &handler.body {
+                self.compile_stmt(stmt, code)?;
+            }
+            code.instructions.push(Op::EndExcept);
+
+            end_jumps.push(code.instructions.len());
+            code.instructions.push(Op::Jump(0));
+
+            if let Some(skip_jump) = skip_jump {
+                code.instructions[skip_jump] = Op::Jump(code.instructions.len());
+            }
+        }
+
+        // no clause matched
+        code.instructions.push(Op::Reraise);
+
+        for jump in end_jumps {
+            code.instructions[jump] = Op::Jump(code.instructions.len());
+        }
+
+        Ok(())
+    }
+
+    fn compile_store(&mut self, target: &ast::Expr, code: &mut CodeObject) -> Result<(), String> {
+        match target {
+            ast::Expr::Name(n) => {
+                self.store_name(code, n.id.as_str());
+                Ok(())
+            }
+            ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+            | ast::Expr::List(ast::ExprList { elts, .. }) => {
+                let starred: Vec<usize> = elts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| matches!(e, ast::Expr::Starred(_)))
+                    .map(|(i, _)| i)
+                    .collect();
+
+                match starred[..] {
+                    [] => code.instructions.push(Op::UnpackSequence(elts.len())),
+                    [star] => code.instructions.push(Op::UnpackEx {
+                        before: star,
+                        after: elts.len() - star - 1,
+                    }),
+                    _ => {
+                        return Err(
+                            "SyntaxError: multiple starred expressions in assignment".to_string()
+                        );
+                    }
+                }
+
+                for elt in elts {
+                    match elt {
+                        ast::Expr::Starred(s) => self.compile_store(&s.value, code)?,
+                        other => self.compile_store(other, code)?,
+                    }
+                }
+
+                Ok(())
+            }
+            // like Python, the value is evaluated before the container and key
+            ast::Expr::Subscript(sub) => {
+                self.compile_expr(&sub.value, code)?;
+                self.compile_expr(&sub.slice, code)?;
+                code.instructions.push(Op::RotThree);
+                code.instructions.push(Op::StoreIndex);
+                Ok(())
+            }
+            ast::Expr::Attribute(attr) => {
+                self.compile_expr(&attr.value, code)?;
+                code.instructions.push(Op::Swap);
+                let attr_idx = self.name_index(code, attr.attr.as_str());
+                code.instructions.push(Op::StoreAttr(attr_idx));
+                Ok(())
+            }
+            ast::Expr::BooleanLiteral(_) | ast::Expr::NoneLiteral(_) => Err(format!(
+                "SyntaxError: cannot assign to {}",
+                Self::literal_name(target)
+            )),
+            _ => Err("unsupported assignment target".to_string()),
+        }
+    }
+
+    /// The text of a leading string statement, which documents its body.
+    fn docstring(body: &[ast::Stmt]) -> Option<String> {
+        match body.first() {
+            Some(ast::Stmt::Expr(e)) => match &*e.value {
+                ast::Expr::StringLiteral(s) => Some(s.value.to_string()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn literal_name(expr: &ast::Expr) -> &'static str {
+        match expr {
+            ast::Expr::BooleanLiteral(b) if b.value => "True",
+            ast::Expr::BooleanLiteral(_) => "False",
+            _ => "None",
+        }
+    }
+
+    /// Names bound by assigning to `target`.
+    fn target_names(target: &ast::Expr, names: &mut Vec<String>) {
+        match target {
+            ast::Expr::Name(n) => {
+                if !names.iter().any(|name| name == n.id.as_str()) {
+                    names.push(n.id.to_string());
+                }
+            }
+            ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+            | ast::Expr::List(ast::ExprList { elts, .. }) => {
+                for elt in elts {
+                    Self::target_names(elt, names);
+                }
+            }
+            ast::Expr::Starred(s) => Self::target_names(&s.value, names),
+            _ => {}
+        }
+    }
+
+    /// Compiles a comprehension into the container `build` creates. The loop
+    /// variables belong to the comprehension, so whatever the enclosing scope
+    /// bound to those names is put back afterwards.
+    fn compile_comprehension(
+        &mut self,
+        generators: &[ast::Comprehension],
+        key: Option<&ast::Expr>,
+        elt: &ast::Expr,
+        build: Op,
+        add: Op,
+        code: &mut CodeObject,
+    ) -> Result<(), String> {
+        let mut names = Vec::new();
+        for generator in generators {
+            Self::target_names(&generator.target, &mut names);
+        }
+
+        let saved: Vec<usize> = names.iter().map(|n| self.name_index(code, n)).collect();
+        for &idx in &saved {
+            code.instructions.push(Op::SaveName(idx));
+        }
+        code.instructions.push(build);
+
+        // a `global` declaration doesn't reach the loop variables either
+        let globals = self.globals.clone();
+        for name in &names {
+            self.globals.remove(name);
+        }
+        let result = self.compile_generators(generators, key, elt, add, code);
+        self.globals = globals;
+        result?;
+
+        for &idx in saved.iter().rev() {
+            code.instructions.push(Op::Swap);
+            code.instructions.push(Op::RestoreName(idx));
+        }
+
+        Ok(())
+    }
+
+    fn compile_generators(
+        &mut self,
+        generators: &[ast::Comprehension],
+        key: Option<&ast::Expr>,
+        elt: &ast::Expr,
+        add: Op,
+        code: &mut CodeObject,
+    ) -> Result<(), String> {
+        let Some((generator, rest)) = generators.split_first() else {
+            if let Some(key) = key {
+                self.compile_expr(key, code)?;
+            }
+
+            self.compile_expr(elt, code)?;
+            code.instructions.push(add);
+            return Ok(());
+        };
+
+        self.compile_expr(&generator.iter, code)?;
+        code.instructions.push(Op::GetIter);
+
+        let for_iter_pos = code.instructions.len();
+        code.instructions.push(Op::ForIter(0));
+        self.compile_store(&generator.target, code)?;
+
+        for cond in &generator.ifs {
+            self.compile_expr(cond, code)?;
+            code.instructions.push(Op::JumpIfFalse(for_iter_pos));
+        }
+
+        self.compile_generators(rest, key, elt, add, code)?;
+        code.instructions.push(Op::Jump(for_iter_pos));
+        code.instructions[for_iter_pos] = Op::ForIter(code.instructions.len());
+
+        Ok(())
+    }
+
+    fn binop(op: ast::Operator) -> Result<Op, String> {
+        match op {
+            ast::Operator::Add => Ok(Op::Add),
+            ast::Operator::Sub => Ok(Op::Sub),
+            ast::Operator::Mult => Ok(Op::Mul),
+            ast::Operator::Div => Ok(Op::Div),
+            ast::Operator::FloorDiv => Ok(Op::FloorDiv),
+            _ => Err("unsupported binop".to_string()),
+        }
+    }
+
     fn compile_expr(&mut self, expr: &ast::Expr, code: &mut CodeObject) -> Result<(), String> {
         match expr {
             ast::Expr::BooleanLiteral(bl) => {
@@ -305,12 +938,21 @@ impl Compiler {
                 code.instructions.push(Op::LoadConst(idx));
                 Ok(())
             }
+            ast::Expr::BytesLiteral(bl) => {
+                let obj = PyObject::Bytes(bl.value.bytes().collect());
+                let idx = self.const_index(code, obj);
+                code.instructions.push(Op::LoadConst(idx));
+                Ok(())
+            }
             ast::Expr::NumberLiteral(il) => {
-                let obj = if il.value.is_int() {
-                    PyObject::Int(il.value.as_int().unwrap().as_i64().unwrap())
-                } else {
-                    PyObject::Float(*il.value.as_float().unwrap())
-                };
+                let obj =
+                    match &il.value {
+                        ast::Number::Int(i) => PyObject::Int(i.as_i64().ok_or_else(|| {
+                            "OverflowError: integer literal too large".to_string()
+                        })?),
+                        ast::Number::Float(f) => PyObject::Float(*f),
+                        ast::Number::Complex { real, imag } => PyObject::Complex(*real, *imag),
+                    };
                 let idx = self.const_index(code, obj);
                 code.instructions.push(Op::LoadConst(idx));
                 Ok(())
@@ -321,20 +963,25 @@ impl Compiler {
                 code.instructions.push(Op::LoadConst(idx));
                 Ok(())
             }
+            ast::Expr::EllipsisLiteral(_) => {
+                let idx = self.const_index(code, PyObject::Ellipsis);
+                code.instructions.push(Op::LoadConst(idx));
+                Ok(())
+            }
             ast::Expr::UnaryOp(unary) => {
                 self.compile_expr(&unary.operand, code)?;
 
                 match unary.op {
                     ast::UnaryOp::UAdd => code.instructions.push(Op::UnaryPos),
                     ast::UnaryOp::USub => code.instructions.push(Op::UnaryNeg),
+                    ast::UnaryOp::Not => code.instructions.push(Op::UnaryNot),
                     _ => return Err("unsupported unary operator".to_string()),
                 }
 
                 Ok(())
             }
             ast::Expr::Name(n) => {
-                let idx = self.name_index(code, n.id.as_str());
-                code.instructions.push(Op::LoadName(idx));
+                self.load_name(code, n.id.as_str());
                 Ok(())
             }
             ast::Expr::Attribute(attr) => {
@@ -386,16 +1033,61 @@ impl Compiler {
                 code.instructions.push(Op::LoadIndex);
                 Ok(())
             }
+            ast::Expr::ListComp(comp) => self.compile_comprehension(
+                &comp.generators,
+                None,
+                &comp.elt,
+                Op::BuildList(0),
+                Op::ListAppend,
+                code,
+            ),
+            ast::Expr::SetComp(comp) => self.compile_comprehension(
+                &comp.generators,
+                None,
+                &comp.elt,
+                Op::BuildSet(0),
+                Op::SetAdd,
+                code,
+            ),
+            ast::Expr::DictComp(comp) => self.compile_comprehension(
+                &comp.generators,
+                Some(&*comp.key),
+                &comp.value,
+                Op::BuildDict(0),
+                Op::MapAdd,
+                code,
+            ),
             ast::Expr::BinOp(b) => {
                 self.compile_expr(&b.left, code)?;
                 self.compile_expr(&b.right, code)?;
+                code.instructions.push(Self::binop(b.op)?);
+                Ok(())
+            }
+            ast::Expr::Named(named) => {
+                self.compile_expr(&named.value, code)?;
+                self.compile_store(&named.target, code)?;
+                self.compile_expr(&named.target, code)
+            }
+            ast::Expr::BoolOp(bool_op) => {
+                let mut jumps = Vec::new();
+
+                for (i, value) in bool_op.values.iter().enumerate() {
+                    self.compile_expr(value, code)?;
+
+                    // the last operand is the result whenever no earlier one
+                    // short-circuits
+                    if i + 1 < bool_op.values.len() {
+                        jumps.push(code.instructions.len());
+                        code.instructions.push(Op::Jump(0));
+                    }
+                }
 
-                match b.op {
-                    ast::Operator::Add => code.instructions.push(Op::Add),
-                    ast::Operator::Sub => code.instructions.push(Op::Sub),
-                    ast::Operator::Mult => code.instructions.push(Op::Mul),
-                    ast::Operator::Div => code.instructions.push(Op::Div),
-                    _ => return Err("unsupported binop".to_string()),
+                let end = code.instructions.len();
+                for j in jumps {
+                    code.instructions[j] = match bool_op.op {
+                        ast::BoolOp::And => Op::JumpIfFalseOrPop(end),
+                        ast::BoolOp::Or => Op::JumpIfTrueOrPop(end),
+                    };
                 }
 
                 Ok(())
@@ -415,6 +1107,11 @@ impl Compiler {
                     ast::CmpOp::LtE => code.instructions.push(Op::Le),
                     ast::CmpOp::Gt => code.instructions.push(Op::Gt),
                     ast::CmpOp::GtE => code.instructions.push(Op::Ge),
+                    ast::CmpOp::In => code.instructions.push(Op::Contains),
+                    ast::CmpOp::NotIn => {
+                        code.instructions.push(Op::Contains);
+                        code.instructions.push(Op::UnaryNot);
+                    }
                     _ => return Err("unsupported comparison".to_string()),
                 }
 
@@ -423,16 +1120,42 @@ impl Compiler {
             ast::Expr::Call(call) => {
                 self.compile_expr(&call.func, code)?;
 
-                let argc = call.arguments.len();
+                let argc = call.arguments.args.len();
 
                 for a in &call.arguments.args {
+                    if matches!(a, ast::Expr::Starred(_)) {
+                        return Err("unsupported starred argument".to_string());
+                    }
                     self.compile_expr(a, code)?;
                 }
 
-                code.instructions.push(Op::Call(argc));
+                if call.arguments.keywords.is_empty() {
+                    if matches!(&*call.func, ast::Expr::Attribute(_)) {
+                        code.instructions.push(Op::CallMethod(argc));
+                    } else {
+                        code.instructions.push(Op::Call(argc));
+                    }
+                    return Ok(());
+                }
+
+                for kw in &call.arguments.keywords {
+                    let Some(name) = &kw.arg else {
+                        return Err("unsupported ** argument".to_string());
+                    };
+                    let idx = self.const_index(code, PyObject::Str(name.to_string()));
+                    code.instructions.push(Op::LoadConst(idx));
+                    self.compile_expr(&kw.value, code)?;
+                }
+
+                code.instructions
+                    .push(Op::BuildDict(call.arguments.keywords.len()));
+                code.instructions.push(Op::CallKw(argc));
 
                 Ok(())
             }
+            ast::Expr::Yield(_) | ast::Expr::YieldFrom(_) if !self.in_function => {
+                Err("SyntaxError: 'yield' outside function".to_string())
+            }
             _ => Err("unsupported expression".to_string()),
         }
     }
@@ -6,10 +6,32 @@ pub struct CodeObject {
     pub consts: Vec<PyObject>,
     pub names: Vec<String>,
     pub instructions: Vec<Op>,
+    pub lines: Vec<usize>,
     pub nested: Vec<CodeObject>,
+    // a function's docstring, exposed as `__doc__`
+    pub doc: Option<String>,
 }
 
 impl CodeObject {
+    /// `names[idx]`, or an error for bytecode indexing past the table.
+    pub fn name(&self, idx: usize) -> Result<&String, String> {
+        self.names
+            .get(idx)
+            .ok_or_else(|| format!("SystemError: bad name index {}", idx))
+    }
+
+    pub fn constant(&self, idx: usize) -> Result<&PyObject, String> {
+        self.consts
+            .get(idx)
+            .ok_or_else(|| format!("SystemError: bad constant index {}", idx))
+    }
+
+    pub fn nested_code(&self, idx: usize) -> Result<&CodeObject, String> {
+        self.nested
+            .get(idx)
+            .ok_or_else(|| format!("SystemError: bad code index {}", idx))
+    }
+
     pub fn debug_print(&self) {
         println!("Constants: {:?}", self.consts);
         println!("Names: {:?}", self.names);
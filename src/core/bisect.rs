@@ -0,0 +1,95 @@
+use crate::core::heapq::less;
+use crate::object::PyObject;
+use std::collections::HashMap;
+
+/// Where `x` goes in the sorted list `args[0]`: before equal items for
+/// `bisect_left`, after them otherwise. Takes optional `lo` and `hi` bounds.
+fn bisect(name: &str, args: &[PyObject], left: bool) -> Result<usize, String> {
+    let (args, kwargs) = PyObject::split_kwargs(args);
+    let (list, x, rest) = match args {
+        [list, x, rest @ ..] if rest.len() <= 2 => (list, x, rest),
+        _ => {
+            return Err(format!(
+                "TypeError: {}() takes from 2 to 4 positional arguments but {} were given",
+                name,
+                args.len()
+            ));
+        }
+    };
+    let PyObject::List(list) = list else {
+        return Err(format!(
+            "TypeError: {}() argument 1 must be list, not {}",
+            name,
+            list.type_name()
+        ));
+    };
+    let items = list.borrow();
+
+    let bound = |pos: usize, key: &str, default: usize| -> Result<usize, String> {
+        match rest.get(pos).or_else(|| kwargs.and_then(|kw| kw.get(key))) {
+            None | Some(PyObject::None) => Ok(default),
+            Some(PyObject::Int(n)) if *n < 0 => {
+                Err(format!("ValueError: {} must be non-negative", key))
+            }
+            Some(PyObject::Int(n)) => Ok((*n as usize).min(items.len())),
+            Some(other) => Err(format!(
+                "TypeError: '{}' object cannot be interpreted as an integer",
+                other.type_name()
+            )),
+        }
+    };
+    let (mut lo, mut hi) = (bound(0, "lo", 0)?, bound(1, "hi", items.len())?);
+
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let before = if left {
+            less(&items[mid], x)?
+        } else {
+            !less(x, &items[mid])?
+        };
+        if before {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(lo)
+}
+
+fn insort(name: &str, args: &[PyObject], left: bool) -> Result<PyObject, String> {
+    let pos = bisect(name, args, left)?;
+    if let [PyObject::List(list), x, ..] = args {
+        list.borrow_mut().insert(pos, x.clone());
+    }
+    Ok(PyObject::None)
+}
+
+pub fn bisect_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    for (name, left) in [
+        ("bisect_left", true),
+        ("bisect_right", false),
+        ("bisect", false),
+    ] {
+        m.insert(
+            name.to_string(),
+            PyObject::native_fn(name, usize::MAX, move |args| {
+                Ok(PyObject::Int(bisect(name, args, left)? as i64))
+            }),
+        );
+    }
+
+    for (name, left) in [
+        ("insort_left", true),
+        ("insort_right", false),
+        ("insort", false),
+    ] {
+        m.insert(
+            name.to_string(),
+            PyObject::native_fn(name, usize::MAX, move |args| insort(name, args, left)),
+        );
+    }
+
+    m
+}
@@ -0,0 +1,141 @@
+use std::rc::Rc;
+
+use crate::object::{PyNativeFunction, PyObject};
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Utf8,
+    Ascii,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Ascii => "ascii",
+        }
+    }
+}
+
+/// The `encoding` argument of `str.encode`/`bytes.decode`, utf-8 if omitted.
+fn encoding_arg(method: &str, args: &[PyObject]) -> Result<Encoding, String> {
+    let (args, kwargs) = PyObject::split_kwargs(args);
+
+    let encoding = match (args, kwargs.and_then(|kw| kw.get("encoding"))) {
+        ([], None) => return Ok(Encoding::Utf8),
+        ([encoding], None) | ([], Some(encoding)) => encoding,
+        _ => {
+            return Err(format!(
+                "TypeError: {}() takes at most 1 argument ({} given)",
+                method,
+                args.len() + kwargs.map_or(0, |kw| kw.len())
+            ));
+        }
+    };
+
+    let PyObject::Str(encoding) = encoding else {
+        return Err(format!(
+            "TypeError: {}() argument 'encoding' must be str, not {}",
+            method,
+            encoding.type_name()
+        ));
+    };
+
+    match encoding.to_lowercase().replace('_', "-").as_str() {
+        "utf-8" | "utf8" => Ok(Encoding::Utf8),
+        "ascii" | "us-ascii" => Ok(Encoding::Ascii),
+        _ => Err(format!("LookupError: unknown encoding: {}", encoding)),
+    }
+}
+
+/// `str.encode`.
+pub(crate) fn encode(s: &str, args: &[PyObject]) -> Result<PyObject, String> {
+    let encoding = encoding_arg("encode", args)?;
+
+    let non_ascii = s.chars().enumerate().find(|(_, c)| !c.is_ascii());
+    if let (Encoding::Ascii, Some((pos, c))) = (encoding, non_ascii) {
+        let escaped = match c as u32 {
+            n @ 0..=0xff => format!("\\x{:02x}", n),
+            n @ 0x100..=0xffff => format!("\\u{:04x}", n),
+            n => format!("\\U{:08x}", n),
+        };
+        return Err(format!(
+            "UnicodeEncodeError: 'ascii' codec can't encode character '{}' in position {}: ordinal not in range(128)",
+            escaped, pos
+        ));
+    }
+
+    Ok(PyObject::Bytes(s.as_bytes().to_vec()))
+}
+
+fn decode(b: &[u8], args: &[PyObject]) -> Result<PyObject, String> {
+    let encoding = encoding_arg("decode", args)?;
+
+    let bad = |pos: usize, reason: &str| {
+        format!(
+            "UnicodeDecodeError: '{}' codec can't decode byte 0x{:02x} in position {}: {}",
+            encoding.name(),
+            b[pos],
+            pos,
+            reason
+        )
+    };
+
+    match encoding {
+        Encoding::Ascii => match b.iter().position(|c| !c.is_ascii()) {
+            Some(pos) => Err(bad(pos, "ordinal not in range(128)")),
+            None => Ok(PyObject::Str(b.iter().map(|&c| c as char).collect())),
+        },
+        Encoding::Utf8 => match std::str::from_utf8(b) {
+            Ok(s) => Ok(PyObject::Str(s.to_string())),
+            Err(e) => Err(bad(e.valid_up_to(), "invalid utf-8")),
+        },
+    }
+}
+
+/// Looks up `name` on a `bytes` value, returning the method bound to `b`.
+pub(crate) fn method(b: &[u8], name: &str) -> Option<PyObject> {
+    let b = b.to_vec();
+
+    let func: Rc<dyn Fn(&[PyObject]) -> Result<PyObject, String>> = match name {
+        "decode" => Rc::new(move |args| decode(&b, args)),
+        _ => return None,
+    };
+
+    Some(PyObject::NativeFunction(Rc::new(PyNativeFunction {
+        name: format!("bytes.{}", name),
+        arity: usize::MAX,
+        func,
+    })))
+}
+
+/// Python's `repr` of a bytes value, e.g. `b'caf\xc3\xa9'`.
+pub(crate) fn repr(b: &[u8]) -> String {
+    let quote = if b.contains(&b'\'') && !b.contains(&b'"') {
+        '"'
+    } else {
+        '\''
+    };
+
+    let mut out = String::with_capacity(b.len() + 3);
+    out.push('b');
+    out.push(quote);
+
+    for &c in b {
+        match c {
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            c if c as char == quote => {
+                out.push('\\');
+                out.push(quote);
+            }
+            0x20..0x7f => out.push(c as char),
+            c => out.push_str(&format!("\\x{:02x}", c)),
+        }
+    }
+
+    out.push(quote);
+    out
+}
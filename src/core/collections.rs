@@ -0,0 +1,91 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use crate::core::globs::iterable_items;
+use crate::object::{PyNativeFunction, PyObject};
+
+type Deque = Rc<RefCell<VecDeque<PyObject>>>;
+
+/// Looks up `name` on a `deque` value, returning the method bound to `d`.
+pub(crate) fn method(d: &Deque, name: &str) -> Option<PyObject> {
+    let d = d.clone();
+
+    let (arity, func): (usize, Rc<dyn Fn(&[PyObject]) -> Result<PyObject, String>>) = match name {
+        "append" => (
+            1,
+            Rc::new(move |args| {
+                d.borrow_mut().push_back(args[0].clone());
+                Ok(PyObject::None)
+            }),
+        ),
+        "appendleft" => (
+            1,
+            Rc::new(move |args| {
+                d.borrow_mut().push_front(args[0].clone());
+                Ok(PyObject::None)
+            }),
+        ),
+        "pop" => (
+            0,
+            Rc::new(move |_| {
+                d.borrow_mut()
+                    .pop_back()
+                    .ok_or_else(|| "IndexError: pop from an empty deque".to_string())
+            }),
+        ),
+        "popleft" => (
+            0,
+            Rc::new(move |_| {
+                d.borrow_mut()
+                    .pop_front()
+                    .ok_or_else(|| "IndexError: pop from an empty deque".to_string())
+            }),
+        ),
+        "extend" => (
+            1,
+            Rc::new(move |args| {
+                let items = iterable_items(&args[0])?;
+                d.borrow_mut().extend(items);
+                Ok(PyObject::None)
+            }),
+        ),
+        "clear" => (
+            0,
+            Rc::new(move |_| {
+                d.borrow_mut().clear();
+                Ok(PyObject::None)
+            }),
+        ),
+        _ => return None,
+    };
+
+    Some(PyObject::NativeFunction(Rc::new(PyNativeFunction {
+        name: format!("deque.{}", name),
+        arity,
+        func,
+    })))
+}
+
+pub fn collections_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "deque".to_string(),
+        PyObject::native_fn("deque", usize::MAX, |args| {
+            let items = match args {
+                [] => VecDeque::new(),
+                [iterable] => iterable_items(iterable)?.into(),
+                _ => {
+                    return Err(format!(
+                        "TypeError: deque expected at most 1 argument, got {}",
+                        args.len()
+                    ));
+                }
+            };
+            Ok(PyObject::Deque(Rc::new(RefCell::new(items))))
+        }),
+    );
+
+    m
+}
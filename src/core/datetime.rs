@@ -0,0 +1,97 @@
+use crate::object::{PyClass, PyInstance, PyNativeFunction, PyObject};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian (year, month, day).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // shift the epoch to 0000-03-01 so leap days fall at the end of a year
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// A UTC `datetime` instance for `ts` seconds since the epoch.
+fn datetime(class: &Rc<PyClass>, ts: f64) -> PyObject {
+    let secs = ts.floor() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+
+    let mut attrs = HashMap::new();
+    for (name, value) in [
+        ("year", year),
+        ("month", month),
+        ("day", day),
+        ("hour", time / 3600),
+        ("minute", time / 60 % 60),
+        ("second", time % 60),
+    ] {
+        attrs.insert(name.to_string(), PyObject::Int(value));
+    }
+    attrs.insert(
+        "timestamp".to_string(),
+        PyObject::native_fn("timestamp", 0, move |_| Ok(PyObject::Float(ts))),
+    );
+
+    PyObject::Instance(Rc::new(RefCell::new(PyInstance {
+        class: class.clone(),
+        attrs,
+    })))
+}
+
+pub fn datetime_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    let class = Rc::new(PyClass {
+        name: "datetime".to_string(),
+        methods: RefCell::new(HashMap::new()),
+        bases: Vec::new(),
+    });
+
+    m.insert(
+        "now".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "now".to_string(),
+            arity: 0,
+            func: {
+                let class = class.clone();
+                Rc::new(move |_| {
+                    let ts = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs_f64();
+                    Ok(datetime(&class, ts))
+                })
+            },
+        })),
+    );
+
+    m.insert(
+        "fromtimestamp".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "fromtimestamp".to_string(),
+            arity: 1,
+            func: Rc::new(move |args| match &args[0] {
+                PyObject::Int(n) => Ok(datetime(&class, *n as f64)),
+                PyObject::Float(f) if f.is_finite() => Ok(datetime(&class, *f)),
+                PyObject::Float(_) => {
+                    Err("ValueError: timestamp out of range for platform".to_string())
+                }
+                other => Err(format!(
+                    "TypeError: 'float' object is required (got type {})",
+                    other.type_name()
+                )),
+            }),
+        })),
+    );
+
+    m
+}
@@ -0,0 +1,233 @@
+use crate::{PyNativeFunction, PyObject};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+// significant digits kept by inexact divisions, as in Python's default context
+const PRECISION: u32 = 28;
+
+/// An exact decimal number, `mantissa * 10^-scale`.
+#[derive(Clone, Copy, Debug)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let s = s.trim();
+        let (s, exp) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i32>().ok()?),
+            None => (s, 0),
+        };
+        let (negative, s) = match s.as_bytes().first()? {
+            b'-' => (true, &s[1..]),
+            b'+' => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+
+        let digits = || int.chars().chain(frac.chars());
+        if int.is_empty() && frac.is_empty() || !digits().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for c in digits() {
+            mantissa = mantissa
+                .checked_mul(10)?
+                .checked_add(c.to_digit(10)? as i128)?;
+        }
+
+        let mut value = Decimal {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale: 0,
+        };
+
+        let scale = frac.len() as i64 - exp as i64;
+        if scale >= 0 {
+            value.scale = u32::try_from(scale).ok()?;
+        } else {
+            value.mantissa = value
+                .mantissa
+                .checked_mul(pow10(u32::try_from(-scale).ok()?)?)?;
+        }
+
+        Some(value)
+    }
+
+    pub fn from_int(n: i64) -> Decimal {
+        Decimal {
+            mantissa: n as i128,
+            scale: 0,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// Brings both operands to the larger scale.
+    fn align(self, other: Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        let a = self.mantissa.checked_mul(pow10(scale - self.scale)?)?;
+        let b = other.mantissa.checked_mul(pow10(scale - other.scale)?)?;
+        Some((a, b, scale))
+    }
+
+    pub fn neg(self) -> Decimal {
+        Decimal {
+            mantissa: -self.mantissa,
+            ..self
+        }
+    }
+
+    pub fn add(self, other: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Decimal {
+            mantissa: a.checked_add(b)?,
+            scale,
+        })
+    }
+
+    pub fn sub(self, other: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Decimal {
+            mantissa: a.checked_sub(b)?,
+            scale,
+        })
+    }
+
+    pub fn mul(self, other: Decimal) -> Option<Decimal> {
+        Some(Decimal {
+            mantissa: self.mantissa.checked_mul(other.mantissa)?,
+            scale: self.scale.checked_add(other.scale)?,
+        })
+    }
+
+    /// Long division, stopping once the quotient is exact or has
+    /// `PRECISION` significant digits. The divisor must be non-zero.
+    pub fn div(self, other: Decimal) -> Option<Decimal> {
+        let divisor = other.mantissa.unsigned_abs();
+        let mut quotient = self.mantissa.unsigned_abs() / divisor;
+        let mut rem = self.mantissa.unsigned_abs() % divisor;
+        let mut scale = self.scale as i64 - other.scale as i64;
+
+        while rem != 0 && quotient.checked_ilog10().map_or(0, |d| d + 1) < PRECISION {
+            let Some(next) = rem.checked_mul(10) else {
+                break;
+            };
+            quotient = quotient.checked_mul(10)?.checked_add(next / divisor)?;
+            rem = next % divisor;
+            scale += 1;
+        }
+
+        if scale < 0 {
+            quotient = quotient.checked_mul(pow10(u32::try_from(-scale).ok()?)? as u128)?;
+            scale = 0;
+        }
+
+        let mantissa = i128::try_from(quotient).ok()?;
+        let negative = (self.mantissa < 0) != (other.mantissa < 0);
+        Some(Decimal {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale: u32::try_from(scale).ok()?,
+        })
+    }
+
+    /// Drops trailing fractional zeros, so equal values compare and hash alike.
+    fn normalized(self) -> Decimal {
+        let mut d = self;
+        while d.scale > 0 && d.mantissa % 10 == 0 {
+            d.mantissa /= 10;
+            d.scale -= 1;
+        }
+        d
+    }
+}
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let (a, b) = (self.normalized(), other.normalized());
+        match a.align(b) {
+            Some((a, b, _)) => Some(a.cmp(&b)),
+            // too far apart to align exactly, so an approximation is decisive
+            None => a.to_f64().partial_cmp(&b.to_f64()),
+        }
+    }
+}
+
+impl std::hash::Hash for Decimal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let d = self.normalized();
+        // integral values hash like the int they equal
+        match i64::try_from(d.mantissa) {
+            Ok(n) if d.scale == 0 => n.hash(state),
+            _ => (d.mantissa, d.scale).hash(state),
+        }
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int, frac) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+pub fn decimal_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "Decimal".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "Decimal".to_string(),
+            arity: usize::MAX,
+            func: Rc::new(|args| match args {
+                [] => Ok(PyObject::Decimal(Decimal::from_int(0))),
+                [PyObject::Int(n)] => Ok(PyObject::Decimal(Decimal::from_int(*n))),
+                [PyObject::Decimal(d)] => Ok(PyObject::Decimal(*d)),
+                [PyObject::Str(s)] => Decimal::parse(s).map(PyObject::Decimal).ok_or_else(|| {
+                    format!(
+                        "ValueError: invalid literal for Decimal: {}",
+                        PyObject::Str(s.clone()).repr()
+                    )
+                }),
+                [other] => Err(format!(
+                    "TypeError: conversion from {} to Decimal is not supported",
+                    other.type_name()
+                )),
+                _ => Err(format!(
+                    "TypeError: Decimal() takes at most 1 argument ({} given)",
+                    args.len()
+                )),
+            }),
+        })),
+    );
+
+    m
+}
@@ -0,0 +1,71 @@
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::object::{PyNativeFunction, PyObject};
+use crate::vm::check_hashable;
+
+type Dict = Rc<RefCell<IndexMap<PyObject, PyObject>>>;
+
+/// Looks up `name` on a `dict` value, returning the method bound to `d`.
+/// Views are returned as lists, in insertion order.
+pub(crate) fn method(d: &Dict, name: &str) -> Option<PyObject> {
+    let d = d.clone();
+
+    let func: Rc<dyn Fn(&[PyObject]) -> Result<PyObject, String>> = match name {
+        "keys" => Rc::new(move |args| {
+            no_args("keys", args)?;
+            Ok(list(d.borrow().keys().cloned().collect()))
+        }),
+        "values" => Rc::new(move |args| {
+            no_args("values", args)?;
+            Ok(list(d.borrow().values().cloned().collect()))
+        }),
+        "items" => Rc::new(move |args| {
+            no_args("items", args)?;
+            let items = d
+                .borrow()
+                .iter()
+                .map(|(k, v)| PyObject::Tuple(vec![k.clone(), v.clone()]))
+                .collect();
+            Ok(list(items))
+        }),
+        "get" => Rc::new(move |args| {
+            let (key, default) = match args {
+                [key] => (key, PyObject::None),
+                [key, default] => (key, default.clone()),
+                _ => {
+                    return Err(format!(
+                        "TypeError: get expected at most 2 arguments, got {}",
+                        args.len()
+                    ));
+                }
+            };
+            check_hashable(key)?;
+            Ok(d.borrow().get(key).cloned().unwrap_or(default))
+        }),
+        _ => return None,
+    };
+
+    Some(PyObject::NativeFunction(Rc::new(PyNativeFunction {
+        name: format!("dict.{}", name),
+        arity: usize::MAX,
+        func,
+    })))
+}
+
+fn no_args(method: &str, args: &[PyObject]) -> Result<(), String> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "TypeError: dict.{}() takes no arguments ({} given)",
+            method,
+            args.len()
+        ))
+    }
+}
+
+fn list(items: Vec<PyObject>) -> PyObject {
+    PyObject::List(Rc::new(RefCell::new(items)))
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::object::{PyException, PyNativeFunction, PyObject};
+
+const EXCEPTIONS: &[(&str, &str)] = &[
+    ("BaseException", ""),
+    ("SystemExit", "BaseException"),
+    ("KeyboardInterrupt", "BaseException"),
+    ("Exception", "BaseException"),
+    ("ArithmeticError", "Exception"),
+    ("ZeroDivisionError", "ArithmeticError"),
+    ("OverflowError", "ArithmeticError"),
+    ("LookupError", "Exception"),
+    ("KeyError", "LookupError"),
+    ("IndexError", "LookupError"),
+    ("AssertionError", "Exception"),
+    ("AttributeError", "Exception"),
+    ("ImportError", "Exception"),
+    ("ModuleNotFoundError", "ImportError"),
+    ("NameError", "Exception"),
+    ("RuntimeError", "Exception"),
+    ("NotImplementedError", "RuntimeError"),
+    ("RecursionError", "RuntimeError"),
+    ("StopIteration", "Exception"),
+    ("SyntaxError", "Exception"),
+    ("TypeError", "Exception"),
+    ("ValueError", "Exception"),
+];
+
+pub fn apply(builtins: &mut HashMap<String, PyObject>) {
+    for (kind, _) in EXCEPTIONS {
+        builtins.insert(
+            kind.to_string(),
+            PyObject::NativeFunction(Rc::new(PyNativeFunction {
+                name: kind.to_string(),
+                arity: usize::MAX,
+                func: Rc::new(move |args| {
+                    let message = match args {
+                        [] => String::new(),
+                        [arg] => arg.to_string(),
+                        _ => PyObject::Tuple(args.to_vec()).to_string(),
+                    };
+                    Ok(new_exception(kind, message))
+                }),
+            })),
+        );
+    }
+}
+
+pub(crate) fn new_exception(kind: &str, message: impl Into<String>) -> PyObject {
+    PyObject::Exception(Rc::new(PyException {
+        kind: kind.to_string(),
+        message: message.into(),
+    }))
+}
+
+/// Turns an error string produced by the VM or a native back into an
+/// exception object. Errors without a `Kind: ` prefix become `RuntimeError`.
+pub(crate) fn from_error(err: &str) -> PyObject {
+    let is_kind = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric());
+
+    match err.split_once(':') {
+        Some((kind, message)) if is_kind(kind) => new_exception(kind, message.trim_start()),
+        _ if is_kind(err) => new_exception(err, ""),
+        _ => new_exception("RuntimeError", err),
+    }
+}
+
+/// Whether `name` is one of the builtin exception types.
+pub(crate) fn is_exception_kind(name: &str) -> bool {
+    EXCEPTIONS.iter().any(|(k, _)| *k == name)
+}
+
+/// Whether an exception of type `kind` is caught by an `except name:` clause.
+pub(crate) fn matches(kind: &str, name: &str) -> bool {
+    let mut cur = kind;
+
+    loop {
+        if cur == name {
+            return true;
+        }
+
+        match EXCEPTIONS.iter().find(|(k, _)| *k == cur) {
+            Some((_, base)) if !base.is_empty() => cur = base,
+            // unknown kinds (e.g. from natives) behave as plain exceptions
+            None if cur != "Exception" => cur = "Exception",
+            _ => return false,
+        }
+    }
+}
+
+pub(crate) fn to_error(exc: &PyException) -> String {
+    if exc.message.is_empty() {
+        exc.kind.clone()
+    } else {
+        format!("{}: {}", exc.kind, exc.message)
+    }
+}
@@ -0,0 +1,18 @@
+from _functools import lru_cache
+
+
+class _Missing:
+    pass
+
+
+def reduce(function, iterable, initial=_Missing):
+    it = iter(iterable)
+    if initial == _Missing:
+        value = next(it, _Missing)
+        if value == _Missing:
+            raise TypeError("reduce() of empty iterable with no initial value")
+    else:
+        value = initial
+    for element in it:
+        value = function(value, element)
+    return value
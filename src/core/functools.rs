@@ -0,0 +1,100 @@
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::object::PyObject;
+use crate::vm::check_hashable;
+
+const DEFAULT_MAXSIZE: usize = 128;
+
+/// Wraps `func` so results are memoized by argument, keeping at most `maxsize`
+/// of the most recently used ones (`None` for no limit).
+fn cached(func: PyObject, maxsize: Option<usize>) -> PyObject {
+    let cache: Rc<RefCell<IndexMap<PyObject, PyObject>>> = Rc::default();
+    let name = match &func {
+        PyObject::Function(f) => f.name.clone(),
+        _ => "lru_cache".to_string(),
+    };
+
+    PyObject::vm_fn(&name, usize::MAX, move |vm, args| {
+        let (positional, kwargs) = PyObject::split_kwargs(args);
+        positional
+            .iter()
+            .chain(kwargs.into_iter().flat_map(|kw| kw.values()))
+            .try_for_each(check_hashable)?;
+
+        let mut key = positional.to_vec();
+        if let Some(kw) = kwargs {
+            // keyword arguments follow a marker, so `f(1, a=2)` and
+            // `f(1, ('a', 2))` are cached apart
+            key.push(PyObject::Ellipsis);
+            key.extend(
+                kw.iter()
+                    .map(|(k, v)| PyObject::Tuple(vec![PyObject::Str(k.clone()), v.clone()])),
+            );
+        }
+        let key = PyObject::Tuple(key);
+
+        let hit = cache.borrow_mut().shift_remove(&key);
+        if let Some(hit) = hit {
+            cache.borrow_mut().insert(key, hit.clone());
+            return Ok(hit);
+        }
+
+        // the cache isn't borrowed while `func` runs, since it may recurse
+        let result = vm.call_value(func.clone(), args.to_vec())?;
+
+        let mut cache = cache.borrow_mut();
+        cache.insert(key, result.clone());
+        if maxsize.is_some_and(|max| cache.len() > max) {
+            cache.shift_remove_index(0);
+        }
+        Ok(result)
+    })
+}
+
+fn maxsize_arg(v: &PyObject) -> Result<Option<usize>, String> {
+    match v {
+        PyObject::None => Ok(None),
+        PyObject::Int(n) => Ok(Some((*n).max(0) as usize)),
+        _ => Err(
+            "TypeError: Expected first argument to be an integer, a callable, or None".to_string(),
+        ),
+    }
+}
+
+/// Natives behind the `functools` source module.
+pub fn functools_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    // `@lru_cache` wraps the function directly, `@lru_cache(maxsize=...)`
+    // returns the decorator
+    m.insert(
+        "lru_cache".to_string(),
+        PyObject::native_fn("lru_cache", usize::MAX, |args| {
+            let (args, kwargs) = PyObject::split_kwargs(args);
+            let maxsize = match (args, kwargs.and_then(|kw| kw.get("maxsize"))) {
+                (
+                    [
+                        func @ (PyObject::Function(_)
+                        | PyObject::NativeFunction(_)
+                        | PyObject::VmFunction(_)),
+                    ],
+                    None,
+                ) => return Ok(cached(func.clone(), Some(DEFAULT_MAXSIZE))),
+                ([], None) => Some(DEFAULT_MAXSIZE),
+                ([maxsize], None) | ([], Some(maxsize)) => maxsize_arg(maxsize)?,
+                _ => {
+                    return Err("TypeError: lru_cache() takes at most 1 argument".to_string());
+                }
+            };
+
+            Ok(PyObject::native_fn("lru_cache", 1, move |args| {
+                Ok(cached(args[0].clone(), maxsize))
+            }))
+        }),
+    );
+
+    m
+}
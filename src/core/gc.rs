@@ -0,0 +1,27 @@
+use crate::object::PyObject;
+use std::collections::HashMap;
+
+pub fn gc_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "collect".to_string(),
+        PyObject::vm_fn("collect", 0, |vm, _| {
+            Ok(PyObject::Int(vm.collect_cycles() as i64))
+        }),
+    );
+
+    // there is a single generation, so the younger counts are always 0
+    m.insert(
+        "get_count".to_string(),
+        PyObject::vm_fn("get_count", 0, |vm, _| {
+            Ok(PyObject::Tuple(vec![
+                PyObject::Int(vm.tracked_count() as i64),
+                PyObject::Int(0),
+                PyObject::Int(0),
+            ]))
+        }),
+    );
+
+    m
+}
@@ -1,44 +1,724 @@
-use std::collections::HashSet;
+use indexmap::{IndexMap, IndexSet};
 use std::rc::Rc;
 use std::{cell::RefCell, collections::HashMap};
 
-use crate::object::{PyNativeFunction, PyObject, PyType};
+use crate::object::{PyIterator, PyNativeFunction, PyObject, PyType};
+use crate::vm::{Vm, arith_add, check_hashable, cmp_gt, cmp_lt, is_falsey};
+
+/// The items of an iterable argument.
+pub(crate) fn iterable_items(v: &PyObject) -> Result<Vec<PyObject>, String> {
+    match v {
+        PyObject::Iterator(it) => Ok(it.borrow_mut().by_ref().collect()),
+        PyObject::List(_)
+        | PyObject::Tuple(_)
+        | PyObject::Str(_)
+        | PyObject::Bytes(_)
+        | PyObject::Deque(_)
+        | PyObject::Set(_)
+        | PyObject::Dict(_)
+        | PyObject::Range(..) => Ok(PyIterator::new(v.clone()).collect()),
+        other => Err(format!(
+            "TypeError: '{}' object is not iterable",
+            other.type_name()
+        )),
+    }
+}
+
+/// Calls the `name` method of an instance with `args`, if its class defines
+/// one, so builtins like `abs` work on user types.
+fn call_dunder(
+    vm: &mut Vm,
+    obj: &PyObject,
+    name: &str,
+    args: &[PyObject],
+) -> Option<Result<PyObject, String>> {
+    let PyObject::Instance(inst) = obj else {
+        return None;
+    };
+    let method = inst.borrow().class.methods.borrow().get(name).cloned()?;
+
+    let mut full_args = Vec::with_capacity(args.len() + 1);
+    full_args.push(obj.clone());
+    full_args.extend_from_slice(args);
+    Some(vm.call_value(method, full_args))
+}
+
+/// `round(n, ndigits)` for an int: unchanged unless `ndigits` is negative,
+/// with ties going to the even multiple.
+fn round_int(n: i64, ndigits: i64) -> Result<i64, String> {
+    if ndigits >= 0 {
+        return Ok(n);
+    }
+    // any i64 rounds to 0 at 10**20 and beyond, and i128 holds that unit
+    let unit = 10i128.pow(ndigits.unsigned_abs().min(20) as u32);
+
+    let n = n as i128;
+    let (q, r) = (n.div_euclid(unit), n.rem_euclid(unit));
+    let q = match (2 * r).cmp(&unit) {
+        std::cmp::Ordering::Greater => q + 1,
+        std::cmp::Ordering::Equal if q % 2 != 0 => q + 1,
+        _ => q,
+    };
+    i64::try_from(q * unit).map_err(|_| "OverflowError: integer overflow in round()".to_string())
+}
+
+/// `round(x, ndigits)` for a float, rounding the exact binary value like
+/// Python does, so `round(2.675, 2)` is `2.67`.
+fn round_float(x: f64, ndigits: i64) -> f64 {
+    if !x.is_finite() {
+        return x;
+    }
+    if ndigits >= 0 {
+        return format!("{:.*}", ndigits.min(340) as usize, x)
+            .parse()
+            .unwrap_or(x);
+    }
+    let unit = 10f64.powi(ndigits.max(-340) as i32);
+    (x * unit).round_ties_even() / unit
+}
+
+/// `pow(base, exp[, modulus])` for numbers.
+fn pow(base: &PyObject, exp: &PyObject, modulus: Option<&PyObject>) -> Result<PyObject, String> {
+    let as_float = |v: &PyObject| match v {
+        PyObject::Int(n) => Some(*n as f64),
+        PyObject::Bool(b) => Some(*b as i64 as f64),
+        PyObject::Float(f) => Some(*f),
+        _ => None,
+    };
+    let as_int = |v: &PyObject| match v {
+        PyObject::Int(n) => Some(*n),
+        PyObject::Bool(b) => Some(*b as i64),
+        _ => None,
+    };
+
+    if let Some(modulus) = modulus {
+        let (Some(b), Some(e), Some(m)) = (as_int(base), as_int(exp), as_int(modulus)) else {
+            return Err(
+                "TypeError: pow() 3rd argument not allowed unless all arguments are integers"
+                    .to_string(),
+            );
+        };
+        if m == 0 {
+            return Err("ValueError: pow() 3rd argument cannot be 0".to_string());
+        }
+        if e < 0 {
+            return Err("ValueError: base is not invertible for the given modulus".to_string());
+        }
+
+        // square-and-multiply, in i128 so products cannot overflow
+        let m = m as i128;
+        let (mut b, mut e, mut acc) = ((b as i128).rem_euclid(m), e, 1 % m);
+        while e > 0 {
+            if e & 1 == 1 {
+                acc = acc * b % m;
+            }
+            b = b * b % m;
+            e >>= 1;
+        }
+        // the result takes the sign of the modulus, as with `%`
+        let acc = if m < 0 && acc != 0 { acc + m } else { acc };
+        return Ok(PyObject::Int(acc as i64));
+    }
+
+    match (as_int(base), as_int(exp)) {
+        (Some(b), Some(e)) if e >= 0 => u32::try_from(e)
+            .ok()
+            .and_then(|e| b.checked_pow(e))
+            .map(PyObject::Int)
+            .ok_or_else(|| "OverflowError: integer overflow in pow()".to_string()),
+        (Some(0), Some(_)) => {
+            Err("ZeroDivisionError: 0.0 cannot be raised to a negative power".to_string())
+        }
+        _ => match (as_float(base), as_float(exp)) {
+            (Some(b), Some(e)) if b == 0.0 && e < 0.0 => {
+                Err("ZeroDivisionError: 0.0 cannot be raised to a negative power".to_string())
+            }
+            (Some(b), Some(e)) => Ok(PyObject::Float(b.powf(e))),
+            _ => Err(format!(
+                "TypeError: unsupported operand type(s) for ** or pow(): '{}' and '{}'",
+                base.type_name(),
+                exp.type_name()
+            )),
+        },
+    }
+}
+
+/// `sum(iterable, start)`. Float totals use Neumaier's compensated summation,
+/// as CPython does, so `sum([0.1, 0.2, 0.3])` is `0.6`.
+pub(crate) fn sum(items: Vec<PyObject>, start: PyObject) -> Result<PyObject, String> {
+    let mut total = start;
+    let mut compensation = 0.0;
+
+    for item in items {
+        let (t, x) = match (&total, &item) {
+            (PyObject::Float(t), PyObject::Float(x)) => (*t, *x),
+            (PyObject::Float(t), PyObject::Int(n)) => (*t, *n as f64),
+            _ => {
+                total = arith_add(total, item)?;
+                continue;
+            }
+        };
+
+        let s = t + x;
+        if s.is_finite() {
+            compensation += if t.abs() >= x.abs() {
+                (t - s) + x
+            } else {
+                (x - s) + t
+            };
+        }
+        total = PyObject::Float(s);
+    }
+
+    match total {
+        PyObject::Float(t) if t.is_finite() => Ok(PyObject::Float(t + compensation)),
+        total => Ok(total),
+    }
+}
+
+/// `min`/`max`: the first item whose key no other item's key `beats`. Takes
+/// either one iterable or several items, and the `key=` and `default=` keywords.
+fn extremum(
+    vm: &mut Vm,
+    name: &str,
+    args: &[PyObject],
+    beats: fn(PyObject, PyObject) -> Result<PyObject, String>,
+) -> Result<PyObject, String> {
+    let (args, kwargs) = PyObject::split_kwargs(args);
+
+    let (mut key, mut default) = (None, None);
+    for (k, v) in kwargs.into_iter().flatten() {
+        match k.as_str() {
+            "key" if *v == PyObject::None => {}
+            "key" => key = Some(v.clone()),
+            "default" => default = Some(v.clone()),
+            _ => {
+                return Err(format!(
+                    "TypeError: '{}' is an invalid keyword argument for {}()",
+                    k, name
+                ));
+            }
+        }
+    }
+
+    let candidates = match args {
+        [] => {
+            return Err(format!(
+                "TypeError: {} expected at least 1 argument, got 0",
+                name
+            ));
+        }
+        [iterable] => iterable_items(iterable)?,
+        _ if default.is_some() => {
+            return Err(format!(
+                "TypeError: Cannot specify a default for {}() with multiple positional arguments",
+                name
+            ));
+        }
+        _ => args.to_vec(),
+    };
+
+    let mut best: Option<(PyObject, PyObject)> = None;
+    for item in candidates {
+        let k = match &key {
+            Some(key) => vm.call_value(key.clone(), vec![item.clone()])?,
+            None => item.clone(),
+        };
+
+        match &best {
+            Some((best_key, _)) if beats(k.clone(), best_key.clone())? != PyObject::Bool(true) => {}
+            _ => best = Some((k, item)),
+        }
+    }
+
+    match best {
+        Some((_, item)) => Ok(item),
+        None => default.ok_or_else(|| format!("ValueError: {}() arg is an empty sequence", name)),
+    }
+}
+
+fn int_literal(s: &str, base: u32) -> Result<PyObject, String> {
+    let invalid = || {
+        format!(
+            "ValueError: invalid literal for int() with base {}: {}",
+            base,
+            PyObject::Str(s.to_string()).repr()
+        )
+    };
+
+    let digits = s.trim();
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(invalid());
+    }
+
+    i64::from_str_radix(&digits.replace('_', ""), base)
+        .map(PyObject::Int)
+        .map_err(|_| invalid())
+}
+
+pub(crate) fn float_to_int(f: f64) -> Result<PyObject, String> {
+    if f.is_nan() {
+        Err("ValueError: cannot convert float NaN to integer".to_string())
+    } else if f.is_infinite() {
+        Err("OverflowError: cannot convert float infinity to integer".to_string())
+    } else {
+        Ok(PyObject::Int(f.trunc() as i64))
+    }
+}
+
+fn to_int(v: &PyObject, base: Option<&PyObject>) -> Result<PyObject, String> {
+    match (v, base) {
+        (PyObject::Str(s), None) => int_literal(s, 10),
+        (PyObject::Str(s), Some(PyObject::Int(b @ 2..=36))) => int_literal(s, *b as u32),
+        (_, Some(b)) if !matches!(b, PyObject::Int(_)) => Err(format!(
+            "TypeError: '{}' object cannot be interpreted as an integer",
+            b.type_name()
+        )),
+        (PyObject::Str(_), Some(_)) => {
+            Err("ValueError: int() base must be >= 2 and <= 36".to_string())
+        }
+        (_, Some(_)) => {
+            Err("TypeError: int() can't convert non-string with explicit base".to_string())
+        }
+        (PyObject::Int(i), None) => Ok(PyObject::Int(*i)),
+        (PyObject::Bool(b), None) => Ok(PyObject::Int(*b as i64)),
+        (PyObject::Float(f), None) => float_to_int(*f),
+        (PyObject::Decimal(d), None) => float_to_int(d.to_f64()),
+        (other, None) => Err(format!(
+            "TypeError: int() argument must be a string, a bytes-like object or a real number, not '{}'",
+            other.type_name()
+        )),
+    }
+}
+
+fn to_float(v: &PyObject) -> Result<PyObject, String> {
+    match v {
+        PyObject::Float(f) => Ok(PyObject::Float(*f)),
+        PyObject::Int(i) => Ok(PyObject::Float(*i as f64)),
+        PyObject::Bool(b) => Ok(PyObject::Float(*b as i64 as f64)),
+        PyObject::Decimal(d) => Ok(PyObject::Float(d.to_f64())),
+        PyObject::Str(s) => {
+            let t = s.trim().to_lowercase();
+            let parsed = match t.trim_start_matches(['+', '-']) {
+                "inf" | "infinity" | "nan" => t.parse::<f64>().ok(),
+                // Rust also accepts spellings like "infinite" that Python rejects
+                n if n.chars().all(|c| c.is_ascii_digit() || "._e+-".contains(c)) => {
+                    t.replace('_', "").parse::<f64>().ok()
+                }
+                _ => None,
+            };
+            parsed.map(PyObject::Float).ok_or_else(|| {
+                format!(
+                    "ValueError: could not convert string to float: {}",
+                    v.repr()
+                )
+            })
+        }
+        other => Err(format!(
+            "TypeError: float() argument must be a string or a real number, not '{}'",
+            other.type_name()
+        )),
+    }
+}
+
+fn to_dict(
+    v: Option<&PyObject>,
+    kwargs: Option<&IndexMap<String, PyObject>>,
+) -> Result<PyObject, String> {
+    let mut dict = IndexMap::new();
+
+    match v {
+        None => {}
+        Some(PyObject::Dict(d)) => dict = d.borrow().clone(),
+        Some(v) => {
+            for (i, item) in iterable_items(v)?.into_iter().enumerate() {
+                let pair = iterable_items(&item).map_err(|_| {
+                    format!(
+                        "TypeError: cannot convert dictionary update sequence element #{} to a sequence",
+                        i
+                    )
+                })?;
+                let [key, value] = <[PyObject; 2]>::try_from(pair).map_err(|pair| {
+                    format!(
+                        "ValueError: dictionary update sequence element #{} has length {}; 2 is required",
+                        i,
+                        pair.len()
+                    )
+                })?;
+                check_hashable(&key)?;
+                dict.insert(key, value);
+            }
+        }
+    }
+
+    for (k, v) in kwargs.into_iter().flatten() {
+        dict.insert(PyObject::Str(k.clone()), v.clone());
+    }
+    Ok(PyObject::Dict(Rc::new(RefCell::new(dict))))
+}
+
+/// Calls the builtin type `name` as a conversion, as both the `int`, `str`,
+/// ... builtins and their type objects do.
+pub(crate) fn construct(name: &str, args: &[PyObject]) -> Result<PyObject, String> {
+    let (args, kwargs) = PyObject::split_kwargs(args);
+    if kwargs.is_some() && name != "dict" {
+        return Err(format!("TypeError: {}() takes no keyword arguments", name));
+    }
+
+    match (name, args) {
+        ("int", []) => Ok(PyObject::Int(0)),
+        ("int", [v]) => to_int(v, None),
+        ("int", [v, base]) => to_int(v, Some(base)),
+        ("float", []) => Ok(PyObject::Float(0.0)),
+        ("float", [v]) => to_float(v),
+        ("bool", []) => Ok(PyObject::Bool(false)),
+        ("bool", [v]) => Ok(PyObject::Bool(!is_falsey(v))),
+        ("str", []) => Ok(PyObject::Str(String::new())),
+        ("str", [v]) => Ok(PyObject::Str(v.to_string())),
+        ("list", []) => Ok(PyObject::List(Rc::new(RefCell::new(Vec::new())))),
+        ("list", [v]) => Ok(PyObject::List(Rc::new(RefCell::new(iterable_items(v)?)))),
+        ("tuple", []) => Ok(PyObject::Tuple(Vec::new())),
+        ("tuple", [v]) => Ok(PyObject::Tuple(iterable_items(v)?)),
+        ("set", []) => Ok(PyObject::Set(Rc::new(RefCell::new(IndexSet::new())))),
+        ("set", [v]) => {
+            let items = iterable_items(v)?;
+            items.iter().try_for_each(check_hashable)?;
+            Ok(PyObject::Set(Rc::new(RefCell::new(
+                items.into_iter().collect(),
+            ))))
+        }
+        ("dict", []) => to_dict(None, kwargs),
+        ("dict", [v]) => to_dict(Some(v), kwargs),
+        ("int", _) => Err(format!(
+            "TypeError: int() takes at most 2 arguments ({} given)",
+            args.len()
+        )),
+        ("float" | "bool" | "str" | "list" | "tuple" | "set" | "dict", _) => Err(format!(
+            "TypeError: {} expected at most 1 argument, got {}",
+            name,
+            args.len()
+        )),
+        _ => Err(format!("TypeError: cannot create '{}' instances", name)),
+    }
+}
 
 pub fn apply(builtins: &mut HashMap<String, PyObject>) {
+    for name in [
+        "int", "float", "bool", "str", "list", "tuple", "set", "dict",
+    ] {
+        builtins.insert(
+            name.to_string(),
+            PyObject::native_fn(name, usize::MAX, move |args| construct(name, args)),
+        );
+    }
+
     builtins.insert(
-        "set".to_string(), /*@todo: class*/
+        "print".to_string(),
         PyObject::NativeFunction(Rc::new(PyNativeFunction {
-            name: "set".to_string(),
-            arity: 0,
-            func: Rc::new(|_| Ok(PyObject::Set(Rc::new(RefCell::new(HashSet::new()))))),
+            name: "print".to_string(),
+            arity: usize::MAX,
+            func: Rc::new(crate::core::io::print),
         })),
     );
 
     builtins.insert(
-        "print".to_string(),
+        "iter".to_string(),
         PyObject::NativeFunction(Rc::new(PyNativeFunction {
-            name: "print".to_string(),
+            name: "iter".to_string(),
+            arity: 1,
+            func: Rc::new(|args| match &args[0] {
+                PyObject::Iterator(_) => Ok(args[0].clone()),
+                PyObject::List(_)
+                | PyObject::Tuple(_)
+                | PyObject::Str(_)
+                | PyObject::Bytes(_)
+                | PyObject::Deque(_)
+                | PyObject::Set(_)
+                | PyObject::Dict(_)
+                | PyObject::Range(..) => Ok(PyObject::Iterator(Rc::new(RefCell::new(
+                    PyIterator::new(args[0].clone()),
+                )))),
+                other => Err(format!(
+                    "TypeError: '{}' object is not iterable",
+                    other.type_name()
+                )),
+            }),
+        })),
+    );
+
+    builtins.insert(
+        "next".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "next".to_string(),
             arity: usize::MAX,
             func: Rc::new(|args| {
-                let mut fst = true;
-
-                for a in args {
-                    if !fst {
-                        print!(" ");
+                let (it, default) = match args {
+                    [it] => (it, None),
+                    [it, default] => (it, Some(default)),
+                    _ => {
+                        return Err(format!(
+                            "TypeError: next expected 1 or 2 arguments, got {}",
+                            args.len()
+                        ));
                     }
+                };
+
+                let PyObject::Iterator(it) = it else {
+                    return Err(format!(
+                        "TypeError: '{}' object is not an iterator",
+                        it.type_name()
+                    ));
+                };
+
+                match it.borrow_mut().next() {
+                    Some(v) => Ok(v),
+                    None => default.cloned().ok_or_else(|| "StopIteration".to_string()),
+                }
+            }),
+        })),
+    );
+
+    builtins.insert(
+        "abs".to_string(),
+        PyObject::vm_fn("abs", 1, |vm, args| {
+            if let Some(r) = call_dunder(vm, &args[0], "__abs__", &[]) {
+                return r;
+            }
+
+            match &args[0] {
+                PyObject::Int(n) => n
+                    .checked_abs()
+                    .map(PyObject::Int)
+                    .ok_or_else(|| "OverflowError: integer overflow in abs()".to_string()),
+                PyObject::Bool(b) => Ok(PyObject::Int(*b as i64)),
+                PyObject::Float(f) => Ok(PyObject::Float(f.abs())),
+                PyObject::Complex(re, im) => Ok(PyObject::Float(re.hypot(*im))),
+                other => Err(format!(
+                    "TypeError: bad operand type for abs(): '{}'",
+                    other.type_name()
+                )),
+            }
+        }),
+    );
 
-                    fst = false;
+    builtins.insert(
+        "round".to_string(),
+        PyObject::vm_fn("round", usize::MAX, |vm, args| {
+            let (number, ndigits) = match PyObject::split_kwargs(args) {
+                ([number], None) => (number, None),
+                ([number], Some(kw)) if kw.len() == 1 && kw.contains_key("ndigits") => {
+                    (number, kw.get("ndigits"))
+                }
+                ([number, ndigits], None) => (number, Some(ndigits)),
+                _ => {
+                    return Err(format!(
+                        "TypeError: round() takes at most 2 arguments ({} given)",
+                        args.len()
+                    ));
+                }
+            };
+
+            let ndigits = ndigits.filter(|n| **n != PyObject::None);
+            let dunder_args: Vec<PyObject> = ndigits.into_iter().cloned().collect();
+            if let Some(r) = call_dunder(vm, number, "__round__", &dunder_args) {
+                return r;
+            }
+
+            let ndigits = match ndigits {
+                None => None,
+                Some(PyObject::Int(n)) => Some(*n),
+                Some(PyObject::Bool(b)) => Some(*b as i64),
+                Some(other) => {
+                    return Err(format!(
+                        "TypeError: '{}' object cannot be interpreted as an integer",
+                        other.type_name()
+                    ));
+                }
+            };
+
+            match (number, ndigits) {
+                (PyObject::Bool(b), _) => Ok(PyObject::Int(*b as i64)),
+                (PyObject::Int(n), None) => Ok(PyObject::Int(*n)),
+                (PyObject::Int(n), Some(d)) => Ok(PyObject::Int(round_int(*n, d)?)),
+                (PyObject::Float(x), None) if x.is_nan() => {
+                    Err("ValueError: cannot convert float NaN to integer".to_string())
+                }
+                (PyObject::Float(x), None) if x.is_infinite() => {
+                    Err("OverflowError: cannot convert float infinity to integer".to_string())
+                }
+                (PyObject::Float(x), None) => Ok(PyObject::Int(x.round_ties_even() as i64)),
+                (PyObject::Float(x), Some(d)) => Ok(PyObject::Float(round_float(*x, d))),
+                (other, _) => Err(format!(
+                    "TypeError: type {} doesn't define __round__ method",
+                    other.type_name()
+                )),
+            }
+        }),
+    );
+
+    builtins.insert(
+        "pow".to_string(),
+        PyObject::vm_fn("pow", usize::MAX, |vm, args| {
+            let (base, exp, modulus) = match args {
+                [base, exp] => (base, exp, None),
+                [base, exp, modulus] => {
+                    (base, exp, Some(modulus).filter(|m| **m != PyObject::None))
+                }
+                _ => {
+                    return Err(format!(
+                        "TypeError: pow expected 2 or 3 arguments, got {}",
+                        args.len()
+                    ));
+                }
+            };
+
+            let dunder_args: Vec<PyObject> = std::iter::once(exp).chain(modulus).cloned().collect();
+            if let Some(r) = call_dunder(vm, base, "__pow__", &dunder_args) {
+                return r;
+            }
+
+            pow(base, exp, modulus)
+        }),
+    );
+
+    builtins.insert(
+        "min".to_string(),
+        PyObject::vm_fn("min", usize::MAX, |vm, args| {
+            extremum(vm, "min", args, cmp_lt)
+        }),
+    );
+
+    builtins.insert(
+        "max".to_string(),
+        PyObject::vm_fn("max", usize::MAX, |vm, args| {
+            extremum(vm, "max", args, cmp_gt)
+        }),
+    );
+
+    builtins.insert(
+        "sorted".to_string(),
+        PyObject::vm_fn("sorted", usize::MAX, |vm, args| {
+            let (iterable, _) = PyObject::split_kwargs(args);
+            let [iterable] = iterable else {
+                return Err(format!(
+                    "TypeError: sorted expected 1 argument, got {}",
+                    iterable.len()
+                ));
+            };
+
+            let list = Rc::new(RefCell::new(iterable_items(iterable)?));
+            // what follows the iterable is the keyword arguments `sort` takes
+            crate::core::lists::sort(vm, &list, &args[1..])?;
+            Ok(PyObject::List(list))
+        }),
+    );
 
-                    print!("{}", a);
+    builtins.insert(
+        "sum".to_string(),
+        PyObject::native_fn("sum", usize::MAX, |args| {
+            let (iterable, start) = match PyObject::split_kwargs(args) {
+                ([iterable], None) => (iterable, PyObject::Int(0)),
+                ([iterable, start], None) => (iterable, start.clone()),
+                ([iterable], Some(kw)) if kw.len() == 1 && kw.contains_key("start") => {
+                    (iterable, kw["start"].clone())
+                }
+                _ => {
+                    return Err(format!(
+                        "TypeError: sum() takes at most 2 arguments ({} given)",
+                        args.len()
+                    ));
                 }
+            };
 
-                println!();
+            match start {
+                PyObject::Str(_) => {
+                    Err("TypeError: sum() can't sum strings [use ''.join(seq) instead]".to_string())
+                }
+                PyObject::Bytes(_) => {
+                    Err("TypeError: sum() can't sum bytes [use b''.join(seq) instead]".to_string())
+                }
+                start => sum(iterable_items(iterable)?, start),
+            }
+        }),
+    );
 
-                Ok(PyObject::None)
+    builtins.insert(
+        "len".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "len".to_string(),
+            arity: 1,
+            func: Rc::new(|args| {
+                let len = match &args[0] {
+                    PyObject::Str(s) => s.chars().count(),
+                    PyObject::Bytes(b) => b.len(),
+                    PyObject::List(l) => l.borrow().len(),
+                    PyObject::Deque(d) => d.borrow().len(),
+                    PyObject::Tuple(t) => t.len(),
+                    PyObject::Dict(d) => d.borrow().len(),
+                    PyObject::Set(s) => s.borrow().len(),
+                    PyObject::Range(start, stop, step) => {
+                        let len = crate::vm::range_len(*start, *stop, *step);
+                        i64::try_from(len).map_err(|_| {
+                            "OverflowError: Python int too large to convert to C ssize_t"
+                                .to_string()
+                        })? as usize
+                    }
+                    other => {
+                        return Err(format!(
+                            "TypeError: object of type '{}' has no len()",
+                            other.type_name()
+                        ));
+                    }
+                };
+                Ok(PyObject::Int(len as i64))
+            }),
+        })),
+    );
+
+    builtins.insert(
+        "complex".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "complex".to_string(),
+            arity: usize::MAX,
+            func: Rc::new(|args| {
+                let part = |v: &PyObject| match v {
+                    PyObject::Int(n) => Ok((*n as f64, 0.0)),
+                    PyObject::Bool(b) => Ok((*b as i64 as f64, 0.0)),
+                    PyObject::Float(f) => Ok((*f, 0.0)),
+                    PyObject::Complex(re, im) => Ok((*re, *im)),
+                    other => Err(format!(
+                        "TypeError: complex() argument must be a number, not '{}'",
+                        other.type_name()
+                    )),
+                };
+
+                let ((re, re_im), (im_re, im)) = match args {
+                    [] => ((0.0, 0.0), (0.0, 0.0)),
+                    [re] => (part(re)?, (0.0, 0.0)),
+                    [re, im] => (part(re)?, part(im)?),
+                    _ => {
+                        return Err(format!(
+                            "TypeError: complex() takes at most 2 arguments ({} given)",
+                            args.len()
+                        ));
+                    }
+                };
+
+                // complex(a, b) == a + b*1j, even when a or b are complex
+                Ok(PyObject::Complex(re - im, re_im + im_re))
             }),
         })),
     );
 
+    builtins.insert(
+        "repr".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "repr".to_string(),
+            arity: 1,
+            func: Rc::new(|args| Ok(PyObject::Str(args[0].repr()))),
+        })),
+    );
+
     builtins.insert(
         "range".to_string(),
         PyObject::NativeFunction(Rc::new(PyNativeFunction {
@@ -79,22 +759,7 @@ pub fn apply(builtins: &mut HashMap<String, PyObject>) {
                     _ => return Err("TypeError: range expected 1 to 3 arguments".to_string()),
                 };
 
-                let mut items = Vec::new();
-                if step > 0 {
-                    let mut i = start;
-                    while i < stop {
-                        items.push(PyObject::Int(i));
-                        i += step;
-                    }
-                } else {
-                    let mut i = start;
-                    while i > stop {
-                        items.push(PyObject::Int(i));
-                        i += step;
-                    }
-                }
-
-                Ok(PyObject::List(Rc::new(RefCell::new(items))))
+                Ok(PyObject::Range(start, stop, step))
             }),
         })),
     );
@@ -112,31 +777,52 @@ pub fn apply(builtins: &mut HashMap<String, PyObject>) {
                     PyObject::Float(_) => PyType {
                         name: "float".to_string(),
                     },
+                    PyObject::Complex(..) => PyType {
+                        name: "complex".to_string(),
+                    },
+                    PyObject::Decimal(_) => PyType {
+                        name: "Decimal".to_string(),
+                    },
                     PyObject::Bool(_) => PyType {
                         name: "bool".to_string(),
                     },
                     PyObject::Str(_) => PyType {
                         name: "str".to_string(),
                     },
+                    PyObject::Bytes(_) => PyType {
+                        name: "bytes".to_string(),
+                    },
                     PyObject::List(_) => PyType {
                         name: "list".to_string(),
                     },
                     PyObject::Dict(_) => PyType {
                         name: "dict".to_string(),
                     },
+                    PyObject::Deque(_) => PyType {
+                        name: "deque".to_string(),
+                    },
                     PyObject::Tuple(_) => PyType {
                         name: "tuple".to_string(),
                     },
                     PyObject::Set(_) => PyType {
                         name: "set".to_string(),
                     },
+                    PyObject::Range(..) => PyType {
+                        name: "range".to_string(),
+                    },
                     PyObject::None => PyType {
                         name: "NoneType".to_string(),
                     },
+                    PyObject::Ellipsis => PyType {
+                        name: "ellipsis".to_string(),
+                    },
                     PyObject::Function(_) => PyType {
                         name: "function".to_string(),
                     },
-                    PyObject::NativeFunction(_) => PyType {
+                    PyObject::Method(_) => PyType {
+                        name: "method".to_string(),
+                    },
+                    PyObject::NativeFunction(_) | PyObject::VmFunction(_) => PyType {
                         name: "native_function".to_string(),
                     },
                     PyObject::NativeModule(_) => PyType {
@@ -157,6 +843,12 @@ pub fn apply(builtins: &mut HashMap<String, PyObject>) {
                     PyObject::Module(_) => PyType {
                         name: "module".to_string(),
                     },
+                    PyObject::Exception(e) => PyType {
+                        name: e.kind.clone(),
+                    },
+                    PyObject::Iterator(_) | PyObject::WeakRef(_) | PyObject::Kwargs(_) => PyType {
+                        name: args[0].type_name(),
+                    },
                 };
 
                 Ok(PyObject::Type(t))
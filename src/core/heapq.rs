@@ -0,0 +1,167 @@
+use crate::object::{PyIterator, PyObject};
+use crate::vm::cmp_lt;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+type Less = fn(&PyObject, &PyObject) -> Result<bool, String>;
+
+/// `a < b`, comparing tuples and lists item by item as Python does.
+pub(crate) fn less(a: &PyObject, b: &PyObject) -> Result<bool, String> {
+    match (a, b) {
+        (PyObject::Tuple(x), PyObject::Tuple(y)) => less_seq(x, y),
+        (PyObject::List(x), PyObject::List(y)) => less_seq(&x.borrow(), &y.borrow()),
+        _ => Ok(matches!(
+            cmp_lt(a.clone(), b.clone())?,
+            PyObject::Bool(true)
+        )),
+    }
+}
+
+fn less_seq(x: &[PyObject], y: &[PyObject]) -> Result<bool, String> {
+    match x.iter().zip(y).find(|(a, b)| a != b) {
+        Some((a, b)) => less(a, b),
+        None => Ok(x.len() < y.len()),
+    }
+}
+
+fn greater(a: &PyObject, b: &PyObject) -> Result<bool, String> {
+    less(b, a)
+}
+
+fn sift_up(heap: &mut [PyObject], mut pos: usize, lt: Less) -> Result<(), String> {
+    while pos > 0 {
+        let parent = (pos - 1) / 2;
+        if !lt(&heap[pos], &heap[parent])? {
+            break;
+        }
+        heap.swap(pos, parent);
+        pos = parent;
+    }
+    Ok(())
+}
+
+fn sift_down(heap: &mut [PyObject], mut pos: usize, lt: Less) -> Result<(), String> {
+    loop {
+        let mut smallest = pos;
+        for child in [2 * pos + 1, 2 * pos + 2] {
+            if child < heap.len() && lt(&heap[child], &heap[smallest])? {
+                smallest = child;
+            }
+        }
+        if smallest == pos {
+            return Ok(());
+        }
+        heap.swap(pos, smallest);
+        pos = smallest;
+    }
+}
+
+fn heapify(heap: &mut [PyObject], lt: Less) -> Result<(), String> {
+    for pos in (0..heap.len() / 2).rev() {
+        sift_down(heap, pos, lt)?;
+    }
+    Ok(())
+}
+
+fn pop(heap: &mut Vec<PyObject>, lt: Less) -> Result<Option<PyObject>, String> {
+    if heap.is_empty() {
+        return Ok(None);
+    }
+    let last = heap.len() - 1;
+    heap.swap(0, last);
+    let top = heap.pop();
+    sift_down(heap, 0, lt)?;
+    Ok(top)
+}
+
+fn list_arg<'a>(name: &str, arg: &'a PyObject) -> Result<&'a RefCell<Vec<PyObject>>, String> {
+    match arg {
+        PyObject::List(l) => Ok(l),
+        other => Err(format!(
+            "TypeError: {}() argument 1 must be list, not {}",
+            name,
+            other.type_name()
+        )),
+    }
+}
+
+/// The `n` first items of `iterable` in `lt` order, for `nsmallest`/`nlargest`.
+fn first_n(name: &str, args: &[PyObject], lt: Less) -> Result<PyObject, String> {
+    let PyObject::Int(n) = args[0] else {
+        return Err(format!(
+            "TypeError: {}() argument 1 must be int, not {}",
+            name,
+            args[0].type_name()
+        ));
+    };
+
+    let mut heap: Vec<PyObject> = match &args[1] {
+        PyObject::Iterator(it) => it.borrow_mut().by_ref().collect(),
+        v @ (PyObject::List(_)
+        | PyObject::Tuple(_)
+        | PyObject::Str(_)
+        | PyObject::Set(_)
+        | PyObject::Dict(_)
+        | PyObject::Range(..)) => PyIterator::new(v.clone()).collect(),
+        other => {
+            return Err(format!(
+                "TypeError: '{}' object is not iterable",
+                other.type_name()
+            ));
+        }
+    };
+    heapify(&mut heap, lt)?;
+
+    let mut out = Vec::new();
+    while (out.len() as i64) < n {
+        match pop(&mut heap, lt)? {
+            Some(v) => out.push(v),
+            None => break,
+        }
+    }
+    Ok(PyObject::List(Rc::new(RefCell::new(out))))
+}
+
+pub fn heapq_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "heappush".to_string(),
+        PyObject::native_fn("heappush", 2, |args| {
+            let mut heap = list_arg("heappush", &args[0])?.borrow_mut();
+            heap.push(args[1].clone());
+            let last = heap.len() - 1;
+            sift_up(&mut heap, last, less)?;
+            Ok(PyObject::None)
+        }),
+    );
+
+    m.insert(
+        "heappop".to_string(),
+        PyObject::native_fn("heappop", 1, |args| {
+            let mut heap = list_arg("heappop", &args[0])?.borrow_mut();
+            pop(&mut heap, less)?.ok_or_else(|| "IndexError: index out of range".to_string())
+        }),
+    );
+
+    m.insert(
+        "heapify".to_string(),
+        PyObject::native_fn("heapify", 1, |args| {
+            heapify(&mut list_arg("heapify", &args[0])?.borrow_mut(), less)?;
+            Ok(PyObject::None)
+        }),
+    );
+
+    m.insert(
+        "nsmallest".to_string(),
+        PyObject::native_fn("nsmallest", 2, |args| first_n("nsmallest", args, less)),
+    );
+
+    m.insert(
+        "nlargest".to_string(),
+        PyObject::native_fn("nlargest", 2, |args| first_n("nlargest", args, greater)),
+    );
+
+    m
+}
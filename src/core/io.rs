@@ -1,8 +1,100 @@
 use crate::{PyNativeFunction, PyObject};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::{self, Read, Write};
 use std::rc::Rc;
 
+type Stream = RefCell<Box<dyn Write>>;
+
+thread_local! {
+    static STDOUT: Stream = RefCell::new(Box::new(io::stdout()));
+    static STDERR: Stream = RefCell::new(Box::new(io::stderr()));
+}
+
+fn write_to(stream: &'static std::thread::LocalKey<Stream>, s: &str) -> Result<(), String> {
+    stream.with(|out| {
+        let mut out = out.borrow_mut();
+        out.write_all(s.as_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|e| format!("OSError: {}", e))
+    })
+}
+
+/// Writes to the stream shared by `print`, `io.write` and `sys.stdout.write`.
+/// Every write is flushed so their output interleaves in program order.
+pub(crate) fn write_stdout(s: &str) -> Result<(), String> {
+    write_to(&STDOUT, s)
+}
+
+/// Writes to the stream behind `sys.stderr.write`.
+pub(crate) fn write_stderr(s: &str) -> Result<(), String> {
+    write_to(&STDERR, s)
+}
+
+/// Replaces the shared stdout stream, returning the previous one.
+pub(crate) fn set_stdout(out: Box<dyn Write>) -> Box<dyn Write> {
+    STDOUT.with(|cur| std::mem::replace(&mut *cur.borrow_mut(), out))
+}
+
+/// Replaces the shared stderr stream, returning the previous one.
+pub(crate) fn set_stderr(err: Box<dyn Write>) -> Box<dyn Write> {
+    STDERR.with(|cur| std::mem::replace(&mut *cur.borrow_mut(), err))
+}
+
+/// Python's `print`, shared by the builtin and `io.print`.
+pub(crate) fn print(args: &[PyObject]) -> Result<PyObject, String> {
+    let (args, kwargs) = match args.split_last() {
+        Some((PyObject::Kwargs(kw), rest)) => (rest, Some(kw)),
+        _ => (args, None),
+    };
+
+    let mut sep = " ".to_string();
+    let mut end = "\n".to_string();
+
+    for (k, v) in kwargs.into_iter().flat_map(|kw| kw.iter()) {
+        let slot = match k.as_str() {
+            "sep" => &mut sep,
+            "end" => &mut end,
+            _ => {
+                return Err(format!(
+                    "TypeError: '{}' is an invalid keyword argument for print()",
+                    k
+                ));
+            }
+        };
+
+        match v {
+            PyObject::Str(s) => *slot = s.clone(),
+            PyObject::None => {}
+            other => {
+                return Err(format!(
+                    "TypeError: {} must be None or a string, not {}",
+                    k,
+                    other.type_name()
+                ));
+            }
+        }
+    }
+
+    let mut line = String::new();
+    let mut fst = true;
+
+    for a in args {
+        if !fst {
+            line.push_str(&sep);
+        }
+
+        fst = false;
+
+        line.push_str(&a.to_string());
+    }
+
+    line.push_str(&end);
+    write_stdout(&line)?;
+
+    Ok(PyObject::None)
+}
+
 pub fn io_module() -> HashMap<String, PyObject> {
     let mut m = HashMap::new();
 
@@ -10,11 +102,8 @@ pub fn io_module() -> HashMap<String, PyObject> {
         "print".to_string(),
         PyObject::NativeFunction(Rc::new(PyNativeFunction {
             name: "print".to_string(),
-            arity: 1,
-            func: Rc::new(|args| {
-                println!("{}", args[0]);
-                Ok(PyObject::None)
-            }),
+            arity: usize::MAX,
+            func: Rc::new(print),
         })),
     );
 
@@ -51,8 +140,7 @@ pub fn io_module() -> HashMap<String, PyObject> {
             arity: 1,
             func: Rc::new(|args| {
                 if let PyObject::Str(s) = &args[0] {
-                    io::stdout().write_all(s.as_bytes()).unwrap();
-                    io::stdout().flush().unwrap();
+                    write_stdout(s)?;
                     Ok(PyObject::None)
                 } else {
                     Err("bad args".to_string())
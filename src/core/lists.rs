@@ -0,0 +1,183 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::core::heapq::less;
+use crate::core::tuples;
+use crate::object::{PyNativeFunction, PyObject};
+use crate::vm::{Vm, is_falsey};
+
+type List = Rc<RefCell<Vec<PyObject>>>;
+
+/// A stable merge sort of `(key, item)` pairs by key. Comparisons can fail,
+/// which `slice::sort_by` has no way to report.
+fn merge_sort(mut items: Vec<(PyObject, PyObject)>) -> Result<Vec<(PyObject, PyObject)>, String> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+
+    let right = items.split_off(items.len() / 2);
+    let mut left = merge_sort(items)?.into_iter().peekable();
+    let mut right = merge_sort(right)?.into_iter().peekable();
+
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // ties take from the left so equal items keep their order
+        if less(&b.0, &a.0)? {
+            out.extend(right.next());
+        } else {
+            out.extend(left.next());
+        }
+    }
+    out.extend(left);
+    out.extend(right);
+    Ok(out)
+}
+
+/// `list.sort`, also behind the `sorted` builtin.
+pub(crate) fn sort(vm: &mut Vm, l: &List, args: &[PyObject]) -> Result<PyObject, String> {
+    let (args, kwargs) = PyObject::split_kwargs(args);
+    if !args.is_empty() {
+        return Err("TypeError: sort() takes no positional arguments".to_string());
+    }
+
+    let mut key = None;
+    let mut reverse = false;
+    for (name, value) in kwargs.into_iter().flatten() {
+        match name.as_str() {
+            "key" if *value != PyObject::None => key = Some(value.clone()),
+            "key" => {}
+            "reverse" => reverse = !is_falsey(value),
+            _ => {
+                return Err(format!(
+                    "TypeError: sort() got an unexpected keyword argument '{}'",
+                    name
+                ));
+            }
+        }
+    }
+
+    // work on a copy so a key function that touches the list can't clash
+    // with a borrow held here
+    let mut items = l.borrow().clone();
+    if reverse {
+        items.reverse();
+    }
+
+    let mut keyed = Vec::with_capacity(items.len());
+    for item in items {
+        let k = match &key {
+            Some(f) => vm.call_value(f.clone(), vec![item.clone()])?,
+            None => item.clone(),
+        };
+        keyed.push((k, item));
+    }
+
+    let mut sorted: Vec<PyObject> = merge_sort(keyed)?.into_iter().map(|(_, v)| v).collect();
+    // sorting the reversed list and reversing back keeps equal items stable
+    if reverse {
+        sorted.reverse();
+    }
+    *l.borrow_mut() = sorted;
+    Ok(PyObject::None)
+}
+
+fn pop(l: &List, args: &[PyObject]) -> Result<PyObject, String> {
+    let mut list = l.borrow_mut();
+    if list.is_empty() {
+        return Err("IndexError: pop from empty list".to_string());
+    }
+
+    let len = list.len() as i64;
+    let index = match args {
+        [] => len - 1,
+        [PyObject::Int(i)] if *i < 0 => len + i,
+        [PyObject::Int(i)] => *i,
+        [other] => {
+            return Err(format!(
+                "TypeError: '{}' object cannot be interpreted as an integer",
+                other.type_name()
+            ));
+        }
+        _ => {
+            return Err(format!(
+                "TypeError: pop expected at most 1 argument, got {}",
+                args.len()
+            ));
+        }
+    };
+
+    if !(0..len).contains(&index) {
+        return Err("IndexError: pop index out of range".to_string());
+    }
+    Ok(list.remove(index as usize))
+}
+
+/// Looks up `name` on a `list` value, returning the method bound to `l`.
+pub(crate) fn method(l: &List, name: &str) -> Option<PyObject> {
+    let l = l.clone();
+
+    // these compare items with `__eq__` or call a key function, so need the VM
+    match name {
+        "count" => {
+            return Some(PyObject::vm_fn("list.count", 1, move |vm, args| {
+                let items = l.borrow().clone();
+                tuples::count(vm, &items, &args[0])
+            }));
+        }
+        "index" => {
+            return Some(PyObject::vm_fn(
+                "list.index",
+                usize::MAX,
+                move |vm, args| {
+                    let items = l.borrow().clone();
+                    match tuples::index(vm, "index", &items, args)? {
+                        Some(i) => Ok(PyObject::Int(i as i64)),
+                        None => Err(format!("ValueError: {} is not in list", args[0].repr())),
+                    }
+                },
+            ));
+        }
+        "sort" => {
+            return Some(PyObject::vm_fn("list.sort", usize::MAX, move |vm, args| {
+                sort(vm, &l, args)
+            }));
+        }
+        _ => {}
+    }
+
+    let (arity, func): (usize, Rc<dyn Fn(&[PyObject]) -> Result<PyObject, String>>) = match name {
+        "append" => (
+            1,
+            Rc::new(move |args| {
+                l.borrow_mut().push(args[0].clone());
+                Ok(PyObject::None)
+            }),
+        ),
+        "pop" => (usize::MAX, Rc::new(move |args| pop(&l, args))),
+        "reverse" => (
+            0,
+            Rc::new(move |_| {
+                l.borrow_mut().reverse();
+                Ok(PyObject::None)
+            }),
+        ),
+        "copy" => (
+            0,
+            Rc::new(move |_| Ok(PyObject::List(Rc::new(RefCell::new(l.borrow().clone()))))),
+        ),
+        "clear" => (
+            0,
+            Rc::new(move |_| {
+                l.borrow_mut().clear();
+                Ok(PyObject::None)
+            }),
+        ),
+        _ => return None,
+    };
+
+    Some(PyObject::NativeFunction(Rc::new(PyNativeFunction {
+        name: format!("list.{}", name),
+        arity,
+        func,
+    })))
+}
@@ -245,5 +245,80 @@ pub fn math_module() -> HashMap<String, PyObject> {
         })),
     );
 
+    m.insert(
+        "trunc".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "trunc".to_string(),
+            arity: 1,
+            func: Rc::new(|args| match args[0] {
+                PyObject::Int(x) => Ok(PyObject::Int(x)),
+                PyObject::Bool(b) => Ok(PyObject::Int(b as i64)),
+                PyObject::Float(x) => crate::core::globs::float_to_int(x),
+                _ => Err("bad args".to_string()),
+            }),
+        })),
+    );
+
+    m.insert(
+        "modf".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "modf".to_string(),
+            arity: 1,
+            func: Rc::new(|args| {
+                let x = to_float(&args[0])?;
+                // inf has no fractional part, where `fract` would give NaN
+                let fract = if x.is_infinite() {
+                    0.0f64.copysign(x)
+                } else {
+                    x.fract()
+                };
+                Ok(PyObject::Tuple(vec![
+                    PyObject::Float(fract),
+                    PyObject::Float(x.trunc()),
+                ]))
+            }),
+        })),
+    );
+
+    m.insert(
+        "copysign".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "copysign".to_string(),
+            arity: 2,
+            func: Rc::new(|args| {
+                let x = to_float(&args[0])?;
+                let y = to_float(&args[1])?;
+                Ok(PyObject::Float(x.copysign(y)))
+            }),
+        })),
+    );
+
+    m.insert(
+        "isnan".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "isnan".to_string(),
+            arity: 1,
+            func: Rc::new(|args| Ok(PyObject::Bool(to_float(&args[0])?.is_nan()))),
+        })),
+    );
+
+    m.insert(
+        "isinf".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "isinf".to_string(),
+            arity: 1,
+            func: Rc::new(|args| Ok(PyObject::Bool(to_float(&args[0])?.is_infinite()))),
+        })),
+    );
+
     m
 }
+
+fn to_float(v: &PyObject) -> Result<f64, String> {
+    match v {
+        PyObject::Int(x) => Ok(*x as f64),
+        PyObject::Bool(b) => Ok(*b as i64 as f64),
+        PyObject::Float(x) => Ok(*x),
+        _ => Err("TypeError: must be real number".to_string()),
+    }
+}
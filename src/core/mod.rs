@@ -1,6 +1,34 @@
+pub(crate) mod bisect;
+pub(crate) mod bytes;
+pub(crate) mod collections;
+pub(crate) mod datetime;
+pub(crate) mod decimal;
+pub(crate) mod dicts;
+pub(crate) mod exceptions;
+pub(crate) mod functools;
+pub(crate) mod gc;
 pub(crate) mod globs;
+pub(crate) mod heapq;
 pub(crate) mod io;
+pub(crate) mod lists;
 pub(crate) mod math;
+pub(crate) mod operator;
 pub(crate) mod os;
+pub(crate) mod pprint;
+pub(crate) mod re;
+pub(crate) mod statistics;
+pub(crate) mod string;
+pub(crate) mod strings;
 pub(crate) mod sys;
 pub(crate) mod time;
+pub(crate) mod tuples;
+pub(crate) mod typing;
+pub(crate) mod weakref;
+
+/// Source of the standard modules written in Python, compiled on first import.
+pub(crate) fn source_module(name: &str) -> Option<&'static str> {
+    match name {
+        "functools" => Some(include_str!("functools.py")),
+        _ => None,
+    }
+}
@@ -0,0 +1,46 @@
+use crate::object::PyObject;
+use crate::vm::{
+    arith_add, arith_div, arith_floordiv, arith_mul, arith_sub, cmp_ge, cmp_gt, cmp_le, cmp_lt,
+    load_index,
+};
+use std::collections::HashMap;
+
+type BinaryOp = fn(PyObject, PyObject) -> Result<PyObject, String>;
+
+pub fn operator_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    let binary: [(&str, BinaryOp); 12] = [
+        ("add", arith_add),
+        ("sub", arith_sub),
+        ("mul", arith_mul),
+        ("truediv", arith_div),
+        ("floordiv", arith_floordiv),
+        ("lt", cmp_lt),
+        ("le", cmp_le),
+        ("gt", cmp_gt),
+        ("ge", cmp_ge),
+        ("eq", |a, b| Ok(PyObject::Bool(a == b))),
+        ("ne", |a, b| Ok(PyObject::Bool(a != b))),
+        ("getitem", load_index),
+    ];
+
+    for (name, op) in binary {
+        m.insert(
+            name.to_string(),
+            PyObject::native_fn(name, 2, move |args| op(args[0].clone(), args[1].clone())),
+        );
+    }
+
+    m.insert(
+        "itemgetter".to_string(),
+        PyObject::native_fn("itemgetter", 1, |args| {
+            let index = args[0].clone();
+            Ok(PyObject::native_fn("itemgetter", 1, move |args| {
+                load_index(args[0].clone(), index.clone())
+            }))
+        }),
+    );
+
+    m
+}
@@ -3,7 +3,7 @@ use std::env as sys_env;
 use std::rc::Rc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::{Env, PyNativeFunction, PyObject};
+use crate::{PyNativeFunction, PyObject};
 
 pub fn os_module() -> HashMap<String, PyObject> {
     let mut m = HashMap::new();
@@ -59,12 +59,10 @@ pub fn os_module() -> HashMap<String, PyObject> {
         "exit".to_string(),
         PyObject::NativeFunction(Rc::new(PyNativeFunction {
             name: "exit".to_string(),
-            arity: 1,
-            func: Rc::new(|args| {
-                if let PyObject::Int(code) = args[0] {
-                    std::process::exit(code as i32);
-                }
-                Err("bad args".to_string())
+            arity: usize::MAX,
+            func: Rc::new(|args| match args.first() {
+                None => Err("SystemExit: 0".to_string()),
+                Some(code) => Err(format!("SystemExit: {}", code)),
             }),
         })),
     );
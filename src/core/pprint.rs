@@ -0,0 +1,157 @@
+use crate::object::{PyNativeFunction, PyObject};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const DEFAULT_INDENT: usize = 2;
+
+/// Splits `pformat`/`pprint` arguments into the object and the indent width.
+fn parse_args(name: &str, args: &[PyObject]) -> Result<(PyObject, usize), String> {
+    let (args, kwargs) = match args.split_last() {
+        Some((PyObject::Kwargs(kw), rest)) => (rest, Some(kw)),
+        _ => (args, None),
+    };
+
+    let mut indent = match args {
+        [_] => None,
+        [_, indent] => Some(indent.clone()),
+        _ => {
+            return Err(format!(
+                "TypeError: {}() takes 1 or 2 positional arguments but {} were given",
+                name,
+                args.len()
+            ));
+        }
+    };
+
+    for (k, v) in kwargs.into_iter().flat_map(|kw| kw.iter()) {
+        match k.as_str() {
+            "indent" if indent.is_none() => indent = Some(v.clone()),
+            "indent" => {
+                return Err(format!(
+                    "TypeError: {}() got multiple values for argument 'indent'",
+                    name
+                ));
+            }
+            _ => {
+                return Err(format!(
+                    "TypeError: '{}' is an invalid keyword argument for {}()",
+                    k, name
+                ));
+            }
+        }
+    }
+
+    let indent = match indent {
+        None => DEFAULT_INDENT,
+        Some(PyObject::Int(n)) if n >= 0 => n as usize,
+        Some(PyObject::Int(_)) => return Err("ValueError: indent must be >= 0".to_string()),
+        Some(other) => {
+            return Err(format!(
+                "TypeError: indent must be an integer, not {}",
+                other.type_name()
+            ));
+        }
+    };
+
+    Ok((args[0].clone(), indent))
+}
+
+/// Writes `items` one per line between `open` and `close`, each nested one
+/// level deeper than the brackets.
+fn format_items<T>(
+    out: &mut String,
+    items: &[T],
+    (open, close): (&str, &str),
+    indent: usize,
+    level: usize,
+    mut item: impl FnMut(&mut String, &T),
+) {
+    out.push_str(open);
+    for (i, x) in items.iter().enumerate() {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent * (level + 1)));
+        item(out, x);
+        if i + 1 < items.len() {
+            out.push(',');
+        }
+    }
+    // a lone tuple item keeps its trailing comma
+    if open == "(" && items.len() == 1 {
+        out.push(',');
+    }
+    out.push('\n');
+    out.push_str(&" ".repeat(indent * level));
+    out.push_str(close);
+}
+
+fn format(out: &mut String, obj: &PyObject, indent: usize, level: usize) {
+    match obj {
+        PyObject::List(l) if !l.borrow().is_empty() => {
+            let items = l.borrow().clone();
+            format_items(out, &items, ("[", "]"), indent, level, |out, x| {
+                format(out, x, indent, level + 1)
+            });
+        }
+        PyObject::Tuple(t) if !t.is_empty() => {
+            format_items(out, t, ("(", ")"), indent, level, |out, x| {
+                format(out, x, indent, level + 1)
+            });
+        }
+        PyObject::Set(s) if !s.borrow().is_empty() => {
+            let items: Vec<PyObject> = s.borrow().iter().cloned().collect();
+            format_items(out, &items, ("{", "}"), indent, level, |out, x| {
+                format(out, x, indent, level + 1)
+            });
+        }
+        PyObject::Dict(d) if !d.borrow().is_empty() => {
+            let items: Vec<(PyObject, PyObject)> = d
+                .borrow()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            format_items(out, &items, ("{", "}"), indent, level, |out, (k, v)| {
+                out.push_str(&k.repr());
+                out.push_str(": ");
+                format(out, v, indent, level + 1);
+            });
+        }
+        other => out.push_str(&other.repr()),
+    }
+}
+
+fn pformat(obj: &PyObject, indent: usize) -> String {
+    let mut out = String::new();
+    format(&mut out, obj, indent, 0);
+    out
+}
+
+pub fn pprint_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "pformat".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "pformat".to_string(),
+            arity: usize::MAX,
+            func: Rc::new(|args| {
+                let (obj, indent) = parse_args("pformat", args)?;
+                Ok(PyObject::Str(pformat(&obj, indent)))
+            }),
+        })),
+    );
+
+    m.insert(
+        "pprint".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "pprint".to_string(),
+            arity: usize::MAX,
+            func: Rc::new(|args| {
+                let (obj, indent) = parse_args("pprint", args)?;
+                crate::core::io::write_stdout(&(pformat(&obj, indent) + "\n"))?;
+                Ok(PyObject::None)
+            }),
+        })),
+    );
+
+    m
+}
@@ -0,0 +1,309 @@
+use regex::{Captures, Regex};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::object::{PyClass, PyInstance, PyObject};
+use crate::vm::Vm;
+
+fn str_arg<'a>(func: &str, v: &'a PyObject) -> Result<&'a str, String> {
+    match v {
+        PyObject::Str(s) => Ok(s),
+        other => Err(format!(
+            "TypeError: {}() expected string, got '{}'",
+            func,
+            other.type_name()
+        )),
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| {
+        // the engine's message ends with a one-line summary after a diagram
+        let msg = e.to_string();
+        let summary = msg.lines().last().unwrap_or_default();
+        format!("error: {}", summary.trim().trim_start_matches("error: "))
+    })
+}
+
+/// Character offset of byte offset `at` in `text`, as Python reports positions.
+fn char_pos(text: &str, at: usize) -> i64 {
+    text[..at].chars().count() as i64
+}
+
+/// A `Match` instance for `caps`, with `group`, `groups`, `start`, `end` and
+/// `span` methods.
+fn match_object(class: &Rc<PyClass>, text: &str, caps: &Captures, re: &Regex) -> PyObject {
+    let groups: Rc<Vec<Option<(i64, i64, String)>>> = Rc::new(
+        caps.iter()
+            .map(|m| {
+                m.map(|m| {
+                    let start = char_pos(text, m.start());
+                    let end = start + m.as_str().chars().count() as i64;
+                    (start, end, m.as_str().to_string())
+                })
+            })
+            .collect(),
+    );
+    let names: Rc<HashMap<String, usize>> = Rc::new(
+        re.capture_names()
+            .enumerate()
+            .filter_map(|(i, name)| Some((name?.to_string(), i)))
+            .collect(),
+    );
+
+    // the group a `group`/`start`/`end`/`span` argument refers to
+    let index = move |method: &str, args: &[PyObject]| -> Result<usize, String> {
+        let i = match args {
+            [] => 0,
+            [PyObject::Int(n)] => usize::try_from(*n).unwrap_or(usize::MAX),
+            [PyObject::Str(name)] => *names
+                .get(name)
+                .ok_or_else(|| "IndexError: no such group".to_string())?,
+            [other] => {
+                return Err(format!("IndexError: no such group: {}", other.repr()));
+            }
+            _ => {
+                return Err(format!(
+                    "TypeError: Match.{}() takes at most 1 argument ({} given)",
+                    method,
+                    args.len()
+                ));
+            }
+        };
+        Ok(i)
+    };
+    let index = Rc::new(index);
+
+    let group_str = |g: &Option<(i64, i64, String)>| match g {
+        Some((_, _, s)) => PyObject::Str(s.clone()),
+        None => PyObject::None,
+    };
+
+    let mut attrs = HashMap::new();
+
+    let (g, idx) = (groups.clone(), index.clone());
+    attrs.insert(
+        "group".to_string(),
+        PyObject::native_fn("group", usize::MAX, move |args| {
+            let get = |args: &[PyObject]| -> Result<PyObject, String> {
+                let i = idx("group", args)?;
+                g.get(i)
+                    .map(group_str)
+                    .ok_or_else(|| "IndexError: no such group".to_string())
+            };
+            match args {
+                [] | [_] => get(args),
+                // `group(1, 2)` is a tuple of both
+                _ => Ok(PyObject::Tuple(
+                    args.iter()
+                        .map(|a| get(std::slice::from_ref(a)))
+                        .collect::<Result<_, _>>()?,
+                )),
+            }
+        }),
+    );
+
+    let g = groups.clone();
+    attrs.insert(
+        "groups".to_string(),
+        PyObject::native_fn("groups", 0, move |_| {
+            Ok(PyObject::Tuple(g[1..].iter().map(group_str).collect()))
+        }),
+    );
+
+    for method in ["start", "end", "span"] {
+        let (g, idx) = (groups.clone(), index.clone());
+        attrs.insert(
+            method.to_string(),
+            PyObject::native_fn(method, usize::MAX, move |args| {
+                let i = idx(method, args)?;
+                let (start, end) = match g.get(i) {
+                    Some(Some((start, end, _))) => (*start, *end),
+                    Some(None) => (-1, -1),
+                    None => return Err("IndexError: no such group".to_string()),
+                };
+                Ok(match method {
+                    "start" => PyObject::Int(start),
+                    "end" => PyObject::Int(end),
+                    _ => PyObject::Tuple(vec![PyObject::Int(start), PyObject::Int(end)]),
+                })
+            }),
+        );
+    }
+
+    PyObject::Instance(Rc::new(RefCell::new(PyInstance {
+        class: class.clone(),
+        attrs,
+    })))
+}
+
+/// Expands a `sub` replacement template: `\1`, `\g<1>` and `\g<name>` insert
+/// groups, and `\n`, `\t` and `\\` the characters they name.
+fn expand(template: &str, re: &Regex, caps: &Captures) -> Result<String, String> {
+    let group = |key: &str| -> Result<String, String> {
+        let m = match key.parse::<usize>() {
+            Ok(i) if i < caps.len() => caps.get(i),
+            Ok(i) => return Err(format!("error: invalid group reference {}", i)),
+            Err(_) if re.capture_names().flatten().any(|name| name == key) => caps.name(key),
+            Err(_) => return Err("IndexError: unknown group name".to_string()),
+        };
+        Ok(m.map_or("", |m| m.as_str()).to_string())
+    };
+
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some(d) if d.is_ascii_digit() => {
+                let mut key = d.to_string();
+                if let Some(e) = chars.next_if(|e| e.is_ascii_digit()) {
+                    key.push(e);
+                }
+                out.push_str(&group(&key)?);
+            }
+            Some('g') if chars.peek() == Some(&'<') => {
+                chars.next();
+                let key: String = chars.by_ref().take_while(|&c| c != '>').collect();
+                out.push_str(&group(&key)?);
+            }
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                out.push('\\');
+                out.push(other);
+            }
+            None => return Err("error: bad escape (end of pattern)".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// `re.match`, `re.search` and `re.fullmatch`, which differ in how the
+/// pattern is anchored.
+fn matcher(class: &Rc<PyClass>, name: &'static str, anchor: fn(&str) -> String) -> PyObject {
+    let class = class.clone();
+    PyObject::native_fn(name, 2, move |args| {
+        let pattern = anchor(str_arg(name, &args[0])?);
+        let text = str_arg(name, &args[1])?;
+        let re = compile(&pattern)?;
+        Ok(match re.captures(text) {
+            Some(caps) => match_object(&class, text, &caps, &re),
+            None => PyObject::None,
+        })
+    })
+}
+
+pub fn re_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    let class = Rc::new(PyClass {
+        name: "Match".to_string(),
+        methods: RefCell::new(HashMap::new()),
+        bases: Vec::new(),
+    });
+
+    m.insert(
+        "match".to_string(),
+        matcher(&class, "match", |p| format!(r"\A(?:{})", p)),
+    );
+    m.insert(
+        "fullmatch".to_string(),
+        matcher(&class, "fullmatch", |p| format!(r"\A(?:{})\z", p)),
+    );
+    m.insert(
+        "search".to_string(),
+        matcher(&class, "search", |p| p.to_string()),
+    );
+
+    // whole matches without groups, the group with one, tuples with several
+    m.insert(
+        "findall".to_string(),
+        PyObject::native_fn("findall", 2, |args| {
+            let re = compile(str_arg("findall", &args[0])?)?;
+            let text = str_arg("findall", &args[1])?;
+            let group = |caps: &Captures, i: usize| {
+                PyObject::Str(caps.get(i).map_or("", |m| m.as_str()).to_string())
+            };
+
+            let found = re
+                .captures_iter(text)
+                .map(|caps| match caps.len() {
+                    1 => group(&caps, 0),
+                    2 => group(&caps, 1),
+                    n => PyObject::Tuple((1..n).map(|i| group(&caps, i)).collect()),
+                })
+                .collect();
+            Ok(PyObject::List(Rc::new(RefCell::new(found))))
+        }),
+    );
+
+    // `repl` is a template string, or a function given each match object
+    m.insert(
+        "sub".to_string(),
+        PyObject::vm_fn("sub", usize::MAX, move |vm: &mut Vm, args| {
+            let (args, kwargs) = PyObject::split_kwargs(args);
+            let (pattern, repl, text, count) = match (args, kwargs.and_then(|kw| kw.get("count"))) {
+                ([pattern, repl, text], None) => (pattern, repl, text, None),
+                ([pattern, repl, text, count], None) | ([pattern, repl, text], Some(count)) => {
+                    (pattern, repl, text, Some(count))
+                }
+                _ => {
+                    return Err(format!(
+                        "TypeError: sub() takes from 3 to 4 positional arguments but {} were given",
+                        args.len()
+                    ));
+                }
+            };
+
+            let re = compile(str_arg("sub", pattern)?)?;
+            let text = str_arg("sub", text)?;
+            let count = match count {
+                None => usize::MAX,
+                Some(PyObject::Int(n)) if *n <= 0 => usize::MAX,
+                Some(PyObject::Int(n)) => *n as usize,
+                Some(other) => {
+                    return Err(format!(
+                        "TypeError: '{}' object cannot be interpreted as an integer",
+                        other.type_name()
+                    ));
+                }
+            };
+
+            let mut out = String::new();
+            let mut last = 0;
+            for caps in re.captures_iter(text).take(count) {
+                let whole = caps.get(0).expect("group 0 always matches");
+                out.push_str(&text[last..whole.start()]);
+
+                match repl {
+                    PyObject::Str(template) => out.push_str(&expand(template, &re, &caps)?),
+                    func => {
+                        let m = match_object(&class, text, &caps, &re);
+                        match vm.call_value(func.clone(), vec![m])? {
+                            PyObject::Str(s) => out.push_str(&s),
+                            other => {
+                                return Err(format!(
+                                    "TypeError: expected str instance, {} found",
+                                    other.type_name()
+                                ));
+                            }
+                        }
+                    }
+                }
+                last = whole.end();
+            }
+            out.push_str(&text[last..]);
+
+            Ok(PyObject::Str(out))
+        }),
+    );
+
+    m
+}
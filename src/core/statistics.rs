@@ -0,0 +1,159 @@
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+use crate::core::globs::{iterable_items, sum};
+use crate::object::PyObject;
+use crate::vm::check_hashable;
+
+/// The data argument as numbers, each kept alongside its value as a float.
+fn numbers(func: &str, data: &PyObject) -> Result<Vec<(PyObject, f64)>, String> {
+    iterable_items(data)?
+        .into_iter()
+        .map(|v| {
+            let x = match &v {
+                PyObject::Int(n) => *n as f64,
+                PyObject::Bool(b) => *b as i64 as f64,
+                PyObject::Float(f) => *f,
+                other => {
+                    return Err(format!(
+                        "TypeError: can't convert type '{}' to numerator/denominator in {}()",
+                        other.type_name(),
+                        func
+                    ));
+                }
+            };
+            Ok((v, x))
+        })
+        .collect()
+}
+
+fn all_ints(data: &[(PyObject, f64)]) -> Option<Vec<i128>> {
+    data.iter()
+        .map(|(v, _)| match v {
+            PyObject::Int(n) => Some(*n as i128),
+            PyObject::Bool(b) => Some(*b as i128),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `num / den`, as an int when ints divide evenly, as Python's exact
+/// arithmetic would give.
+fn quotient(num: i128, den: i128) -> PyObject {
+    match i64::try_from(num / den) {
+        Ok(q) if num % den == 0 => PyObject::Int(q),
+        _ => PyObject::Float(num as f64 / den as f64),
+    }
+}
+
+/// Exact sample variance of ints, or None if the sums leave i128. Deviations
+/// are taken from the first value, which doesn't change the variance but
+/// keeps large, close values small.
+fn int_variance(ints: &[i128]) -> Option<PyObject> {
+    let first = *ints.first()?;
+    let (total, squares) = ints.iter().try_fold((0i128, 0i128), |(s, q), x| {
+        let d = x - first;
+        Some((s.checked_add(d)?, q.checked_add(d.checked_mul(d)?)?))
+    })?;
+    let n = ints.len() as i128;
+    let num = n
+        .checked_mul(squares)?
+        .checked_sub(total.checked_mul(total)?)?;
+    Some(quotient(num, n * (n - 1)))
+}
+
+fn mean(data: &PyObject) -> Result<PyObject, String> {
+    let data = numbers("mean", data)?;
+    if data.is_empty() {
+        return Err("StatisticsError: mean requires at least one data point".to_string());
+    }
+
+    let n = data.len();
+    match sum(data.into_iter().map(|(v, _)| v).collect(), PyObject::Int(0))? {
+        PyObject::Int(total) => Ok(quotient(total as i128, n as i128)),
+        PyObject::Float(total) => Ok(PyObject::Float(total / n as f64)),
+        other => Ok(other),
+    }
+}
+
+fn median(data: &PyObject) -> Result<PyObject, String> {
+    let mut data = numbers("median", data)?;
+    if data.is_empty() {
+        return Err("StatisticsError: no median for empty data".to_string());
+    }
+
+    data.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    let mid = data.len() / 2;
+    if data.len() % 2 == 1 {
+        Ok(data[mid].0.clone())
+    } else {
+        Ok(PyObject::Float((data[mid - 1].1 + data[mid].1) / 2.0))
+    }
+}
+
+fn mode(data: &PyObject) -> Result<PyObject, String> {
+    let mut counts: IndexMap<PyObject, usize> = IndexMap::new();
+    for v in iterable_items(data)? {
+        check_hashable(&v)?;
+        *counts.entry(v).or_default() += 1;
+    }
+
+    // the first value seen wins ties
+    let max = counts.values().copied().max();
+    counts
+        .into_iter()
+        .find(|(_, count)| Some(*count) == max)
+        .map(|(v, _)| v)
+        .ok_or_else(|| "StatisticsError: no mode for empty data".to_string())
+}
+
+/// Sample variance: the squared deviations from the mean over `n - 1`.
+fn variance(func: &str, data: &PyObject) -> Result<PyObject, String> {
+    let data = numbers(func, data)?;
+    let n = data.len();
+    if n < 2 {
+        return Err(format!(
+            "StatisticsError: {} requires at least two data points",
+            func
+        ));
+    }
+
+    if let Some(v) = all_ints(&data).and_then(|ints| int_variance(&ints)) {
+        return Ok(v);
+    }
+
+    let m = data.iter().map(|(_, x)| x).sum::<f64>() / n as f64;
+    let ss: f64 = data.iter().map(|(_, x)| (x - m) * (x - m)).sum();
+    Ok(PyObject::Float(ss / (n - 1) as f64))
+}
+
+pub fn statistics_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "mean".to_string(),
+        PyObject::native_fn("mean", 1, |args| mean(&args[0])),
+    );
+    m.insert(
+        "median".to_string(),
+        PyObject::native_fn("median", 1, |args| median(&args[0])),
+    );
+    m.insert(
+        "mode".to_string(),
+        PyObject::native_fn("mode", 1, |args| mode(&args[0])),
+    );
+    m.insert(
+        "variance".to_string(),
+        PyObject::native_fn("variance", 1, |args| variance("variance", &args[0])),
+    );
+    m.insert(
+        "stdev".to_string(),
+        PyObject::native_fn("stdev", 1, |args| match variance("stdev", &args[0])? {
+            PyObject::Int(v) => Ok(PyObject::Float((v as f64).sqrt())),
+            PyObject::Float(v) => Ok(PyObject::Float(v.sqrt())),
+            other => Ok(other),
+        }),
+    );
+
+    m
+}
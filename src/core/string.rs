@@ -0,0 +1,27 @@
+use crate::object::PyObject;
+use std::collections::HashMap;
+
+pub fn string_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    let lowercase = "abcdefghijklmnopqrstuvwxyz";
+    let uppercase = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+    for (name, value) in [
+        ("ascii_lowercase", lowercase.to_string()),
+        ("ascii_uppercase", uppercase.to_string()),
+        ("ascii_letters", format!("{}{}", lowercase, uppercase)),
+        ("digits", "0123456789".to_string()),
+        ("hexdigits", "0123456789abcdefABCDEF".to_string()),
+        ("octdigits", "01234567".to_string()),
+        (
+            "punctuation",
+            r##"!"#$%&'()*+,-./:;<=>?@[\]^_`{|}~"##.to_string(),
+        ),
+        ("whitespace", " \t\n\r\x0b\x0c".to_string()),
+    ] {
+        m.insert(name.to_string(), PyObject::Str(value));
+    }
+
+    m
+}
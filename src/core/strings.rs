@@ -0,0 +1,431 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::object::{PyNativeFunction, PyObject};
+
+/// Looks up `name` on a `str` value, returning the method bound to `s`.
+pub(crate) fn method(s: &str, name: &str) -> Option<PyObject> {
+    let s = s.to_string();
+
+    let func: Rc<dyn Fn(&[PyObject]) -> Result<PyObject, String>> = match name {
+        "format" => Rc::new(move |args| {
+            let (positional, kwargs) = PyObject::split_kwargs(args);
+            let lookup = |key: &str| kwargs.and_then(|kw| kw.get(key)).cloned();
+            format(&s, positional, &lookup).map(PyObject::Str)
+        }),
+        "format_map" => Rc::new(move |args| {
+            let [PyObject::Dict(map)] = args else {
+                return Err("TypeError: format_map() argument must be a dict".to_string());
+            };
+            let lookup = |key: &str| map.borrow().get(&PyObject::from(key)).cloned();
+            format(&s, &[], &lookup).map(PyObject::Str)
+        }),
+        "encode" => Rc::new(move |args| crate::core::bytes::encode(&s, args)),
+        "splitlines" => Rc::new(move |args| {
+            let keepends = match args {
+                [] => false,
+                [PyObject::Bool(b)] => *b,
+                [PyObject::Int(n)] => *n != 0,
+                _ => return Err(arg_error("splitlines", args)),
+            };
+            let lines = splitlines(&s, keepends).into_iter().map(PyObject::Str);
+            Ok(PyObject::List(Rc::new(RefCell::new(lines.collect()))))
+        }),
+        "title" => Rc::new(move |args| {
+            if !args.is_empty() {
+                return Err(arg_error("title", args));
+            }
+
+            let mut prev_cased = false;
+            let mut out = String::with_capacity(s.len());
+            for c in s.chars() {
+                if prev_cased {
+                    out.extend(c.to_lowercase());
+                } else {
+                    out.extend(c.to_uppercase());
+                }
+                prev_cased = c.is_alphabetic();
+            }
+            Ok(PyObject::Str(out))
+        }),
+        "capitalize" => Rc::new(move |args| {
+            if !args.is_empty() {
+                return Err(arg_error("capitalize", args));
+            }
+
+            let mut chars = s.chars();
+            let out = match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.as_str().to_lowercase().chars())
+                    .collect(),
+                None => String::new(),
+            };
+            Ok(PyObject::Str(out))
+        }),
+        "zfill" => Rc::new(move |args| {
+            let [PyObject::Int(width)] = args else {
+                return Err(arg_error("zfill", args));
+            };
+
+            let pad = (*width).max(0) as usize;
+            let pad = pad.saturating_sub(s.chars().count());
+            let out = match s.strip_prefix(['+', '-']) {
+                Some(rest) => format!("{}{}{}", &s[..1], "0".repeat(pad), rest),
+                None => format!("{}{}", "0".repeat(pad), s),
+            };
+            Ok(PyObject::Str(out))
+        }),
+        "center" | "ljust" | "rjust" => {
+            let name = name.to_string();
+            Rc::new(move |args| {
+                let (width, fill) = match args {
+                    [PyObject::Int(width)] => (*width, ' '),
+                    [PyObject::Int(width), PyObject::Str(fill)] if fill.chars().count() == 1 => {
+                        (*width, fill.chars().next().unwrap_or(' '))
+                    }
+                    _ => return Err(arg_error(&name, args)),
+                };
+
+                let len = s.chars().count();
+                let marg = (width.max(0) as usize).saturating_sub(len);
+                let left = match name.as_str() {
+                    "ljust" => 0,
+                    "rjust" => marg,
+                    // CPython's rounding for odd margins
+                    _ => marg / 2 + (marg & width as usize & 1),
+                };
+
+                let fill = |n: usize| fill.to_string().repeat(n);
+                Ok(PyObject::Str(format!(
+                    "{}{}{}",
+                    fill(left),
+                    s,
+                    fill(marg - left)
+                )))
+            })
+        }
+        _ => return None,
+    };
+
+    Some(PyObject::NativeFunction(Rc::new(PyNativeFunction {
+        name: format!("str.{}", name),
+        arity: usize::MAX,
+        func,
+    })))
+}
+
+fn arg_error(method: &str, args: &[PyObject]) -> String {
+    format!(
+        "TypeError: invalid arguments for str.{}(): ({})",
+        method,
+        args.iter()
+            .map(|a| a.type_name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Splits on the line boundaries `str.splitlines` recognises, treating
+/// `\r\n` as a single break.
+fn splitlines(s: &str, keepends: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let is_break = matches!(
+            c,
+            '\n' | '\r'
+                | '\x0b'
+                | '\x0c'
+                | '\x1c'
+                | '\x1d'
+                | '\x1e'
+                | '\u{85}'
+                | '\u{2028}'
+                | '\u{2029}'
+        );
+
+        if !is_break {
+            line.push(c);
+            continue;
+        }
+
+        if keepends {
+            line.push(c);
+        }
+        if c == '\r' && chars.peek() == Some(&'\n') {
+            chars.next();
+            if keepends {
+                line.push('\n');
+            }
+        }
+        lines.push(std::mem::take(&mut line));
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// `str.format` over `template`, resolving numbered and automatic fields from
+/// `positional` and named fields through `named`.
+pub(crate) fn format(
+    template: &str,
+    positional: &[PyObject],
+    named: &dyn Fn(&str) -> Option<PyObject>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    let mut auto_index = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut field = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err("ValueError: expected '}' before end of string".to_string());
+                        }
+                    }
+                }
+
+                let (field, spec) = field.split_once(':').unwrap_or((&field, ""));
+                let (name, conversion) = match field.split_once('!') {
+                    Some((name, conv)) => (name, Some(conv)),
+                    None => (field, None),
+                };
+
+                let value = if name.is_empty() {
+                    auto_index += 1;
+                    positional_arg(positional, auto_index - 1)?
+                } else if let Ok(index) = name.parse::<usize>() {
+                    positional_arg(positional, index)?
+                } else {
+                    named(name).ok_or_else(|| format!("KeyError: '{}'", name))?
+                };
+
+                let value = match conversion {
+                    None | Some("s") => value,
+                    Some("r") => PyObject::Str(value.repr()),
+                    Some(other) => {
+                        return Err(format!(
+                            "ValueError: Unknown conversion specifier {}",
+                            other
+                        ));
+                    }
+                };
+
+                out.push_str(&format_spec(&value, spec)?);
+            }
+            '}' => {
+                return Err("ValueError: Single '}' encountered in format string".to_string());
+            }
+            c => out.push(c),
+        }
+    }
+
+    Ok(out)
+}
+
+fn positional_arg(positional: &[PyObject], index: usize) -> Result<PyObject, String> {
+    positional.get(index).cloned().ok_or_else(|| {
+        format!(
+            "IndexError: Replacement index {} out of range for positional args tuple",
+            index
+        )
+    })
+}
+
+/// Applies a format spec of the form `[[fill]align][0][width][,][.precision][type]`.
+pub(crate) fn format_spec(value: &PyObject, spec: &str) -> Result<String, String> {
+    if spec.is_empty() {
+        return Ok(value.to_string());
+    }
+
+    let invalid = || {
+        format!(
+            "ValueError: Invalid format specifier '{}' for object of type '{}'",
+            spec,
+            value.type_name()
+        )
+    };
+
+    let chars: Vec<char> = spec.chars().collect();
+    let mut i = 0;
+    let is_align = |c: char| matches!(c, '<' | '>' | '^' | '=');
+
+    let (mut fill, mut align) = (' ', None);
+    if chars.len() >= 2 && is_align(chars[1]) {
+        fill = chars[0];
+        align = Some(chars[1]);
+        i = 2;
+    } else if is_align(chars[0]) {
+        align = Some(chars[0]);
+        i = 1;
+    }
+
+    if chars.get(i) == Some(&'0') && align.is_none() {
+        fill = '0';
+        align = Some('=');
+        i += 1;
+    }
+
+    let digits = |i: &mut usize| {
+        let start = *i;
+        while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+            *i += 1;
+        }
+        chars[start..*i]
+            .iter()
+            .collect::<String>()
+            .parse::<usize>()
+            .ok()
+    };
+
+    let width = digits(&mut i).unwrap_or(0);
+
+    let grouping = chars.get(i) == Some(&',');
+    if grouping {
+        i += 1;
+    }
+
+    let precision = if chars.get(i) == Some(&'.') {
+        i += 1;
+        Some(digits(&mut i).ok_or_else(invalid)?)
+    } else {
+        None
+    };
+
+    let ty = chars.get(i).copied();
+    if i + ty.map_or(0, |_| 1) != chars.len() {
+        return Err(invalid());
+    }
+
+    let as_float = |v: &PyObject| match v {
+        PyObject::Int(n) => Some(*n as f64),
+        PyObject::Float(f) => Some(*f),
+        PyObject::Bool(b) => Some(*b as i64 as f64),
+        _ => None,
+    };
+
+    let body = match (ty, value) {
+        (Some('f' | 'F'), v) => format!(
+            "{:.*}",
+            precision.unwrap_or(6),
+            as_float(v).ok_or_else(invalid)?
+        ),
+        (Some('%'), v) => format!(
+            "{:.*}%",
+            precision.unwrap_or(6),
+            as_float(v).ok_or_else(invalid)? * 100.0
+        ),
+        (Some('e'), v) => exponent_form(format!(
+            "{:.*e}",
+            precision.unwrap_or(6),
+            as_float(v).ok_or_else(invalid)?
+        )),
+        (Some('d') | None, PyObject::Int(n)) => n.to_string(),
+        // Python writes the sign before the digits of the magnitude
+        (Some(radix @ ('x' | 'X' | 'o' | 'b')), PyObject::Int(n)) => {
+            let abs = n.unsigned_abs();
+            let digits = match radix {
+                'x' => format!("{:x}", abs),
+                'X' => format!("{:X}", abs),
+                'o' => format!("{:o}", abs),
+                _ => format!("{:b}", abs),
+            };
+            if *n < 0 {
+                format!("-{}", digits)
+            } else {
+                digits
+            }
+        }
+        (None, PyObject::Float(f)) => match precision {
+            Some(p) => format!("{:.*}", p, f),
+            None => value.to_string(),
+        },
+        (Some('s') | None, PyObject::Str(s)) => match precision {
+            Some(p) => s.chars().take(p).collect(),
+            None => s.clone(),
+        },
+        (None, v) => v.to_string(),
+        _ => return Err(invalid()),
+    };
+
+    let body = if grouping {
+        group_thousands(&body)
+    } else {
+        body
+    };
+
+    let len = body.chars().count();
+    if len >= width {
+        return Ok(body);
+    }
+
+    let pad = width - len;
+    let numeric = matches!(value, PyObject::Int(_) | PyObject::Float(_));
+    let fill_str = |n: usize| fill.to_string().repeat(n);
+
+    Ok(match align.unwrap_or(if numeric { '>' } else { '<' }) {
+        '<' => body + &fill_str(pad),
+        '^' => fill_str(pad / 2) + &body + &fill_str(pad - pad / 2),
+        '=' => match body.strip_prefix('-') {
+            Some(rest) => format!("-{}{}", fill_str(pad), rest),
+            None => fill_str(pad) + &body,
+        },
+        _ => fill_str(pad) + &body,
+    })
+}
+
+/// Rewrites Rust's `1.23e3` exponent as Python's signed, two-digit `1.23e+03`.
+fn exponent_form(num: String) -> String {
+    match num.split_once('e') {
+        Some((mantissa, exp)) => {
+            let (sign, digits) = match exp.strip_prefix('-') {
+                Some(digits) => ('-', digits),
+                None => ('+', exp),
+            };
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        // inf and nan have no exponent
+        None => num,
+    }
+}
+
+fn group_thousands(num: &str) -> String {
+    let (sign, rest) = match num.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", num),
+    };
+    let (int, frac) = match rest.find('.') {
+        Some(dot) => rest.split_at(dot),
+        None => (rest, ""),
+    };
+
+    let mut grouped = String::new();
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    format!("{}{}{}", sign, grouped, frac)
+}
@@ -1,12 +1,12 @@
-use crate::{PyNativeFunction, PyObject};
+use crate::{PyNativeFunction, PyNativeModule, PyObject};
 use indexmap::IndexMap;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::env;
 use std::rc::Rc;
 
-pub fn sys_module() -> HashMap<String, PyObject> {
-    let argv = env::args().map(PyObject::Str).collect::<Vec<_>>();
+pub fn sys_module(argv: Vec<String>) -> HashMap<String, PyObject> {
+    let argv = argv.into_iter().map(PyObject::Str).collect::<Vec<_>>();
     let path = env::var("PYTHONPATH")
         .unwrap_or_default()
         .split(':')
@@ -61,30 +61,20 @@ pub fn sys_module() -> HashMap<String, PyObject> {
     );
     m.insert(
         "stdout".to_string(),
-        PyObject::NativeFunction(Rc::new(PyNativeFunction {
-            name: "stdout".to_string(),
-            arity: 0,
-            func: Rc::new(|_| Ok(PyObject::None)),
-        })),
+        stream("stdout", crate::core::io::write_stdout),
     );
     m.insert(
         "stderr".to_string(),
-        PyObject::NativeFunction(Rc::new(PyNativeFunction {
-            name: "stderr".to_string(),
-            arity: 0,
-            func: Rc::new(|_| Ok(PyObject::None)),
-        })),
+        stream("stderr", crate::core::io::write_stderr),
     );
     m.insert(
         "exit".to_string(),
         PyObject::NativeFunction(Rc::new(PyNativeFunction {
             name: "exit".to_string(),
-            arity: 1,
-            func: Rc::new(|args| {
-                if let PyObject::Int(code) = args[0] {
-                    std::process::exit(code as i32);
-                }
-                Err("bad args".to_string())
+            arity: usize::MAX,
+            func: Rc::new(|args| match args.first() {
+                None => Err("SystemExit: 0".to_string()),
+                Some(code) => Err(format!("SystemExit: {}", code)),
             }),
         })),
     );
@@ -103,8 +93,8 @@ pub fn sys_module() -> HashMap<String, PyObject> {
                             + l.borrow().len() * std::mem::size_of::<PyObject>()
                     }
                     PyObject::Dict(d) => {
-                        std::mem::size_of::<Rc<RefCell<HashMap<String, PyObject>>>>()
-                            + d.borrow().len() * std::mem::size_of::<(String, PyObject)>()
+                        std::mem::size_of::<Rc<RefCell<IndexMap<PyObject, PyObject>>>>()
+                            + d.borrow().len() * std::mem::size_of::<(PyObject, PyObject)>()
                     }
                     PyObject::NativeFunction(_) => std::mem::size_of::<Rc<PyNativeFunction>>(),
                     PyObject::NativeModule(_) => std::mem::size_of::<Rc<crate::PyNativeModule>>(),
@@ -129,3 +119,40 @@ pub fn sys_module() -> HashMap<String, PyObject> {
 
     m
 }
+
+/// A file-like object whose `write` goes through `write`.
+fn stream(name: &str, write: fn(&str) -> Result<(), String>) -> PyObject {
+    let mut dict = HashMap::new();
+
+    dict.insert(
+        "write".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "write".to_string(),
+            arity: 1,
+            func: Rc::new(move |args| match &args[0] {
+                PyObject::Str(s) => {
+                    write(s)?;
+                    Ok(PyObject::Int(s.chars().count() as i64))
+                }
+                other => Err(format!(
+                    "TypeError: write() argument must be str, not {}",
+                    other.type_name()
+                )),
+            }),
+        })),
+    );
+    dict.insert(
+        "flush".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "flush".to_string(),
+            arity: 0,
+            // every write is already flushed
+            func: Rc::new(|_| Ok(PyObject::None)),
+        })),
+    );
+
+    PyObject::NativeModule(Rc::new(PyNativeModule {
+        name: name.to_string(),
+        dict,
+    }))
+}
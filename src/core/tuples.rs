@@ -0,0 +1,71 @@
+use crate::object::PyObject;
+use crate::vm::Vm;
+
+/// How many items of `items` equal `x`, comparing with `__eq__` like `==`.
+pub(crate) fn count(vm: &mut Vm, items: &[PyObject], x: &PyObject) -> Result<PyObject, String> {
+    let mut n = 0;
+    for item in items {
+        if vm.eq_values(item.clone(), x.clone())? {
+            n += 1;
+        }
+    }
+    Ok(PyObject::Int(n))
+}
+
+/// Position of the first item equal to `args[0]` within the optional
+/// `start`/`stop` bounds of `args[1..]`, or None if there is none.
+pub(crate) fn index(
+    vm: &mut Vm,
+    method: &str,
+    items: &[PyObject],
+    args: &[PyObject],
+) -> Result<Option<usize>, String> {
+    let len = items.len() as i64;
+    let bound = |v: Option<&PyObject>, default: i64| match v {
+        None => Ok(default),
+        Some(PyObject::Int(i)) if *i < 0 => Ok((len + i).max(0)),
+        Some(PyObject::Int(i)) => Ok((*i).min(len)),
+        Some(other) => Err(format!(
+            "TypeError: slice indices must be integers or have an __index__ method, not {}",
+            other.type_name()
+        )),
+    };
+
+    let (x, start, stop) = match args {
+        [x, rest @ ..] if rest.len() <= 2 => (x, bound(rest.first(), 0)?, bound(rest.get(1), len)?),
+        _ => {
+            return Err(format!(
+                "TypeError: {} expected between 1 and 3 arguments, got {}",
+                method,
+                args.len()
+            ));
+        }
+    };
+
+    for i in start..stop {
+        if vm.eq_values(items[i as usize].clone(), x.clone())? {
+            return Ok(Some(i as usize));
+        }
+    }
+    Ok(None)
+}
+
+/// Looks up `name` on a `tuple` value, returning the method bound to `t`.
+pub(crate) fn method(t: &[PyObject], name: &str) -> Option<PyObject> {
+    let t = t.to_vec();
+
+    match name {
+        "count" => Some(PyObject::vm_fn("tuple.count", 1, move |vm, args| {
+            count(vm, &t, &args[0])
+        })),
+        "index" => Some(PyObject::vm_fn(
+            "tuple.index",
+            usize::MAX,
+            move |vm, args| match index(vm, "index", &t, args)? {
+                Some(i) => Ok(PyObject::Int(i as i64)),
+                None => Err("ValueError: tuple.index(x): x not in tuple".to_string()),
+            },
+        )),
+        _ => None,
+    }
+}
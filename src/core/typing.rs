@@ -0,0 +1,53 @@
+use crate::object::{PyObject, PyType};
+use std::collections::HashMap;
+
+/// Placeholder for a `typing` name. Annotations are never evaluated, so all
+/// these need to do is exist and, via `load_index`, be subscriptable.
+fn special_form(name: &str) -> PyObject {
+    PyObject::Type(PyType {
+        name: format!("typing.{}", name),
+    })
+}
+
+pub(crate) fn is_special_form(t: &PyType) -> bool {
+    t.name.starts_with("typing.")
+}
+
+pub fn typing_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    for name in [
+        "Any",
+        "Callable",
+        "ClassVar",
+        "Dict",
+        "Final",
+        "FrozenSet",
+        "Generator",
+        "Iterable",
+        "Iterator",
+        "List",
+        "Literal",
+        "Mapping",
+        "Optional",
+        "Sequence",
+        "Set",
+        "Tuple",
+        "Type",
+        "Union",
+    ] {
+        m.insert(name.to_string(), special_form(name));
+    }
+
+    m.insert("TYPE_CHECKING".to_string(), PyObject::Bool(false));
+    m.insert(
+        "cast".to_string(),
+        PyObject::native_fn("cast", 2, |args| Ok(args[1].clone())),
+    );
+    m.insert(
+        "TypeVar".to_string(),
+        PyObject::native_fn("TypeVar", usize::MAX, |_| Ok(special_form("TypeVar"))),
+    );
+
+    m
+}
@@ -0,0 +1,27 @@
+use crate::object::{PyNativeFunction, PyObject, PyWeakRef};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub fn weakref_module() -> HashMap<String, PyObject> {
+    let mut m = HashMap::new();
+
+    m.insert(
+        "ref".to_string(),
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: "ref".to_string(),
+            arity: 1,
+            func: Rc::new(|args| {
+                PyWeakRef::new(&args[0])
+                    .map(PyObject::WeakRef)
+                    .ok_or_else(|| {
+                        format!(
+                            "TypeError: cannot create weak reference to '{}' object",
+                            args[0].type_name()
+                        )
+                    })
+            }),
+        })),
+    );
+
+    m
+}
@@ -0,0 +1,110 @@
+use crate::object::*;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// A container the VM has handed out. Only containers can form reference
+/// cycles, so they are the only objects the collector needs to know about.
+pub(crate) type Tracked = PyWeakRef;
+
+fn identity(obj: &PyObject) -> Option<(usize, usize)> {
+    match obj {
+        PyObject::List(l) => Some((Rc::as_ptr(l) as *const u8 as usize, Rc::strong_count(l))),
+        PyObject::Dict(d) => Some((Rc::as_ptr(d) as *const u8 as usize, Rc::strong_count(d))),
+        PyObject::Set(s) => Some((Rc::as_ptr(s) as *const u8 as usize, Rc::strong_count(s))),
+        PyObject::Instance(i) => Some((Rc::as_ptr(i) as *const u8 as usize, Rc::strong_count(i))),
+        _ => None,
+    }
+}
+
+/// Calls `visit` for every container directly held by `obj`, looking through
+/// tuples since those are stored inline.
+fn for_each_child(obj: &PyObject, visit: &mut dyn FnMut(&PyObject)) {
+    fn walk(obj: &PyObject, visit: &mut dyn FnMut(&PyObject)) {
+        match obj {
+            PyObject::Tuple(items) => items.iter().for_each(|x| walk(x, visit)),
+            other => visit(other),
+        }
+    }
+
+    match obj {
+        PyObject::List(l) => l.borrow().iter().for_each(|x| walk(x, visit)),
+        PyObject::Dict(d) => d.borrow().iter().for_each(|(k, v)| {
+            walk(k, visit);
+            walk(v, visit);
+        }),
+        PyObject::Set(s) => s.borrow().iter().for_each(|x| walk(x, visit)),
+        PyObject::Instance(i) => i.borrow().attrs.values().for_each(|x| walk(x, visit)),
+        _ => {}
+    }
+}
+
+fn clear(obj: &PyObject) {
+    match obj {
+        PyObject::List(l) => l.borrow_mut().clear(),
+        PyObject::Dict(d) => d.borrow_mut().clear(),
+        PyObject::Set(s) => s.borrow_mut().clear(),
+        PyObject::Instance(i) => i.borrow_mut().attrs.clear(),
+        _ => {}
+    }
+}
+
+/// Frees tracked containers that are only kept alive by each other.
+///
+/// An object whose strong count exceeds the references held by other tracked
+/// containers is referenced from somewhere else (a namespace, the stack, a
+/// native closure) and is treated as a root. Everything not reachable from a
+/// root is garbage: its contents are cleared, which breaks the cycle and lets
+/// `Rc` free it. Returns the number of containers collected.
+pub(crate) fn collect(tracked: &mut Vec<Tracked>) -> usize {
+    let mut objects = Vec::new();
+    let mut index = HashMap::new();
+
+    for obj in tracked.drain(..).filter_map(|t| t.upgrade()) {
+        if let Some((id, _)) = identity(&obj) {
+            if !index.contains_key(&id) {
+                index.insert(id, objects.len());
+                objects.push(obj);
+            }
+        }
+    }
+
+    // discount the reference held by `objects` itself
+    let mut external: Vec<isize> = objects
+        .iter()
+        .map(|obj| identity(obj).map_or(0, |(_, count)| count as isize - 1))
+        .collect();
+
+    for obj in &objects {
+        for_each_child(obj, &mut |child| {
+            if let Some(&i) = identity(child).and_then(|(id, _)| index.get(&id)) {
+                external[i] -= 1;
+            }
+        });
+    }
+
+    let mut reachable = HashSet::new();
+    let mut pending: Vec<usize> = (0..objects.len()).filter(|&i| external[i] > 0).collect();
+
+    while let Some(i) = pending.pop() {
+        if reachable.insert(i) {
+            for_each_child(&objects[i], &mut |child| {
+                if let Some(&j) = identity(child).and_then(|(id, _)| index.get(&id)) {
+                    pending.push(j);
+                }
+            });
+        }
+    }
+
+    let mut collected = 0;
+
+    for (i, obj) in objects.iter().enumerate() {
+        if reachable.contains(&i) {
+            tracked.extend(Tracked::new(obj));
+        } else {
+            clear(obj);
+            collected += 1;
+        }
+    }
+
+    collected
+}
@@ -1,6 +1,7 @@
 mod ast;
 mod bytecode;
 mod core;
+mod gc;
 mod object;
 mod opcode;
 mod vm;
@@ -12,6 +13,13 @@ use ast::*;
 use object::*;
 use vm::*;
 
+pub use ast::Compiler;
+pub use bytecode::CodeObject;
+pub use object::{
+    PyClass, PyException, PyInstance, PyNativeFunction, PyObject, PyType, PyVmFunction, PyWeakRef,
+};
+pub use vm::{ExecStats, Vm, error_message, exit_code, format_traceback};
+
 pub fn execute(
     source: &str,
     natives: &[(
@@ -230,8 +238,9 @@ mod tests {
     #[test]
     fn set_creation() {
         let r = execute("{1, 2, 3}", &[], &[], &[]).unwrap();
-        let result = format!("{}", r);
-        assert!(result.contains("1") && result.contains("2") && result.contains("3"));
+        assert_eq!(format!("{}", r), "{1, 2, 3}");
+        let r = execute("{3, 1, 2}", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "{3, 1, 2}");
     }
 
     // @todo: should probably be a set class
@@ -245,8 +254,9 @@ mod tests {
     #[test]
     fn set_deduplication() {
         let r = execute("{1, 2, 2, 3, 1}", &[], &[], &[]).unwrap();
-        let result = format!("{}", r);
-        assert!(result.len() < 15);
+        assert_eq!(format!("{}", r), "{1, 2, 3}");
+        let r = execute("{'b', 'a', 'b'}", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "{'b', 'a'}");
     }
 
     #[test]
@@ -258,7 +268,58 @@ mod tests {
     #[test]
     fn mixed_types() {
         let r = execute("(1, 'hello', [2, 3])", &[], &[], &[]).unwrap();
-        assert_eq!(format!("{}", r), "(1, hello, [2, 3])");
+        assert_eq!(format!("{}", r), "(1, 'hello', [2, 3])");
+    }
+
+    #[test]
+    fn repr_escapes() {
+        let r = execute("repr('a\\nb')", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "'a\\nb'");
+        let r = execute("repr(\"it's\")", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "\"it's\"");
+        let r = execute("['a\\tb', 'c\\\\d']", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "['a\\tb', 'c\\\\d']");
+    }
+
+    #[test]
+    fn traceback_includes_line() {
+        let code = Compiler::default()
+            .compile("x = 1\ny = 2\nz = x + w")
+            .unwrap();
+        let mut vm = Vm::default().with_builtins();
+        let err = vm.run(&code).unwrap_err();
+        assert_eq!(err, "NameError: name 'w' is not defined");
+        let tb = format_traceback(&err, &code, vm.last_error_ip());
+        assert_eq!(tb.lines().count(), 3);
+        assert!(tb.contains("line 3"));
+        assert!(tb.ends_with("NameError: name 'w' is not defined"));
+    }
+
+    #[test]
+    fn list_comprehension() {
+        let r = execute("[x * 2 for x in range(4) if x != 2]", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "[0, 2, 6]");
+    }
+
+    #[test]
+    fn comprehension_variable_does_not_leak() {
+        let r = execute("x = 10\n[x for x in range(3)]\nx", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "10");
+
+        let err = execute("{y: 0 for y in range(3)}\ny", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "NameError: name 'y' is not defined");
+    }
+
+    #[test]
+    fn dict_comprehension() {
+        let r = execute("{x: x*x for x in range(3)}", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "{0: 0, 1: 1, 2: 4}");
+    }
+
+    #[test]
+    fn set_comprehension() {
+        let r = execute("{x for x in [4, 4, 4] if x > 1}", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "{4}");
     }
 
     #[test]
@@ -297,6 +358,18 @@ mod tests {
         assert_eq!(format!("{}", r), "10");
     }
 
+    #[test]
+    fn while_continue_increment() {
+        let r = execute(
+            include_str!("../test/while_continue_incr.py"),
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(6, 18)");
+    }
+
     #[test]
     fn for_loop_range() {
         let r = execute(include_str!("../test/for_range.py"), &[], &[], &[]).unwrap();
@@ -363,6 +436,18 @@ mod tests {
         assert_eq!(format!("{}", r), "8");
     }
 
+    #[test]
+    fn for_nested_break() {
+        let r = execute(include_str!("../test/for_nested_break.py"), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "6");
+    }
+
+    #[test]
+    fn for_return_inside_loop() {
+        let r = execute(include_str!("../test/for_return_in_loop.py"), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "6");
+    }
+
     #[test]
     fn range_negative_step() {
         let r = execute(include_str!("../test/for_range_neg_step.py"), &[], &[], &[]).unwrap();
@@ -444,29 +529,109 @@ mod tests {
         assert_eq!(format!("{}", r), "3");
     }
 
+    #[test]
+    fn module_function_shares_namespace() {
+        std::fs::write(
+            "test_counter.py",
+            "state = [0]\ndef inc():\n  state[0] = state[0] + 1\n  return state[0]",
+        )
+        .unwrap();
+        let r = execute(
+            "from test_counter import inc\ninc()\ninc()\nimport test_counter\ntest_counter.inc()\ntest_counter.state[0]",
+            &[],
+            &[],
+            &[],
+        );
+        std::fs::remove_file("test_counter.py").unwrap();
+        assert_eq!(format!("{}", r.unwrap()), "3");
+    }
+
     #[test]
     fn native_module() {
         let mut dict = HashMap::new();
-        dict.insert("PI".to_string(), PyObject::Float(3.14159));
+        dict.insert("PI".to_string(), PyObject::float(3.14159));
         dict.insert(
             "add".to_string(),
-            PyObject::NativeFunction(Rc::new(PyNativeFunction {
-                name: "add".to_string(),
-                arity: 2,
-                func: Rc::new(|args| {
-                    if let (PyObject::Int(a), PyObject::Int(b)) = (&args[0], &args[1]) {
-                        Ok(PyObject::Int(a + b))
-                    } else {
-                        Err("bad args".to_string())
-                    }
-                }),
-            })),
+            PyObject::native_fn("add", 2, |args| match (&args[0], &args[1]) {
+                (PyObject::Int(a), PyObject::Int(b)) => Ok(PyObject::int(a + b)),
+                _ => Err("bad args".to_string()),
+            }),
         );
 
         let r = execute("import math\nmath.add(2, 3)", &[], &[("math", dict)], &[]).unwrap();
         assert_eq!(format!("{}", r), "5");
     }
 
+    #[test]
+    fn object_constructors() {
+        let mut dict = HashMap::new();
+        dict.insert(
+            "items".to_string(),
+            PyObject::list(vec![PyObject::int(1), PyObject::str("two")]),
+        );
+        dict.insert(
+            "table".to_string(),
+            PyObject::dict([("a", PyObject::int(1)), ("b", PyObject::float(2.5))]),
+        );
+
+        let r = execute(
+            "import cfg\n(cfg.items, cfg.table['b'])",
+            &[],
+            &[("cfg", dict)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "([1, 'two'], 2.5)");
+    }
+
+    #[test]
+    fn math_modf_trunc_copysign() {
+        let r = execute("import math\nmath.modf(3.5)", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(0.5, 3.0)");
+        let r = execute("import math\nmath.trunc(-2.7)", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "-2");
+        let r = execute("import math\nmath.trunc(True)", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "1");
+        let err = execute("import math\nmath.trunc(math.inf)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "OverflowError: cannot convert float infinity to integer"
+        );
+        let err = execute("import math\nmath.trunc(math.nan)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "ValueError: cannot convert float NaN to integer"
+        );
+        let r = execute("import math\nmath.copysign(1.5, -0.0)", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "-1.5");
+        let r = execute(
+            "import math\n(math.modf(math.inf), math.modf(-math.inf), math.isnan(True), math.copysign(True, -1))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "((0.0, inf), (-0.0, -inf), False, -1.0)");
+        let r = execute(
+            "import math\n(math.isnan(math.nan), math.isinf(math.inf), math.isinf(1.0))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, True, False)");
+    }
+
+    #[test]
+    fn sys_exit_returns_to_caller() {
+        let err = execute("import sys\nsys.exit(2)\n1", &[], &[], &[]).unwrap_err();
+        assert_eq!(exit_code(&err), Some(2));
+        let err = execute("import sys\nsys.exit()", &[], &[], &[]).unwrap_err();
+        assert_eq!(exit_code(&err), Some(0));
+        let err = execute("x = [][0]", &[], &[], &[]).unwrap_err();
+        assert_eq!(exit_code(&err), None);
+    }
+
     #[test]
     fn native_class_instantiation() {
         use std::cell::RefCell;
@@ -488,7 +653,7 @@ mod tests {
             let instance = PyInstance {
                 class: Rc::new(PyClass {
                     name: "Point".to_string(),
-                    methods: HashMap::new(),
+                    methods: HashMap::new().into(),
                     bases: Vec::new(),
                 }),
                 attrs,
@@ -506,4 +671,2142 @@ mod tests {
         .unwrap();
         assert_eq!(format!("{}", r), "1");
     }
+
+    #[test]
+    fn range_membership() {
+        let r = execute(
+            "(50 in range(0, 100, 2), 51 in range(0, 100, 2), 3 in range(5, 0, -1))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, False, True)");
+    }
+
+    #[test]
+    fn huge_ranges() {
+        let src = "big = 9223372036854775807\n(len(range(0, big, 2)), big - 1 in range(-big, big), 0 in range(big, -big, -big))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(4611686018427387904, True, True)");
+
+        let err = execute(
+            "len(range(-9223372036854775807, 9223372036854775807))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "OverflowError: Python int too large to convert to C ssize_t"
+        );
+    }
+
+    #[test]
+    fn not_in_membership() {
+        let r = execute(
+            "(2 not in [1, 2], 'ell' in 'hello', 'a' in {'a': 1}, 4 not in (1, 2))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(False, True, True, True)");
+    }
+
+    #[test]
+    fn try_except_matches_type() {
+        let r = execute(include_str!("../test/try_except_multi.py"), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "key");
+    }
+
+    #[test]
+    fn try_except_binding_and_bare() {
+        let src = "try:\n  raise ValueError('bad')\nexcept (TypeError, ValueError) as e:\n  a = e\ntry:\n  missing\nexcept:\n  b = 'caught'\n(a, b)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(bad, 'caught')");
+    }
+
+    #[test]
+    fn try_except_unmatched_propagates() {
+        let err = execute(
+            "try:\n  raise KeyError('k')\nexcept ValueError:\n  pass",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error_message(&err), "KeyError: k");
+    }
+
+    #[test]
+    fn try_except_from_function() {
+        let src = "def f(x):\n  return {}[x]\ntry:\n  r = f(0)\nexcept LookupError:\n  r = -1\nr";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "-1");
+    }
+
+    #[test]
+    fn bare_raise_outside_handler() {
+        let src = "try:\n  raise ValueError('a')\nexcept ValueError:\n  pass\nraise";
+        let err = execute(src, &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "RuntimeError: No active exception to reraise"
+        );
+
+        // a nested handler finishing restores the outer one
+        let src = "try:\n  raise ValueError('a')\nexcept ValueError:\n  try:\n    raise KeyError('k')\n  except KeyError:\n    pass\n  raise";
+        let err = execute(src, &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "ValueError: a");
+    }
+
+    #[test]
+    fn finally_runs_on_every_exit() {
+        let src = "log = []\ntry:\n  log.append(1)\nfinally:\n  log.append(2)\nlog";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "[1, 2]");
+
+        let src = "log = []\ntry:\n  try:\n    raise ValueError('v')\n  finally:\n    log.append('f')\nexcept ValueError:\n  log.append('e')\nlog";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "['f', 'e']");
+
+        let src = "log = []\ndef f():\n  try:\n    return 1\n  finally:\n    log.append('f')\ndef g():\n  try:\n    raise KeyError('k')\n  except KeyError:\n    return 2\n  finally:\n    log.append('g')\n(f(), g(), log)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(1, 2, ['f', 'g'])");
+
+        let src = "log = []\nfor i in range(4):\n  try:\n    if i == 1:\n      continue\n    if i == 2:\n      break\n    log.append(i)\n  finally:\n    log.append('f')\nlog";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "[0, 'f', 'f', 'f']");
+    }
+
+    #[test]
+    fn raise_non_exception_callable() {
+        let err = execute("raise len", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: exceptions must derive from BaseException"
+        );
+    }
+
+    #[test]
+    fn collect_cycles_frees_unreachable_lists() {
+        let mut compiler = Compiler::default();
+        let code = compiler
+            .compile("for i in range(100):\n  a = [0]\n  link(a, a)\n")
+            .unwrap();
+        let mut vm = Vm::default().with_builtins();
+        vm.register_native("link", 2, |args| {
+            if let PyObject::List(l) = &args[0] {
+                l.borrow_mut().push(args[1].clone());
+            }
+            Ok(PyObject::None)
+        });
+        vm.run(&code).unwrap();
+
+        // every list but the one still bound to `a` is an unreachable cycle
+        assert_eq!(vm.collect_cycles(), 99);
+        assert_eq!(vm.collect_cycles(), 0);
+    }
+
+    #[test]
+    fn function_param_rebound_as_local() {
+        let r = execute(include_str!("../test/func_param_rebind.py"), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(2, 3, 100)");
+    }
+
+    #[test]
+    fn bool_display_capitalized() {
+        let r = execute(
+            "([True, False], str(True) == 'True', str(False))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "([True, False], True, 'False')");
+    }
+
+    #[test]
+    fn none_equality_and_ordering() {
+        let r = execute("([None, 1], None == None, None != 0)", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "([None, 1], True, True)");
+
+        let err = execute("None < 1", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: '<' not supported between instances of 'NoneType' and 'int'"
+        );
+    }
+
+    #[test]
+    fn walrus_and_augmented_assignment() {
+        let src = "x = 5\nn = 0\nwhile (x := x - 1) > 0:\n  n += x\ny = (z := 5) + 1\n(n, x, y, z)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(10, 0, 6, 5)");
+    }
+
+    #[test]
+    fn bool_arithmetic_coerces_to_int() {
+        let r = execute(
+            "(True + True == 2, False * 5 == 0, True / 2, True < 2, 1 - True)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, True, 0.5, True, 0)");
+    }
+
+    #[test]
+    fn vm_reset_keeps_natives() {
+        let mut vm = Vm::default().with_builtins();
+        vm.register_native("double", 1, |args| match &args[0] {
+            PyObject::Int(x) => Ok(PyObject::Int(x * 2)),
+            _ => Err("TypeError: expected int".to_string()),
+        });
+
+        let first = Compiler::default().compile("a = double(4)\na").unwrap();
+        assert_eq!(format!("{}", vm.run(&first).unwrap()), "8");
+
+        vm.reset();
+
+        let missing = Compiler::default().compile("a").unwrap();
+        let err = vm.run(&missing).unwrap_err();
+        assert_eq!(error_message(&err), "NameError: name 'a' is not defined");
+
+        let second = Compiler::default().compile("double(5)").unwrap();
+        assert_eq!(format!("{}", vm.run(&second).unwrap()), "10");
+    }
+
+    #[test]
+    fn module_attribute_assignment() {
+        std::fs::write("test_patch.py", "x = 1\ndef get():\n  return x").unwrap();
+        let r = execute(
+            "import test_patch\ntest_patch.x = 5\n(test_patch.x, test_patch.get())",
+            &[],
+            &[],
+            &[],
+        );
+        std::fs::remove_file("test_patch.py").unwrap();
+        assert_eq!(format!("{}", r.unwrap()), "(5, 5)");
+    }
+
+    #[test]
+    fn iter_and_next() {
+        let r = execute(
+            "it = iter([1, 2])\na = next(it)\nb = next(it)\n(a, b, next(it, 'done'))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(1, 2, 'done')");
+
+        let err = execute("it = iter([1])\nnext(it)\nnext(it)", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "StopIteration");
+    }
+
+    #[test]
+    fn for_loop_resumes_iterator() {
+        let src =
+            "it = iter(range(5))\nnext(it)\ntotal = 0\nfor x in it:\n  total = total + x\ntotal";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "10");
+    }
+
+    #[test]
+    fn starred_unpacking() {
+        let r = execute(
+            "first, *middle, last = [1, 2, 3, 4, 5]\n(first, middle, last)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(1, [2, 3, 4], 5)");
+
+        let r = execute("a, b = 1, 2\n*c, = (3,)\n(b, a, c)", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(2, 1, [3])");
+    }
+
+    #[test]
+    fn for_loop_unpacks_target() {
+        let src = "total = 0\nfor k, v in [(1, 2), (3, 4)]:\n  total = total + k * v\ntotal";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "14");
+    }
+
+    #[test]
+    fn unpacking_length_mismatch() {
+        let err = execute("a, b = [1, 2, 3]", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "ValueError: too many values to unpack (expected 2)"
+        );
+    }
+
+    #[test]
+    fn str_format_keywords_and_map() {
+        let r = execute(
+            "a = '{greet}, {who}'.format(greet='Hi', who='you')\nb = '{0}-{1}-{0}'.format('x', 'y')\nc = '{n} is {v:.2f}'.format_map({'n': 'pi', 'v': 3.14159})\n(a, b, c)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "('Hi, you', 'x-y-x', 'pi is 3.14')");
+
+        let r = execute(
+            "('{:x}'.format(-255), '{:X}'.format(255), '{:o}'.format(-8), '{:b}'.format(-5), \
+             '{:.2e}'.format(1234.5), '{:e}'.format(0.00012), '{:.1e}'.format(-1e100))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "('-ff', 'FF', '-10', '-101', '1.23e+03', '1.200000e-04', '-1.0e+100')"
+        );
+
+        let err = execute("'{missing}'.format_map({})", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "KeyError: 'missing'");
+    }
+
+    #[test]
+    fn keyword_arguments_to_functions() {
+        let src = "def sub(a, b):\n  return a - b\n(sub(b=1, a=5), sub(10, b=4))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(4, 6)");
+
+        let err = execute("def f(a):\n  return a\nf(c=1)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: f() got an unexpected keyword argument 'c'"
+        );
+    }
+
+    #[test]
+    fn execution_stats_count_instructions() {
+        let code = Compiler::default().compile("x = 1\ny = 2").unwrap();
+        let mut vm = Vm::default().with_builtins();
+        vm.enable_stats();
+        vm.run(&code).unwrap();
+
+        // LoadConst, StoreName, LoadConst, StoreName, LoadConst, Return
+        let stats = vm.stats().unwrap();
+        assert_eq!(stats.instructions, 6);
+        assert_eq!(stats.per_op["LoadConst"], 3);
+        assert_eq!(stats.per_op["StoreName"], 2);
+        assert_eq!(stats.per_op["Return"], 1);
+    }
+
+    #[test]
+    fn range_boundaries() {
+        let cases = [
+            ("range(5, 5)", "[]"),
+            ("range(5, 5, -1)", "[]"),
+            ("range(5, 0, -1)", "[5, 4, 3, 2, 1]"),
+            ("range(0, -5, -1)", "[0, -1, -2, -3, -4]"),
+            ("range(-3, 3)", "[-3, -2, -1, 0, 1, 2]"),
+            ("range(0, 10, 3)", "[0, 3, 6, 9]"),
+            ("range(10, 0, -4)", "[10, 6, 2]"),
+        ];
+
+        for (range, expected) in cases {
+            let r = execute(&format!("[x for x in {}]", range), &[], &[], &[]).unwrap();
+            assert_eq!(format!("{}", r), expected, "{}", range);
+        }
+    }
+
+    #[test]
+    fn complex_arithmetic() {
+        let r = execute(
+            "z = (1+2j) + (3+4j)\n(z == 4+6j, z, abs(3+4j) == 5.0, (1+2j) * (3-1j), z.real, -2j)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(True, (4+6j), True, (5+5j), 4.0, (-0-2j))"
+        );
+    }
+
+    #[test]
+    fn method_arity_errors() {
+        let class = "class C:\n  def m(self, a):\n    return a\nc = C()\n";
+
+        let r = execute(&format!("{}c.m(3)", class), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "3");
+
+        let err = execute(&format!("{}c.m()", class), &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: m() missing 1 required positional argument: 'a'"
+        );
+
+        let err = execute(&format!("{}c.m(1, 2)", class), &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: m() takes 2 positional arguments but 3 were given"
+        );
+    }
+
+    #[test]
+    fn class_object_exposes_methods() {
+        let src = "class C:\n  def make(n):\n    return n * 2\n  def get(self):\n    return self.v\nc = C()\nc.v = 7\n(C.make(4), C.get(c), c.get(), C)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(8, 7, 7, <class 'C'>)");
+    }
+
+    #[test]
+    fn annotated_assignment() {
+        let r = execute("count: int = 3\nname: str\ncount", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "3");
+
+        let err = execute("name: str\nname", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "NameError: name 'name' is not defined");
+    }
+
+    #[test]
+    fn import_aliases() {
+        let r = execute(
+            "import math as m\nfrom math import pi as P, sqrt\n(m.pi == P, sqrt(16.0))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, 4.0)");
+
+        let err = execute("import math as m\nmath", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "NameError: name 'math' is not defined");
+    }
+
+    #[test]
+    fn dotted_imports() {
+        std::fs::create_dir_all("pkg_dotted/inner").unwrap();
+        std::fs::write("pkg_dotted/__init__.py", "name = 'pkg'\n").unwrap();
+        std::fs::write("pkg_dotted/inner/leaf.py", "value = 42\n").unwrap();
+
+        let result = execute(
+            "import pkg_dotted.inner.leaf\n\
+             from pkg_dotted.inner.leaf import value\n\
+             import pkg_dotted.inner.leaf as leaf\n\
+             (pkg_dotted.name, pkg_dotted.inner.leaf.value, value, leaf.value)",
+            &[],
+            &[],
+            &[],
+        );
+
+        std::fs::remove_dir_all("pkg_dotted").unwrap();
+        assert_eq!(format!("{}", result.unwrap()), "('pkg', 42, 42, 42)");
+    }
+
+    #[test]
+    fn builtins_match_between_execute_and_vm() {
+        let src = "(len('héllo'), len(range(0, 10, 3)), abs(-2), str(1.5), type(set()))";
+
+        let code = Compiler::default().compile(src).unwrap();
+        let direct = Vm::default().with_builtins().run(&code).unwrap();
+        let executed = execute(src, &[], &[], &[]).unwrap();
+
+        assert_eq!(format!("{}", direct), format!("{}", executed));
+        assert_eq!(format!("{}", direct), "(5, 4, 2, '1.5', <type set>)");
+
+        let err = execute("len(3)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: object of type 'int' has no len()"
+        );
+    }
+
+    #[test]
+    fn strings_are_char_based() {
+        let r = execute(
+            "s = 'héllo'\n\
+             chars = []\n\
+             for c in s:\n    chars = chars + [c]\n\
+             (len(s) == 5, s[1] == 'é', s[-1], list(s) == chars, list(s))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(True, True, 'o', True, ['h', 'é', 'l', 'l', 'o'])"
+        );
+
+        let err = execute("'é'[1]", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "IndexError: string index out of range");
+    }
+
+    #[test]
+    fn true_and_floor_division() {
+        let r = execute(
+            "(7 / 2, 6 / 3, 7 // 2, -7 // 2, 7 // -2, -7 // -2, 7.5 // 2, type(6 / 3))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(3.5, 2.0, 3, -4, -4, 3, 3.0, <type float>)"
+        );
+
+        let err = execute("1 // 0", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "ZeroDivisionError: integer division or modulo by zero"
+        );
+
+        for src in [
+            "1 / 0",
+            "0 / 0",
+            "1 / 0.0",
+            "1.5 / 0",
+            "0.0 / -0.0",
+            "1 / False",
+        ] {
+            let err = execute(src, &[], &[], &[]).unwrap_err();
+            assert_eq!(
+                error_message(&err),
+                "ZeroDivisionError: division by zero",
+                "{}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn ellipsis_literal() {
+        let r = execute(
+            "x = ...\ndef stub():\n    ...\n(x == ..., x, x != None, stub())",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, Ellipsis, True, None)");
+    }
+
+    #[test]
+    fn name_and_const_indices_per_code_object() {
+        let code = Compiler::default()
+            .compile("a = 1\nb = 'x'\ndef f(x):\n    return x + 1\na = 1\nb = 'x'\nf(a)")
+            .unwrap();
+
+        assert_eq!(code.names, ["a", "b", "f"]);
+        assert_eq!(code.consts, [PyObject::Int(1), PyObject::from("x")]);
+        assert_eq!(code.nested[0].names, ["x"]);
+        assert_eq!(code.nested[0].consts, [PyObject::Int(1), PyObject::None]);
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture_stdout(src: &str) -> String {
+        let buf = SharedBuf::default();
+        let prev = crate::core::io::set_stdout(Box::new(buf.clone()));
+        let result = execute(src, &[], &[], &[]);
+        crate::core::io::set_stdout(prev);
+        result.unwrap();
+        String::from_utf8(buf.0.take()).unwrap()
+    }
+
+    #[test]
+    fn print_and_writes_interleave() {
+        let out = capture_stdout(
+            "import sys\nimport io\n\
+             sys.stdout.write('a')\n\
+             print('b', 1)\n\
+             io.write('c')\n\
+             sys.stdout.write('d\\n')\n\
+             print('e')",
+        );
+        assert_eq!(out, "ab 1\ncd\ne\n");
+    }
+
+    #[test]
+    fn keyword_constants() {
+        let r = execute(
+            "def f(a, b=None, c=True):\n    return (a, b, c, False)\n\
+             d = {'t': True, 'n': None}\n\
+             (f(1), f(1, c=False), d['t'] == True, d['n'] == None, True != False, None != False)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "((1, None, True, False), (1, None, False, False), True, True, True, True)"
+        );
+
+        for src in ["True = 5", "None = 1", "x, False = 1, 2"] {
+            let err = execute(src, &[], &[], &[]).unwrap_err();
+            assert!(error_message(&err).starts_with("SyntaxError"), "{}", src);
+        }
+    }
+
+    #[test]
+    fn method_calls_use_call_method() {
+        let src = "class C:\n    def add(self, x):\n        return x + 1\nc = C()\n(c.add(1), 'a{}'.format(2))";
+        let code = Compiler::default().compile(src).unwrap();
+        assert_eq!(
+            code.instructions
+                .iter()
+                .filter(|op| matches!(op, opcode::Op::CallMethod(1)))
+                .count(),
+            2
+        );
+
+        let mut vm = Vm::default().with_builtins();
+        vm.enable_stats();
+        let r = vm.run(&code).unwrap();
+        assert_eq!(format!("{}", r), "(2, 'a2')");
+        assert_eq!(vm.stats().unwrap().per_op.get("CallMethod"), Some(&2));
+    }
+
+    #[test]
+    fn attribute_chains() {
+        let prelude = "class Leaf:\n    def __init__(self):\n        self.x = 7\n\
+                       class B:\n    def __init__(self):\n        self.leaf = Leaf()\n\
+                       class A:\n    def __init__(self):\n        self.b = B()\n\
+                       a = A()\n";
+
+        let r = execute(&format!("{}a.b.leaf.x", prelude), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "7");
+
+        let err = execute(&format!("{}a.b.x", prelude), &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "AttributeError: 'B' object has no attribute 'x'"
+        );
+
+        let err = execute(&format!("{}a.b.leaf.x.y", prelude), &[], &[], &[]).unwrap_err();
+        assert!(error_message(&err).starts_with("AttributeError"));
+    }
+
+    #[test]
+    fn decimal_arithmetic() {
+        let r = execute(
+            "from decimal import Decimal\n\
+             a = Decimal('0.1') + Decimal('0.2')\n\
+             (a == Decimal('0.3'), 0.1 + 0.2 == 0.3, a, Decimal('1.50') * 2, \
+             Decimal('19.99') - Decimal(5), Decimal(1) / Decimal(4), Decimal(1) / Decimal(3), \
+             Decimal('0.30') == a, Decimal('2.5') < 3, -Decimal('1'))",
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(
+            format!("{:?}", r.as_ref().map(|v| v.repr())),
+            "Ok(\"(True, False, Decimal('0.3'), Decimal('3.00'), Decimal('14.99'), \
+             Decimal('0.25'), Decimal('0.3333333333333333333333333333'), True, True, \
+             Decimal('-1'))\")"
+        );
+
+        let err = execute(
+            "from decimal import Decimal\nDecimal(1) / Decimal('0.0')",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error_message(&err), "ZeroDivisionError: division by zero");
+
+        let r = execute(
+            "from decimal import Decimal\n\
+             d = {1: 'a', 2: 'b'}\n\
+             (Decimal('1') == 1, 2 == Decimal('2.00'), Decimal(1) == True, Decimal('1.5') == 1, \
+             d[Decimal(1)], d[Decimal('2.0')], Decimal('1.0') in {1})",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(True, True, True, False, 'a', 'b', True)"
+        );
+    }
+
+    #[test]
+    fn global_statement() {
+        let src = "counter = 0\n\
+                   def inc():\n    global counter\n    counter += 1\n    return counter\n\
+                   def shadow():\n    counter = 100\n    return counter\n\
+                   (inc(), inc(), shadow(), counter)";
+
+        let code = Compiler::default().compile(src).unwrap();
+        let inc = &code.nested[0].instructions;
+        assert!(inc.iter().any(|op| matches!(op, opcode::Op::LoadGlobal(_))));
+        assert!(
+            inc.iter()
+                .any(|op| matches!(op, opcode::Op::StoreGlobal(_)))
+        );
+
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(1, 2, 100, 2)");
+    }
+
+    #[test]
+    fn str_padding_and_case_methods() {
+        let r = execute(
+            "('hi'.zfill(5) == '000hi', '-42'.zfill(5), 'a\\nb'.splitlines() == ['a', 'b'], \
+             'a\\r\\nb\\n\\nc\\n'.splitlines(), 'x\\ny'.splitlines(True), \
+             \"they're bill's\".title(), 'hELLO world'.capitalize(), \
+             'ab'.center(5, '*'), 'abc'.center(6), 'ab'.ljust(4, '.'), 'ab'.rjust(4), 'abc'.rjust(2))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(True, '-0042', True, ['a', 'b', '', 'c'], ['x\\n', 'y'], \
+             \"They'Re Bill'S\", 'Hello world', '**ab*', ' abc  ', 'ab..', '  ab', 'abc')"
+        );
+    }
+
+    #[test]
+    fn stateful_native_module() {
+        let mut count = 0;
+        let next = PyObject::native_fn_mut("next", 0, move |_| {
+            count += 1;
+            Ok(PyObject::Int(count))
+        });
+
+        // state shared between two natives of the same module
+        let total = Rc::new(std::cell::RefCell::new(0));
+        let add = PyObject::native_fn("add", 1, {
+            let total = total.clone();
+            move |args| {
+                if let PyObject::Int(n) = args[0] {
+                    *total.borrow_mut() += n;
+                }
+                Ok(PyObject::None)
+            }
+        });
+        let get = PyObject::native_fn("get", 0, {
+            let total = total.clone();
+            move |_| Ok(PyObject::Int(*total.borrow()))
+        });
+
+        let module: HashMap<String, PyObject> = [("next", next), ("add", add), ("get", get)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+
+        let r = execute(
+            "import counter\ncounter.add(5)\ncounter.add(2)\n\
+             (counter.next(), counter.next(), counter.next(), counter.get())",
+            &[],
+            &[("counter", module)],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(1, 2, 3, 7)");
+        assert_eq!(*total.borrow(), 7);
+    }
+
+    #[test]
+    fn program_value_is_last_expression() {
+        for (src, expected) in [
+            ("", "None"),
+            ("2.3", "2.3"),
+            ("1\n2\n3", "3"),
+            ("x = 5\nx", "5"),
+            ("3\nx = 1", "None"),
+            ("if True:\n    7", "None"),
+            ("def f():\n    return 1\nf()\nf() + 1", "2"),
+        ] {
+            let r = execute(src, &[], &[], &[]).unwrap();
+            assert_eq!(format!("{}", r), expected, "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn expression_statements_keep_stack_balanced() {
+        let code = Compiler::default()
+            .compile("def f():\n    return 1\nfor i in range(100):\n    f()\n    i + 1\ni")
+            .unwrap();
+        let mut vm = Vm::default().with_builtins();
+
+        let r = vm.run(&code).unwrap();
+        assert_eq!(format!("{}", r), "99");
+        assert!(vm.stack.is_empty(), "{} values left", vm.stack.len());
+    }
+
+    #[test]
+    fn weak_references() {
+        let r = execute(
+            "import weakref\n\
+             x = [1, 2]\n\
+             r = weakref.ref(x)\n\
+             alive = r() == [1, 2]\n\
+             x = None\n\
+             (alive, r(), r)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, None, <weakref; dead>)");
+
+        let err = execute("import weakref\nweakref.ref(1)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: cannot create weak reference to 'int' object"
+        );
+    }
+
+    #[test]
+    fn module_level_return_is_rejected() {
+        for (src, expected) in [
+            ("return 5", "SyntaxError: 'return' outside function"),
+            (
+                "x = 1\nif x:\n    return x",
+                "SyntaxError: 'return' outside function",
+            ),
+            (
+                "def f():\n    class C:\n        return 1",
+                "SyntaxError: 'return' outside function",
+            ),
+            ("yield 1", "SyntaxError: 'yield' outside function"),
+            (
+                "nonlocal x",
+                "SyntaxError: nonlocal declaration not allowed at module level",
+            ),
+        ] {
+            let err = Compiler::default().compile(src).unwrap_err();
+            assert_eq!(err, expected, "{:?}", src);
+        }
+
+        let r = execute(
+            "def f():\n    if True:\n        return 5\nf()",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "5");
+    }
+
+    #[test]
+    fn class_as_constant_namespace() {
+        let r = execute(
+            "class Color:\n    RED = 1\n    GREEN = 2\n    BLUE = GREEN + 1\n    ALL = (RED, GREEN, BLUE)\n\
+             \x20   def is_red(self, c):\n        return c == self.RED\n\
+             (Color.GREEN == 2, Color.BLUE, Color.ALL, Color().is_red(Color.RED), Color().GREEN)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, 3, (1, 2, 3), True, 2)");
+
+        let err = execute("class Color:\n    RED = 1\nColor.PINK", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "AttributeError: type object 'Color' has no attribute 'PINK'"
+        );
+    }
+
+    #[test]
+    fn equal_numbers_share_keys() {
+        let r = execute(
+            "d = {1: 'a'}
+d[True] = 'b'
+(1.0 in {1, 2}, 2 in {2.0}, d[1.0], len(d), 1 == True, 0.5 in {0, 1})",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, True, 'b', 1, True, False)");
+    }
+
+    #[test]
+    fn pprint_indents_nested_data() {
+        let r = execute(
+            "import pprint\npprint.pformat({'a': [1, 2], 'b': ()})",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "{\n  'a': [\n    1,\n    2\n  ],\n  'b': ()\n}"
+        );
+
+        let out = capture_stdout("import pprint\npprint.pprint((1,), indent=4)");
+        assert_eq!(out, "(\n    1,\n)\n");
+    }
+
+    #[test]
+    fn and_or_return_operands_once() {
+        let r = execute(
+            "calls = ''\n\
+             def f(v):\n    global calls\n    calls = calls + str(v) + '|'\n    return v\n\
+             (f(0) or f('') or f('x'), f(3) and f([]) and f(4), f(1) or f(2), calls)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "('x', [], 1, '0||x|3|[]|1|')");
+    }
+
+    fn run_ops(consts: Vec<PyObject>, instructions: Vec<opcode::Op>) -> Result<PyObject, String> {
+        let code = CodeObject {
+            consts,
+            instructions,
+            ..Default::default()
+        };
+        Vm::default().run(&code)
+    }
+
+    #[test]
+    fn stack_manipulation_ops() {
+        use opcode::Op::*;
+
+        let consts = vec![PyObject::Int(1), PyObject::Int(2)];
+
+        let r = run_ops(
+            consts.clone(),
+            vec![LoadConst(0), Dup, BuildTuple(2), Return],
+        );
+        assert_eq!(format!("{}", r.unwrap()), "(1, 1)");
+
+        let r = run_ops(
+            consts.clone(),
+            vec![LoadConst(0), LoadConst(1), Swap, BuildTuple(2), Return],
+        );
+        assert_eq!(format!("{}", r.unwrap()), "(2, 1)");
+
+        let r = run_ops(
+            consts.clone(),
+            vec![LoadConst(0), LoadConst(1), DupTop2, BuildTuple(4), Return],
+        );
+        assert_eq!(format!("{}", r.unwrap()), "(1, 2, 1, 2)");
+
+        let err = run_ops(consts, vec![LoadConst(0), Swap, Return]).unwrap_err();
+        assert_eq!(error_message(&err), "stack underflow");
+    }
+
+    #[test]
+    fn augmented_assignment_to_items_and_attributes() {
+        let r = execute(
+            "class C:\n    pass\n\
+             c = C()\nc.n = 1\nc.n += 2\n\
+             xs = [1, 2]\nxs[1] *= 5\nd = {'k': 'a'}\nd['k'] += 'b'\n\
+             (c.n, xs, d['k'])",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(3, [1, 10], 'ab')");
+    }
+
+    #[test]
+    fn print_blank_line_and_end() {
+        assert_eq!(capture_stdout("print()"), "\n");
+        assert_eq!(capture_stdout("print('x', end='')\nprint('y')"), "xy\n");
+        assert_eq!(
+            capture_stdout("print(1, 2, 3, sep=', ', end='.\\n')"),
+            "1, 2, 3.\n"
+        );
+
+        let err = execute("print('x', flush=True)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: 'flush' is an invalid keyword argument for print()"
+        );
+    }
+
+    #[test]
+    fn type_name_attribute() {
+        let r = execute(
+            "class Point:\n    pass\n\
+             (type(3).__name__ == 'int', type([]).__name__ == 'list', type(Point()).__name__, Point.__name__)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, True, 'Point', 'Point')");
+
+        let err = execute("type(1).nope", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "AttributeError: type object 'int' has no attribute 'nope'"
+        );
+    }
+
+    #[test]
+    fn datetime_fields() {
+        let r = execute(
+            "import datetime\nnow = datetime.now()\n(now.year >= 2024, 1 <= now.month, now.month <= 12, now.timestamp() > 0)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, True, True, True)");
+
+        let r = execute(
+            "import datetime\nd = datetime.fromtimestamp(1709210096)\n\
+             (d.year, d.month, d.day, d.hour, d.minute, d.second, d.timestamp())",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(2024, 2, 29, 12, 34, 56, 1709210096.0)");
+    }
+
+    #[test]
+    fn io_print_is_variadic() {
+        assert_eq!(capture_stdout("import io\nio.print(1, 2, 3)"), "1 2 3\n");
+        assert_eq!(
+            capture_stdout("import io\nio.print('a', 'b', sep='-', end='!')"),
+            "a-b!"
+        );
+    }
+
+    #[test]
+    fn operator_functions_with_reduce() {
+        let r = execute(
+            "import operator\nfrom functools import reduce\n\
+             def longer(a, b):\n    if len(b) > len(a):\n        return b\n    return a\n\
+             (reduce(operator.add, [1, 2, 3]), reduce(operator.mul, [2, 3], 10), \
+             reduce(longer, ['ab', 'abcd', 'a']), operator.lt(1, 2), operator.itemgetter(1)(['x', 'y']))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(6, 60, 'abcd', True, 'y')");
+
+        let err = execute(
+            "from functools import reduce\nreduce(len, [])",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: reduce() of empty iterable with no initial value"
+        );
+    }
+
+    #[test]
+    fn nested_subscript_assignment() {
+        let r = execute(
+            "m = {'a': {}}\nm['a']['b'] = 5\ngrid = [[0, 0], [0, 0]]\ngrid[1][0] = 7\n(m['a']['b'], grid)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(5, [[0, 0], [7, 0]])");
+    }
+
+    #[test]
+    fn subscript_assignment_evaluates_once() {
+        let r = execute(
+            "calls = ''\nlst = [0, 0, 0]\n\
+             def f():\n    global calls\n    calls = calls + 'f'\n    return 1\n\
+             def g():\n    global calls\n    calls = calls + 'g'\n    return 9\n\
+             lst[f()] = g()\n\
+             class C:\n    pass\n\
+             c = C()\nc.x, lst[0] = 'a', 'b'\n\
+             (lst, calls, c.x)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(['b', 9, 0], 'gf', 'a')");
+
+        let r = run_ops(
+            vec![PyObject::Int(1), PyObject::Int(2), PyObject::Int(3)],
+            vec![
+                opcode::Op::LoadConst(0),
+                opcode::Op::LoadConst(1),
+                opcode::Op::LoadConst(2),
+                opcode::Op::RotThree,
+                opcode::Op::BuildTuple(3),
+                opcode::Op::Return,
+            ],
+        );
+        assert_eq!(format!("{}", r.unwrap()), "(2, 3, 1)");
+    }
+
+    #[test]
+    fn heapq_orders_items() {
+        let r = execute(
+            "import heapq\nh = []\nheapq.heappush(h, 5)\nheapq.heappush(h, 1)\nheapq.heappush(h, 3)\n\
+             first = heapq.heappop(h)\nsecond = heapq.heappop(h)\n\
+             q = [(4, 'd'), (2, 'b'), (2, 'a'), (9, 'z')]\nheapq.heapify(q)\n\
+             (first, second, heapq.heappop(q), heapq.nsmallest(2, [7, 2, 8, 1]), heapq.nlargest(2, range(5)))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(1, 3, (2, 'a'), [1, 2], [4, 3])");
+
+        let err = execute("import heapq\nheapq.heappop([])", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "IndexError: index out of range");
+    }
+
+    #[test]
+    fn container_equality() {
+        let r = execute(
+            "({'a': 1, 'b': 2} == {'b': 2, 'a': 1}, {'a': 1} != {'a': 2}, {1, 2} == {2, 1}, \
+             [1, 2] == (1, 2), [1, [2]] == [1, [2]], (1, 2) != (2, 1), {'k': [1]} == {'k': [1.0]})",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(True, True, True, False, True, True, True)"
+        );
+    }
+
+    #[test]
+    fn docstrings() {
+        let r = execute(
+            "\"\"\"Module docs.\"\"\"\n\
+             def area(w, h):\n    \"\"\"Return the area.\n\n    Multiplies.\"\"\"\n    return w * h\n\
+             def bare():\n    pass\n\
+             class Shape:\n    '''A shape.'''\n    sides = 0\n\
+             (area(2, 3), area.__doc__, bare.__doc__, Shape.__doc__, Shape.sides, area.__name__)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(6, 'Return the area.\\n\\n    Multiplies.', None, 'A shape.', 0, 'area')"
+        );
+    }
+
+    #[test]
+    fn dict_iteration() {
+        let r = execute(
+            "prices = {'b': 2, 'a': 1, 'c': 3}\n\
+             keys = ''\n\
+             for key in prices:\n    keys = keys + key\n\
+             total = 0\n\
+             for v in prices.values():\n    total = total + v\n\
+             pairs = ''\n\
+             for k, v in prices.items():\n    pairs = pairs + k + str(v)\n\
+             (keys, prices.keys(), total, pairs, prices.get('z', 0), prices.get('a'))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "('bac', ['b', 'a', 'c'], 6, 'b2a1c3', 0, 1)"
+        );
+    }
+
+    #[test]
+    fn vm_output_sinks() {
+        let out = SharedBuf::default();
+        let err = SharedBuf::default();
+        let code = Compiler::default()
+            .compile("import sys\nprint('hi')\nsys.stderr.write('oops\\n')")
+            .unwrap();
+
+        let mut vm = Vm::default()
+            .with_builtins()
+            .with_stdout(out.clone())
+            .with_stderr(err.clone());
+        vm.run(&code).unwrap();
+        vm.run(&code).unwrap();
+
+        assert_eq!(String::from_utf8(out.0.take()).unwrap(), "hi\nhi\n");
+        assert_eq!(String::from_utf8(err.0.take()).unwrap(), "oops\noops\n");
+    }
+
+    #[test]
+    fn builtin_types_raise_typed_attribute_errors() {
+        for (src, msg) in [
+            (
+                "[].nope",
+                "AttributeError: 'list' object has no attribute 'nope'",
+            ),
+            (
+                "{}.nope",
+                "AttributeError: 'dict' object has no attribute 'nope'",
+            ),
+            (
+                "set().nope",
+                "AttributeError: 'set' object has no attribute 'nope'",
+            ),
+            (
+                "(1,).x = 2",
+                "AttributeError: 'tuple' object has no attribute 'x'",
+            ),
+        ] {
+            let err = execute(src, &[], &[], &[]).unwrap_err();
+            assert_eq!(error_message(&err), msg);
+        }
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let r = execute(
+            "s = 'café ☕'\nb = s.encode()\n\
+             (b, len(b), b.decode('utf-8') == s, b[3], 'abc'.encode(encoding='ascii'), b'x\\'y'.decode('ascii'))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(b'caf\\xc3\\xa9 \\xe2\\x98\\x95', 9, True, 195, b'abc', \"x'y\")"
+        );
+
+        for (src, msg) in [
+            (
+                "'café'.encode('ascii')",
+                "UnicodeEncodeError: 'ascii' codec can't encode character '\\xe9' in position 3: ordinal not in range(128)",
+            ),
+            (
+                "b'caf\\xc3\\xa9'.decode('ascii')",
+                "UnicodeDecodeError: 'ascii' codec can't decode byte 0xc3 in position 3: ordinal not in range(128)",
+            ),
+            (
+                "'x'.encode('latin-9')",
+                "LookupError: unknown encoding: latin-9",
+            ),
+        ] {
+            let err = execute(src, &[], &[], &[]).unwrap_err();
+            assert_eq!(error_message(&err), msg);
+        }
+    }
+
+    #[test]
+    fn fallback_import_on_import_error() {
+        let r = execute(
+            "try:\n    import fastmath as m\nexcept ImportError as e:\n    import math as m\n    err = str(e)\n\
+             try:\n    from speedups import sqrt\nexcept ImportError:\n    from math import sqrt\n\
+             (m.sqrt(16), sqrt(9), err)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(4.0, 3.0, \"No module named 'fastmath'\")"
+        );
+    }
+
+    #[test]
+    fn native_class_methods_bind_self() {
+        use std::cell::RefCell;
+
+        let ctor = Rc::new(|args: &[PyObject]| -> Result<PyObject, String> {
+            let mut attrs = HashMap::new();
+            attrs.insert("x".to_string(), args[0].clone());
+            attrs.insert("y".to_string(), args[1].clone());
+
+            Ok(PyObject::Instance(Rc::new(RefCell::new(PyInstance {
+                class: Rc::new(PyClass {
+                    name: "Point".to_string(),
+                    methods: HashMap::new().into(),
+                    bases: Vec::new(),
+                }),
+                attrs,
+            }))))
+        });
+
+        let coord = |p: &PyObject, name: &str| match p {
+            PyObject::Instance(inst) => match inst.borrow().attrs.get(name) {
+                Some(PyObject::Int(v)) => Ok(*v),
+                _ => Err(format!("TypeError: {} must be an int", name)),
+            },
+            _ => Err("TypeError: expected a Point".to_string()),
+        };
+
+        let mut methods = HashMap::new();
+        methods.insert(
+            "distance".to_string(),
+            PyObject::native_fn("distance", 1, move |args| {
+                let (x, y) = (coord(&args[0], "x")?, coord(&args[0], "y")?);
+                Ok(PyObject::Float(((x * x + y * y) as f64).sqrt()))
+            }),
+        );
+        methods.insert(
+            "shifted".to_string(),
+            PyObject::native_fn("shifted", 2, move |args| {
+                let (x, PyObject::Int(dx)) = (coord(&args[0], "x")?, &args[1]) else {
+                    return Err("TypeError: dx must be an int".to_string());
+                };
+                Ok(PyObject::Int(x + dx))
+            }),
+        );
+
+        let r = execute(
+            "p = Point(3, 4)\n(p.distance(), p.shifted(10), p.x)",
+            &[],
+            &[],
+            &[("Point", ctor, methods)],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(5.0, 13, 3)");
+    }
+
+    #[test]
+    fn min_max_key_and_default() {
+        let src = "\
+def neg(x):
+    return -x
+(max(['a', 'bbb', 'cc'], key=len), max([], default=0), min(3, 1, 2), min([4, 9, 7], key=neg), max([2, 5, 5.0]))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "('bbb', 0, 1, 9, 5)");
+
+        let err = execute("max([])", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "ValueError: max() arg is an empty sequence"
+        );
+    }
+
+    #[test]
+    fn for_loop_variable_outlives_the_loop() {
+        let code = Compiler::default()
+            .compile("for i in range(3):\n    pass\ni")
+            .unwrap();
+        let for_iter = code
+            .instructions
+            .iter()
+            .position(|op| matches!(op, opcode::Op::ForIter(_)))
+            .unwrap();
+        assert!(matches!(
+            code.instructions[for_iter + 1],
+            opcode::Op::StoreName(_)
+        ));
+
+        let r = execute("for i in range(3):\n    pass\ni", &[], &[], &[]).unwrap();
+        assert_eq!(r, PyObject::Int(2));
+
+        let err = execute("for i in []:\n    pass\ni", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "NameError: name 'i' is not defined");
+    }
+
+    #[test]
+    fn collections_deque_pops_from_both_ends() {
+        let r = execute(
+            "from collections import deque\nd = deque([1, 2])\nd.appendleft(0)\nd.popleft()",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(r, PyObject::Int(0));
+
+        let src = "\
+from collections import deque
+d = deque()
+d.append(1)
+d.append(2)
+d.appendleft(0)
+total = 0
+for x in d:
+    total = total + x
+(d.pop(), len(d), total, d)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(2, 2, 3, deque([0, 1]))");
+
+        let err = execute(
+            "import collections\ncollections.deque().pop()",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error_message(&err), "IndexError: pop from an empty deque");
+    }
+
+    #[test]
+    fn special_floats_display_like_python() {
+        let r = execute(
+            "import math\n(str(math.inf), str(-math.inf), str(math.nan), str(-0.0), [math.nan, -0.0])",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "('inf', '-inf', 'nan', '-0.0', [nan, -0.0])"
+        );
+    }
+
+    #[test]
+    fn lru_cache_memoizes_recursive_calls() {
+        let src = "\
+from functools import lru_cache
+calls = 0
+@lru_cache(maxsize=None)
+def fib(n):
+    global calls
+    calls = calls + 1
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+(fib(30), calls)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(832040, 31)");
+
+        let src = "\
+import functools
+calls = 0
+@functools.lru_cache
+def square(x):
+    global calls
+    calls = calls + 1
+    return x * x
+(square(3), square(3), square(x=3), calls)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(9, 9, 9, 2)");
+
+        let err = execute(
+            "from functools import lru_cache\n@lru_cache\ndef f(x):\n    return x\nf([1])",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error_message(&err), "TypeError: unhashable type: 'list'");
+    }
+
+    #[test]
+    fn decorators_apply_innermost_first() {
+        let src = "\
+def twice(f):
+    return 'twice(' + f() + ')'
+def loud(f):
+    return f
+@twice
+@loud
+def hello():
+    return 'hi'
+hello";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(r, PyObject::Str("twice(hi)".to_string()));
+    }
+
+    #[test]
+    fn not_equal_is_the_negation_of_equal() {
+        let src = "\
+pairs = [({'a': 1, 'b': 2}, {'b': 2, 'a': 1}), ({'a': 1}, {'a': 1}), ({'a': 1}, {'a': 2}), ([1, 2], [1, 2]), ([1, 2], [2, 1]), (1, 1.0), (True, 1), (0.5, 1), ((1, 'x'), (1.0, 'x')), (math.nan, math.nan)]
+agree = True
+for a, b in pairs:
+    if (a != b) == (a == b):
+        agree = False
+(agree, {'a': 1} != {'a': 1}, [1, 2] != [1, 2.0], 1 != 1.5)";
+        let r = execute(&format!("import math\n{}", src), &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(True, False, False, True)");
+    }
+
+    #[test]
+    fn for_loop_uses_iter_and_next_dunders() {
+        let src = "\
+class Countdown:
+    def __init__(self, start):
+        self.n = start
+    def __iter__(self):
+        return self
+    def __next__(self):
+        if self.n == 0:
+            raise StopIteration
+        self.n = self.n - 1
+        return self.n + 1
+seen = ''
+for x in Countdown(3):
+    seen = seen + str(x)
+seen";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(r, PyObject::Str("321".to_string()));
+
+        let err = execute(
+            "class Box:\n    pass\nfor x in Box():\n    pass",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: 'Box' object is not iterable"
+        );
+    }
+
+    #[test]
+    fn abs_round_and_pow_dispatch_to_dunders() {
+        let src = "\
+class Vec2:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+    def __abs__(self):
+        return 'abs(' + str(self.x) + ', ' + str(self.y) + ')'
+    def __round__(self, ndigits=None):
+        return ndigits
+    def __pow__(self, exp):
+        return self.x * exp
+v = Vec2(3, 4)
+(abs(v), round(v), round(v, 2), pow(v, 5))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "('abs(3, 4)', None, 2, 15)");
+
+        let r = execute(
+            "(abs(-3), round(2.5), round(3.5), round(2.675, 2), round(1250, -2), pow(2, 10), pow(2, -1), pow(3, 4, 5), pow(2, 3, -5))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(3, 2, 4, 2.67, 1200, 1024, 0.5, 1, -2)");
+
+        let big = "9223372036854775807";
+        let src = format!("(round({big}, -2), round(-{big}, -18), round({big}, -20))");
+        let r = execute(&src, &[], &[], &[]).unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(9223372036854775800, -9000000000000000000, 0)"
+        );
+        for src in [format!("round({big}, -1)"), format!("round({big}, -19)")] {
+            let err = execute(&src, &[], &[], &[]).unwrap_err();
+            assert_eq!(
+                error_message(&err),
+                "OverflowError: integer overflow in round()",
+                "{}",
+                src
+            );
+        }
+
+        let err = execute("class C:\n    pass\nabs(C())", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: bad operand type for abs(): 'C'"
+        );
+    }
+
+    #[test]
+    fn string_module_constants() {
+        let r = execute(
+            "import string\n(string.digits == '0123456789', len(string.ascii_letters), string.hexdigits, len(string.punctuation))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(True, 52, '0123456789abcdefABCDEF', 32)");
+    }
+
+    #[test]
+    fn class_call_checks_arguments_against_init() {
+        let err = execute("class C:\n    pass\nC(1)", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "TypeError: C() takes no arguments");
+
+        let err = execute("class C:\n    pass\nC(x=1)", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "TypeError: C() takes no arguments");
+
+        let src = "\
+class P:
+    def __init__(self, x):
+        self.x = x
+P(1, 2)";
+        let err = execute(src, &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: __init__() takes 2 positional arguments but 3 were given"
+        );
+
+        let r = execute("class C:\n    pass\ntype(C())", &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "<type C>");
+    }
+
+    #[test]
+    fn instances_compare_by_identity_unless_eq_is_defined() {
+        let src = "\
+class P:
+    def __init__(self, x):
+        self.x = x
+a = P(1)
+b = P(1)
+(a == b, a != b, a == a, [a] == [a], [a] == [b])";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(False, True, True, True, False)");
+
+        let src = "\
+class P:
+    def __init__(self, x):
+        self.x = x
+    def __eq__(self, other):
+        return self.x == other.x
+(P(1) == P(1), P(1) != P(1), P(1) == P(2))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(True, False, False)");
+    }
+
+    #[test]
+    fn re_module_matches_and_substitutes() {
+        let src = r#"
+import re
+m = re.search(r'(\w+)@(\w+)', 'mail bob@example now')
+(re.findall('[0-9]+', 'a1b22'), re.findall(r'(\w)=(\d)', 'a=1 b=2'), m.group(0), m.group(2), m.span(), re.match('b', 'abc'), re.match('a', 'abc').group(), re.sub(r'(\d+)', r'<\1>', 'a1b22'), re.sub('a', 'x', 'aaa', count=2))"#;
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(
+            format!("{}", r),
+            "(['1', '22'], [('a', '1'), ('b', '2')], 'bob@example', 'example', (5, 16), None, 'a', 'a<1>b<22>', 'xxa')"
+        );
+
+        let src = "\
+import re
+def bracket(m):
+    return '[' + m.group(0) + ']'
+re.sub('[aeiou]', bracket, 'banana')";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(r, PyObject::Str("b[a]n[a]n[a]".to_string()));
+
+        let r = execute(
+            "import re\nre.sub(r'(?P<d>\\d)', r'<\\g<d>>', 'a1')",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(r, PyObject::Str("a<1>".to_string()));
+        let err = execute(
+            "import re\nre.sub(r'(?P<d>\\d)', r'\\g<e>', 'a1')",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(error_message(&err), "IndexError: unknown group name");
+    }
+
+    #[test]
+    fn functions_resolve_globals_at_call_time() {
+        let src = "\
+def is_even(n):
+    if n == 0:
+        return True
+    return is_odd(n - 1)
+def is_odd(n):
+    if n == 0:
+        return False
+    return is_even(n - 1)
+def scaled(x):
+    return x * factor
+factor = 3
+(is_even(10), is_odd(7), is_even(3), scaled(2))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(True, True, False, 6)");
+    }
+
+    #[test]
+    fn sum_promotes_ints_to_floats() {
+        let r = execute(
+            "(sum([1, 2, 3]), sum([1.5, 2.5]), sum([0.1, 0.2, 0.3]), sum(range(4), 10), sum([1, 0.5]), sum([], start=2.0))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(6, 4.0, 0.6, 16, 1.5, 2.0)");
+
+        let err = execute("sum(['a', 'b'], '')", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: sum() can't sum strings [use ''.join(seq) instead]"
+        );
+    }
+
+    #[test]
+    fn host_calls_a_script_function() {
+        let code = Compiler::default()
+            .compile("def handler(x):\n    return x * 2\ndef fail():\n    raise ValueError('nope')")
+            .unwrap();
+        let mut vm = Vm::default().with_builtins();
+        vm.run(&code).unwrap();
+
+        let handler = vm.env.locals.borrow().get("handler").cloned().unwrap();
+        let r = vm.call_function(&handler, &[PyObject::Int(21)]).unwrap();
+        assert_eq!(r, PyObject::Int(42));
+
+        let fail = vm.env.locals.borrow().get("fail").cloned().unwrap();
+        let depth = vm.stack.len();
+        let err = vm.call_function(&fail, &[]).unwrap_err();
+        assert_eq!(error_message(&err), "ValueError: nope");
+        assert_eq!(vm.stack.len(), depth);
+    }
+
+    #[test]
+    fn star_import_honors_all() {
+        std::fs::write(
+            "test_star_all.py",
+            "__all__ = ['a', '_hidden']\na = 1\nb = 2\n_hidden = 3",
+        )
+        .unwrap();
+        let r = execute("from test_star_all import *\n(a, _hidden)", &[], &[], &[]);
+        let err = execute("from test_star_all import *\nb", &[], &[], &[]).unwrap_err();
+        std::fs::remove_file("test_star_all.py").unwrap();
+
+        assert_eq!(format!("{}", r.unwrap()), "(1, 3)");
+        assert_eq!(error_message(&err), "NameError: name 'b' is not defined");
+    }
+
+    #[test]
+    fn statistics_module_summaries() {
+        let r = execute(
+            "import statistics as st\n(st.mean([1, 2, 3, 4]), st.mean([1, 2, 3]), st.median([1, 3, 2]), st.median([4, 1, 3, 2]), st.mode(['a', 'b', 'b']), st.variance([1, 2, 3, 4, 5]), st.stdev([2, 4, 4, 4, 5, 5, 7, 9]) > 2.13)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(2.5, 2, 2, 2.5, 'b', 2.5, True)");
+
+        let r = execute(
+            "import statistics as st\nbig = 9223372036854775807\n(st.variance([4611686018427387904, 4611686018427387906, 4611686018427387908]), st.variance([-big, big, 0]) > 8.5e37)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(format!("{}", r), "(4, True)");
+
+        let err = execute("import statistics\nstatistics.mean([])", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "StatisticsError: mean requires at least one data point"
+        );
+    }
+
+    #[test]
+    fn continue_in_for_advances_the_iterator() {
+        let src = "\
+total = 0
+steps = 0
+for i in [1, 2, 3, 4, 5, 6, 7]:
+    steps = steps + 1
+    if i // 2 * 2 == i:
+        continue
+    total = total + i
+(total, steps)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(16, 7)");
+
+        let code = Compiler::default()
+            .compile("for i in range(3):\n    continue")
+            .unwrap();
+        let for_iter = code
+            .instructions
+            .iter()
+            .position(|op| matches!(op, opcode::Op::ForIter(_)))
+            .unwrap();
+        assert!(
+            code.instructions
+                .iter()
+                .any(|op| matches!(op, opcode::Op::SetupLoop { cont, .. } if *cont == for_iter))
+        );
+    }
+
+    #[test]
+    fn bisect_module_searches_and_inserts() {
+        let src = "\
+import bisect
+xs = [1, 3, 3, 5]
+bisect.insort(xs, 4)
+bisect.insort_left(xs, 0)
+(bisect.bisect_left([1, 3, 5], 3), bisect.bisect_right([1, 3, 3, 5], 3), bisect.bisect_left([1, 3, 5], 3, 2), xs)";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(format!("{}", r), "(1, 3, 2, [0, 1, 3, 3, 4, 5])");
+    }
+
+    #[test]
+    fn item_assignment_on_immutable_values_is_a_type_error() {
+        let err = execute("t = (1, 2, 3)\nt[0] = 9", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: 'tuple' object does not support item assignment"
+        );
+
+        let err = execute("s = 'abc'\ns[-1] = 'x'", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: 'str' object does not support item assignment"
+        );
+
+        let err = execute("xs = [1]\nxs['a'] = 2", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: list indices must be integers or slices, not str"
+        );
+    }
+
+    #[test]
+    fn set_argv_is_visible_as_sys_argv() {
+        std::fs::write("test_argv.py", "import sys\nargs = sys.argv").unwrap();
+        let code = Compiler::default()
+            .compile("import test_argv\nimport sys\nargs = sys.argv\nmodule_args = test_argv.args")
+            .unwrap();
+        let mut vm = Vm::default().with_builtins();
+        vm.set_argv(vec!["script.py".to_string(), "--fast".to_string()]);
+        let r = vm.run(&code);
+        std::fs::remove_file("test_argv.py").unwrap();
+        r.unwrap();
+
+        let args = vm.env.locals.borrow().get("args").cloned().unwrap();
+        assert_eq!(args.to_string(), "['script.py', '--fast']");
+        let module_args = vm.env.locals.borrow().get("module_args").cloned().unwrap();
+        assert_eq!(module_args.to_string(), "['script.py', '--fast']");
+    }
+
+    #[test]
+    fn builtin_types_convert_when_called() {
+        let src = "(int('5'), type(1)('5'), type('')(7), float(' 2.5 '), bool([]), \
+                   tuple('ab'), dict([('a', 1)], b=2), int(-2.7), int('ff', 16))";
+        let r = execute(src, &[], &[], &[]).unwrap();
+        assert_eq!(
+            r.to_string(),
+            "(5, 5, '7', 2.5, False, ('a', 'b'), {'a': 1, 'b': 2}, -2, 255)"
+        );
+
+        let err = execute("type(1)('x')", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "ValueError: invalid literal for int() with base 10: 'x'"
+        );
+    }
+
+    #[test]
+    fn operand_type_errors_name_the_types() {
+        let cases = [
+            (
+                "'x' + 5",
+                "TypeError: can only concatenate str (not \"int\") to str",
+            ),
+            (
+                "[1] + (2,)",
+                "TypeError: can only concatenate list (not \"tuple\") to list",
+            ),
+            (
+                "1 - 'a'",
+                "TypeError: unsupported operand type(s) for -: 'int' and 'str'",
+            ),
+            (
+                "None * 2",
+                "TypeError: unsupported operand type(s) for *: 'NoneType' and 'int'",
+            ),
+            (
+                "True / 'a'",
+                "TypeError: unsupported operand type(s) for /: 'bool' and 'str'",
+            ),
+        ];
+        for (src, msg) in cases {
+            let err = execute(src, &[], &[], &[]).unwrap_err();
+            assert_eq!(error_message(&err), msg, "{}", src);
+        }
+
+        let r = execute("('ab' * 2, [0] * 3, 2 * (1,), [1] + [2])", &[], &[], &[]).unwrap();
+        assert_eq!(r.to_string(), "('abab', [0, 0, 0], (1, 1), [1, 2])");
+
+        let cases = [
+            (
+                "'ab' * 4611686018427387904",
+                "OverflowError: repeated string is too long",
+            ),
+            ("[0] * 4611686018427387904", "MemoryError"),
+            ("4611686018427387904 * (0,)", "MemoryError"),
+        ];
+        for (src, msg) in cases {
+            let err = execute(src, &[], &[], &[]).unwrap_err();
+            assert_eq!(error_message(&err), msg, "{}", src);
+        }
+        let r = execute(
+            "('' * 4611686018427387904, [] * 4611686018427387904)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(r.to_string(), "('', [])");
+    }
+
+    #[test]
+    fn compile_expression_returns_its_value() {
+        let code = Compiler::default().compile_expression("2 + 3 * 4").unwrap();
+        let r = Vm::default().with_builtins().run(&code).unwrap();
+        assert_eq!(r, PyObject::Int(14));
+
+        assert!(Compiler::default().compile_expression("x = 1").is_err());
+    }
+
+    #[test]
+    fn print_dict_reprs_keys_and_values() {
+        let out = SharedBuf::default();
+        let code = Compiler::default()
+            .compile("print({'a': 'b', 1: [None, 'c']})")
+            .unwrap();
+        Vm::default()
+            .with_builtins()
+            .with_stdout(out.clone())
+            .run(&code)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out.0.take()).unwrap(),
+            "{'a': 'b', 1: [None, 'c']}\n"
+        );
+    }
+
+    #[test]
+    fn tuple_count_and_index() {
+        let r = execute(
+            "t = (1, 2, 2, 3)\n(t.count(2), t.index(3), t.index(2, 2), (1.0, 1).count(1))",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(r.to_string(), "(2, 3, 2, 2)");
+
+        let err = execute("(1, 2, 3).index(4)", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "ValueError: tuple.index(x): x not in tuple"
+        );
+    }
+
+    #[test]
+    fn list_methods() {
+        let r = execute(
+            "x = [3, 1, 2]\nx.sort()\nsorted_ok = x == [1, 2, 3]\nx.reverse()\n\
+             y = x.copy()\ny.append(0)\nwords = ['bb', 'a', 'ccc', 'dd']\n\
+             words.sort(key=len, reverse=True)\n\
+             (sorted_ok, x, y, y.index(1), y.count(2), y.pop(), y.pop(0), words)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            r.to_string(),
+            "(True, [3, 2, 1], [2, 1], 2, 1, 0, 3, ['ccc', 'bb', 'dd', 'a'])"
+        );
+
+        let r = execute("x = [1]\nx.clear()\nx", &[], &[], &[]).unwrap();
+        assert_eq!(r.to_string(), "[]");
+
+        let err = execute("[1, 2].index(5)", &[], &[], &[]).unwrap_err();
+        assert_eq!(error_message(&err), "ValueError: 5 is not in list");
+    }
+
+    #[test]
+    fn malformed_bytecode_errors_instead_of_panicking() {
+        use opcode::Op::*;
+
+        let one = vec![PyObject::Int(1)];
+        let programs = [
+            vec![BuildList(5), Return],
+            vec![LoadConst(0), BuildTuple(2), Return],
+            vec![BuildSet(usize::MAX), Return],
+            vec![LoadConst(0), BuildDict(usize::MAX), Return],
+            vec![LoadConst(0), BuildDict(1), Return],
+            vec![Add, Return],
+            vec![LoadConst(0), Sub, Return],
+            vec![LoadConst(0), Mul, Return],
+            vec![Div, Return],
+            vec![LoadConst(0), FloorDiv, Return],
+            vec![UnaryNeg, Return],
+        ];
+        for ops in programs {
+            let err = run_ops(one.clone(), ops.clone()).unwrap_err();
+            assert_eq!(error_message(&err), "stack underflow", "{:?}", ops);
+        }
+
+        let big = vec![PyObject::Int(i64::MAX), PyObject::Int(i64::MIN)];
+        for (ops, msg) in [
+            (
+                vec![LoadConst(0), LoadConst(0), Add, Return],
+                "OverflowError: integer overflow in +",
+            ),
+            (
+                vec![LoadConst(1), LoadConst(0), Sub, Return],
+                "OverflowError: integer overflow in -",
+            ),
+            (
+                vec![LoadConst(0), LoadConst(0), Mul, Return],
+                "OverflowError: integer overflow in *",
+            ),
+            (
+                vec![LoadConst(1), UnaryNeg, Return],
+                "OverflowError: integer overflow in -",
+            ),
+        ] {
+            let err = run_ops(big.clone(), ops).unwrap_err();
+            assert_eq!(error_message(&err), msg);
+        }
+
+        for (consts, ops, msg) in [
+            (
+                vec![],
+                vec![LoadConst(0), Return],
+                "SystemError: bad constant index 0",
+            ),
+            (
+                one.clone(),
+                vec![LoadName(3), Return],
+                "SystemError: bad name index 3",
+            ),
+            (
+                one.clone(),
+                vec![LoadConst(0), StoreGlobal(0)],
+                "SystemError: bad name index 0",
+            ),
+            (
+                one.clone(),
+                vec![LoadConst(0), LoadAttr(1), Return],
+                "SystemError: bad name index 1",
+            ),
+        ] {
+            let err = run_ops(consts, ops.clone()).unwrap_err();
+            assert_eq!(error_message(&err), msg, "{:?}", ops);
+        }
+
+        let code = CodeObject {
+            names: vec!["f".to_string()],
+            instructions: vec![opcode::Op::ClassDef {
+                name: 0,
+                code_idx: 0,
+            }],
+            ..Default::default()
+        };
+        let err = Vm::default().run(&code).unwrap_err();
+        assert_eq!(error_message(&err), "SystemError: bad code index 0");
+    }
+
+    #[test]
+    fn gc_collect_frees_cyclic_garbage() {
+        let r = execute(
+            "import gc\nfor i in range(3):\n  a = []\n  a.append(a)\na = None\n\
+             before = gc.get_count()[0]\nfreed = gc.collect()\n\
+             (freed, before - gc.get_count()[0])",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(r.to_string(), "(3, 3)");
+    }
+
+    #[test]
+    fn strings_iterate_by_character_everywhere() {
+        let r = execute(
+            "chars = []\nfor c in 'hé':\n  chars.append(c)\n\
+             ([c for c in 'abc'], list('abc'), set('aab'), sorted('cba'), \
+             tuple(iter('xy')), chars, {c: 1 for c in 'ab'})",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            r.to_string(),
+            "(['a', 'b', 'c'], ['a', 'b', 'c'], {'a', 'b'}, ['a', 'b', 'c'], ('x', 'y'), \
+             ['h', 'é'], {'a': 1, 'b': 1})"
+        );
+
+        let err = execute("for x in 5:\n  pass", &[], &[], &[]).unwrap_err();
+        assert_eq!(
+            error_message(&err),
+            "TypeError: 'int' object is not iterable"
+        );
+    }
+
+    #[test]
+    fn self_referential_instances_print_and_compare() {
+        let out = SharedBuf::default();
+        let code = Compiler::default()
+            .compile(
+                "class Node:\n  def __init__(self):\n    self.me = self\n\
+                 n = Node()\nprint(n)\nprint([n.me])\n\
+                 same = (n == n, n.me == n, n == Node(), n.me.me == n)",
+            )
+            .unwrap();
+        let mut vm = Vm::default().with_builtins().with_stdout(out.clone());
+        vm.run(&code).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out.0.take()).unwrap(),
+            "<Node object>\n[<Node object>]\n"
+        );
+        let same = vm.env.locals.borrow().get("same").cloned().unwrap();
+        assert_eq!(same.to_string(), "(True, True, False, True)");
+    }
+
+    #[test]
+    fn typing_names_import_for_annotations() {
+        let r = execute(
+            "from typing import List, Dict, Optional, cast\n\
+             def first(xs: List[int]) -> Optional[int]:\n  return xs[0]\n\
+             Table = Dict[str, List[int]]\nx: List[int] = [1]\n\
+             (first([3]), x, cast(int, 4), Table)",
+            &[],
+            &[],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(r.to_string(), "(3, [1], 4, <type typing.Dict>)");
+    }
+
+    #[test]
+    fn equal_constants_of_different_types_stay_distinct() {
+        let r = execute("(1, 1.0, True, 0, False, 0.0)", &[], &[], &[]).unwrap();
+        assert_eq!(r.to_string(), "(1, 1.0, True, 0, False, 0.0)");
+
+        let r = execute("x = 1\nTrue", &[], &[], &[]).unwrap();
+        assert_eq!(r, PyObject::Bool(true));
+        assert_eq!(r.to_string(), "True");
+    }
 }
@@ -1,51 +1,136 @@
 use crate::bytecode::*;
+use crate::core::decimal::Decimal;
 use crate::vm::*;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Display};
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 pub enum PyObject {
     Int(i64),
     Float(f64),
+    Complex(f64, f64),
+    Decimal(Decimal),
     Bool(bool),
     Str(String),
+    Bytes(Vec<u8>),
     List(Rc<RefCell<Vec<PyObject>>>),
-    Dict(Rc<RefCell<IndexMap<String, PyObject>>>),
+    Deque(Rc<RefCell<VecDeque<PyObject>>>),
+    Dict(Rc<RefCell<IndexMap<PyObject, PyObject>>>),
     Tuple(Vec<PyObject>),
-    Set(Rc<RefCell<HashSet<PyObject>>>),
+    Set(Rc<RefCell<IndexSet<PyObject>>>),
+    Range(i64, i64, i64),
     None,
+    Ellipsis,
     Function(Rc<PyFunction>),
+    Method(Rc<PyMethod>),
     NativeFunction(Rc<PyNativeFunction>),
+    VmFunction(Rc<PyVmFunction>),
     NativeModule(Rc<PyNativeModule>),
     NativeClass(Rc<PyNativeClass>),
     Type(PyType),
     Class(Rc<PyClass>),
     Instance(Rc<RefCell<PyInstance>>),
     Module(Rc<RefCell<PyModule>>),
+    Exception(Rc<PyException>),
+    Iterator(Rc<RefCell<PyIterator>>),
+    WeakRef(PyWeakRef),
+    Kwargs(Rc<IndexMap<String, PyObject>>),
 }
 
 #[derive(Clone, PartialEq)]
 pub struct PyModule {
     pub name: String,
-    pub dict: HashMap<String, PyObject>,
+    pub dict: Namespace,
 }
 
 #[derive(Clone, PartialEq)]
 pub struct PyClass {
     pub name: String,
-    pub methods: HashMap<String, PyObject>,
+    pub methods: RefCell<HashMap<String, PyObject>>,
     pub bases: Vec<Rc<PyClass>>,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone)]
 pub struct PyInstance {
     pub class: Rc<PyClass>,
     pub attrs: HashMap<String, PyObject>,
 }
 
+/// Instances compare by identity, like `object.__eq__`. Comparing attributes
+/// would recurse forever once an instance refers back to itself.
+impl PartialEq for PyInstance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// The class name of `inst`, even while the instance is mutably borrowed
+/// further up the call stack.
+fn class_name(inst: &RefCell<PyInstance>) -> String {
+    inst.try_borrow()
+        .map_or_else(|_| "object".to_string(), |i| i.class.name.clone())
+}
+
+/// A function bound to the instance it was looked up on.
+#[derive(Clone, PartialEq)]
+pub struct PyMethod {
+    pub receiver: PyObject,
+    pub func: Rc<PyFunction>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct PyIterator {
+    pub source: PyObject,
+    pub index: usize,
+}
+
+impl PyIterator {
+    pub fn new(source: PyObject) -> Self {
+        Self { source, index: 0 }
+    }
+}
+
+impl Iterator for PyIterator {
+    type Item = PyObject;
+
+    fn next(&mut self) -> Option<PyObject> {
+        let item = match &self.source {
+            PyObject::Iterator(inner) => return inner.borrow_mut().next(),
+            source => seq_item(source, self.index)?,
+        };
+        self.index += 1;
+        Some(item)
+    }
+}
+
+/// The `index`-th element produced by iterating `seq`, if any.
+pub fn seq_item(seq: &PyObject, index: usize) -> Option<PyObject> {
+    match seq {
+        PyObject::List(l) => l.borrow().get(index).cloned(),
+        PyObject::Deque(d) => d.borrow().get(index).cloned(),
+        PyObject::Tuple(t) => t.get(index).cloned(),
+        PyObject::Str(s) => s.chars().nth(index).map(|c| PyObject::Str(c.to_string())),
+        PyObject::Bytes(b) => b.get(index).map(|&c| PyObject::Int(c as i64)),
+        PyObject::Set(s) => s.borrow().get_index(index).cloned(),
+        PyObject::Dict(d) => d.borrow().get_index(index).map(|(k, _)| k.clone()),
+        PyObject::Range(start, stop, step) => {
+            let v = start.checked_add((index as i64).checked_mul(*step)?)?;
+            let in_range = if *step > 0 { v < *stop } else { v > *stop };
+            in_range.then_some(PyObject::Int(v))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct PyException {
+    pub kind: String,
+    pub message: String,
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PyType {
     pub name: String,
@@ -63,23 +148,30 @@ impl Display for PyObject {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PyObject::Int(v) => write!(f, "{v}"),
-            PyObject::Float(v) => write!(f, "{v}"),
-            PyObject::Bool(v) => write!(f, "{v}"),
+            PyObject::Float(v) => write!(f, "{}", format_float(*v)),
+            PyObject::Complex(re, im) => write!(f, "{}", format_complex(*re, *im)),
+            PyObject::Bool(true) => write!(f, "True"),
+            PyObject::Bool(false) => write!(f, "False"),
             PyObject::Str(v) => write!(f, "{}", v),
+            PyObject::Bytes(b) => write!(f, "{}", crate::core::bytes::repr(b)),
             PyObject::List(l) => {
-                let items: Vec<String> = l.borrow().iter().map(|x| format!("{}", x)).collect();
+                let items: Vec<String> = l.borrow().iter().map(|x| x.repr()).collect();
                 write!(f, "[{}]", items.join(", "))
             }
+            PyObject::Deque(d) => {
+                let items: Vec<String> = d.borrow().iter().map(|x| x.repr()).collect();
+                write!(f, "deque([{}])", items.join(", "))
+            }
             PyObject::Dict(d) => {
                 let items: Vec<String> = d
                     .borrow()
                     .iter()
-                    .map(|(k, v)| format!("'{}': {}", k, v))
+                    .map(|(k, v)| format!("{}: {}", k.repr(), v.repr()))
                     .collect();
                 write!(f, "{{{}}}", items.join(", "))
             }
             PyObject::Tuple(t) => {
-                let items: Vec<String> = t.iter().map(|x| format!("{}", x)).collect();
+                let items: Vec<String> = t.iter().map(|x| x.repr()).collect();
                 if t.len() == 1 {
                     write!(f, "({},)", items[0])
                 } else {
@@ -87,42 +179,208 @@ impl Display for PyObject {
                 }
             }
             PyObject::Set(s) => {
-                let items: Vec<String> = s.borrow().iter().map(|x| format!("{}", x)).collect();
+                let items: Vec<String> = s.borrow().iter().map(|x| x.repr()).collect();
                 write!(f, "{{{}}}", items.join(", "))
             }
+            PyObject::Range(start, stop, 1) => write!(f, "range({}, {})", start, stop),
+            PyObject::Range(start, stop, step) => {
+                write!(f, "range({}, {}, {})", start, stop, step)
+            }
             PyObject::None => write!(f, "None"),
+            PyObject::Ellipsis => write!(f, "Ellipsis"),
+            PyObject::Decimal(d) => write!(f, "{}", d),
             PyObject::Function(func) => write!(f, "<function {}>", func.name),
+            PyObject::Method(m) => write!(
+                f,
+                "<bound method {}.{} of {}>",
+                m.receiver.type_name(),
+                m.func.name,
+                m.receiver
+            ),
             PyObject::NativeFunction(func) => write!(f, "<native function {}>", func.name),
+            PyObject::VmFunction(func) => write!(f, "<native function {}>", func.name),
             PyObject::NativeModule(m) => write!(f, "<module '{}'>", m.name),
             PyObject::NativeClass(c) => write!(f, "<class '{}'>", c.name),
             PyObject::Type(t) => write!(f, "<type {}>", t.name),
             PyObject::Class(c) => write!(f, "<class '{}'>", c.name),
-            PyObject::Instance(i) => write!(f, "<{} object>", i.borrow().class.name),
+            PyObject::Instance(i) => write!(f, "<{} object>", class_name(i)),
             PyObject::Module(m) => write!(f, "<module '{}'>", m.borrow().name),
+            PyObject::Exception(e) => write!(f, "{}", e.message),
+            PyObject::Iterator(_) => write!(f, "<{} object>", self.type_name()),
+            PyObject::WeakRef(w) => match w.upgrade() {
+                Some(target) => write!(f, "<weakref; to '{}'>", target.type_name()),
+                None => write!(f, "<weakref; dead>"),
+            },
+            PyObject::Kwargs(kw) => {
+                let items: Vec<String> = kw
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v.repr()))
+                    .collect();
+                write!(f, "{}", items.join(", "))
+            }
+        }
+    }
+}
+
+impl PyObject {
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            PyObject::Int(_)
+            | PyObject::Float(_)
+            | PyObject::Complex(..)
+            | PyObject::Decimal(_)
+            | PyObject::Bool(_)
+            | PyObject::Str(_)
+            | PyObject::Bytes(_)
+            | PyObject::None
+            | PyObject::Ellipsis => true,
+            PyObject::Tuple(t) => t.iter().all(|x| x.is_hashable()),
+            _ => false,
+        }
+    }
+
+    pub fn type_name(&self) -> String {
+        match self {
+            PyObject::Int(_) => "int".to_string(),
+            PyObject::Float(_) => "float".to_string(),
+            PyObject::Complex(..) => "complex".to_string(),
+            PyObject::Decimal(_) => "Decimal".to_string(),
+            PyObject::Bool(_) => "bool".to_string(),
+            PyObject::Str(_) => "str".to_string(),
+            PyObject::Bytes(_) => "bytes".to_string(),
+            PyObject::List(_) => "list".to_string(),
+            PyObject::Deque(_) => "deque".to_string(),
+            PyObject::Dict(_) => "dict".to_string(),
+            PyObject::Tuple(_) => "tuple".to_string(),
+            PyObject::Set(_) => "set".to_string(),
+            PyObject::Range(..) => "range".to_string(),
+            PyObject::None => "NoneType".to_string(),
+            PyObject::Ellipsis => "ellipsis".to_string(),
+            PyObject::Function(_) => "function".to_string(),
+            PyObject::Method(_) => "method".to_string(),
+            PyObject::NativeFunction(_) | PyObject::VmFunction(_) => "native_function".to_string(),
+            PyObject::NativeModule(_) | PyObject::Module(_) => "module".to_string(),
+            PyObject::NativeClass(_) | PyObject::Type(_) | PyObject::Class(_) => "type".to_string(),
+            PyObject::Instance(inst) => class_name(inst),
+            PyObject::Exception(e) => e.kind.clone(),
+            PyObject::Iterator(it) => format!("{}_iterator", it.borrow().source.type_name()),
+            PyObject::WeakRef(_) => "weakref".to_string(),
+            PyObject::Kwargs(_) => "dict".to_string(),
+        }
+    }
+
+    pub fn repr(&self) -> String {
+        match self {
+            PyObject::Str(s) => repr_str(s),
+            PyObject::Decimal(d) => format!("Decimal('{}')", d),
+            _ => self.to_string(),
         }
     }
 }
 
+pub fn format_float(v: f64) -> String {
+    if v.is_nan() {
+        // Rust spells it `NaN`; `inf` and `-inf` already match Python
+        "nan".to_string()
+    } else if v.is_finite() && v.fract() == 0.0 && v.abs() < 1e16 {
+        format!("{:.1}", v)
+    } else {
+        format!("{}", v)
+    }
+}
+
+pub fn format_complex(re: f64, im: f64) -> String {
+    // components drop the trailing ".0" a float would show
+    let part = |v: f64| {
+        let s = format_float(v);
+        s.strip_suffix(".0").map(str::to_string).unwrap_or(s)
+    };
+
+    if re == 0.0 && re.is_sign_positive() {
+        format!("{}j", part(im))
+    } else {
+        let sign = if im < 0.0 || (im == 0.0 && im.is_sign_negative()) {
+            "-"
+        } else {
+            "+"
+        };
+        format!("({}{}{}j)", part(re), sign, part(im.abs()))
+    }
+}
+
+pub fn repr_str(s: &str) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push(quote);
+
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if (c as u32) < 0x20 || c as u32 == 0x7f => {
+                out.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push(quote);
+    out
+}
+
 impl fmt::Debug for PyObject {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PyObject::Int(v) => write!(f, "Int({})", v),
             PyObject::Float(v) => write!(f, "Float({})", v),
+            PyObject::Complex(re, im) => write!(f, "Complex({}, {})", re, im),
             PyObject::Bool(v) => write!(f, "Bool({})", v),
             PyObject::Str(v) => write!(f, "Str({:?})", v),
+            PyObject::Bytes(b) => write!(f, "Bytes({:?})", b),
             PyObject::List(l) => write!(f, "List({:?})", l.borrow().as_slice()),
+            PyObject::Deque(d) => write!(f, "Deque({:?})", d.borrow()),
             PyObject::Dict(d) => write!(f, "Dict({:?})", d.borrow()),
             PyObject::Tuple(t) => write!(f, "Tuple({:?})", t),
             PyObject::Set(s) => write!(f, "Set({:?})", s.borrow()),
+            PyObject::Range(start, stop, step) => write!(f, "Range({}, {}, {})", start, stop, step),
             PyObject::None => write!(f, "None"),
+            PyObject::Ellipsis => write!(f, "Ellipsis"),
+            PyObject::Decimal(d) => write!(f, "Decimal({})", d),
             PyObject::Function(func) => write!(f, "Function({})", func.name),
+            PyObject::Method(m) => write!(f, "Method({:?}, {})", m.receiver, m.func.name),
             PyObject::NativeFunction(func) => write!(f, "NativeFunction({})", func.name),
+            PyObject::VmFunction(func) => write!(f, "VmFunction({})", func.name),
             PyObject::NativeModule(m) => write!(f, "NativeModule({})", m.name),
             PyObject::NativeClass(c) => write!(f, "NativeClass({})", c.name),
             PyObject::Type(t) => write!(f, "Type({})", t.name),
             PyObject::Class(c) => write!(f, "Class({})", c.name),
-            PyObject::Instance(i) => write!(f, "Instance({})", i.borrow().class.name),
+            PyObject::Instance(i) => write!(f, "Instance({})", class_name(i)),
             PyObject::Module(m) => write!(f, "Module({})", m.borrow().name),
+            PyObject::Exception(e) => write!(f, "Exception({}: {:?})", e.kind, e.message),
+            PyObject::Iterator(it) => write!(f, "Iterator({:?})", it.borrow().source),
+            PyObject::WeakRef(w) => write!(f, "WeakRef({:?})", w.upgrade()),
+            PyObject::Kwargs(kw) => write!(f, "Kwargs({:?})", kw),
+        }
+    }
+}
+
+impl PyObject {
+    /// Splits the trailing keyword arguments off a native's argument list.
+    pub fn split_kwargs(args: &[PyObject]) -> (&[PyObject], Option<&IndexMap<String, PyObject>>) {
+        match args.split_last() {
+            Some((PyObject::Kwargs(kw), rest)) => (rest, Some(&**kw)),
+            _ => (args, None),
         }
     }
 }
@@ -157,19 +415,150 @@ impl From<&str> for PyObject {
     }
 }
 
+impl From<String> for PyObject {
+    fn from(v: String) -> Self {
+        PyObject::Str(v)
+    }
+}
+
+impl PyObject {
+    pub fn int(v: i64) -> Self {
+        PyObject::Int(v)
+    }
+
+    pub fn float(v: f64) -> Self {
+        PyObject::Float(v)
+    }
+
+    pub fn str(v: impl Into<String>) -> Self {
+        PyObject::Str(v.into())
+    }
+
+    pub fn list(items: Vec<PyObject>) -> Self {
+        PyObject::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn tuple(items: Vec<PyObject>) -> Self {
+        PyObject::Tuple(items)
+    }
+
+    pub fn dict<K: Into<PyObject>>(pairs: impl IntoIterator<Item = (K, PyObject)>) -> Self {
+        let map = pairs.into_iter().map(|(k, v)| (k.into(), v)).collect();
+        PyObject::Dict(Rc::new(RefCell::new(map)))
+    }
+
+    pub fn native_fn<F>(name: &str, arity: usize, f: F) -> Self
+    where
+        F: Fn(&[PyObject]) -> Result<PyObject, String> + 'static,
+    {
+        PyObject::NativeFunction(Rc::new(PyNativeFunction {
+            name: name.to_string(),
+            arity,
+            func: Rc::new(f),
+        }))
+    }
+
+    /// Like `native_fn`, but for closures that mutate captured state, e.g. a
+    /// counter or a cache kept between calls. State shared with other natives
+    /// can instead be captured as an `Rc<RefCell<_>>` by a plain `native_fn`.
+    pub fn native_fn_mut<F>(name: &str, arity: usize, f: F) -> Self
+    where
+        F: FnMut(&[PyObject]) -> Result<PyObject, String> + 'static,
+    {
+        // natives never re-enter themselves, so the borrow cannot conflict
+        let f = RefCell::new(f);
+        PyObject::native_fn(name, arity, move |args| (f.borrow_mut())(args))
+    }
+
+    /// Like `native_fn`, for natives that call back into Python code, e.g.
+    /// to apply a `key=` function, through `Vm::call_value`.
+    pub fn vm_fn<F>(name: &str, arity: usize, f: F) -> Self
+    where
+        F: Fn(&mut Vm, &[PyObject]) -> Result<PyObject, String> + 'static,
+    {
+        PyObject::VmFunction(Rc::new(PyVmFunction {
+            name: name.to_string(),
+            arity,
+            func: Rc::new(f),
+        }))
+    }
+}
+
 impl std::hash::Hash for PyObject {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
+            // numbers that compare equal must hash equal, so `1`, `1.0` and
+            // `True` share the integer hash
             PyObject::Int(v) => v.hash(state),
-            PyObject::Float(v) => v.to_bits().hash(state),
-            PyObject::Bool(v) => v.hash(state),
+            PyObject::Bool(v) => (*v as i64).hash(state),
+            PyObject::Float(v) => match float_as_int(*v) {
+                Some(n) => n.hash(state),
+                None => v.to_bits().hash(state),
+            },
+            PyObject::Complex(re, im) => (re.to_bits(), im.to_bits()).hash(state),
+            PyObject::Decimal(d) => d.hash(state),
             PyObject::Str(v) => v.hash(state),
+            PyObject::Bytes(b) => b.hash(state),
+            PyObject::Tuple(t) => t.hash(state),
             PyObject::None => 0.hash(state),
+            PyObject::Ellipsis => 1.hash(state),
             _ => panic!("unhashable type"),
         }
     }
 }
 
+/// The integer equal to `f`, if there is one.
+fn float_as_int(f: f64) -> Option<i64> {
+    // i64::MAX as f64 rounds up to 2^63, which is already out of range
+    (f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64).then_some(f as i64)
+}
+
+impl PartialEq for PyObject {
+    fn eq(&self, other: &Self) -> bool {
+        use PyObject::*;
+
+        match (self, other) {
+            (Int(a), Int(b)) => a == b,
+            (Bool(a), Bool(b)) => a == b,
+            (Float(a), Float(b)) => a == b,
+            (Int(a), Bool(b)) | (Bool(b), Int(a)) => *a == *b as i64,
+            (Int(a), Float(b)) | (Float(b), Int(a)) => float_as_int(*b) == Some(*a),
+            (Bool(a), Float(b)) | (Float(b), Bool(a)) => *b == *a as i64 as f64,
+            (Complex(a, b), Complex(c, d)) => a == c && b == d,
+            (Decimal(a), Decimal(b)) => a == b,
+            (Decimal(a), Int(b)) | (Int(b), Decimal(a)) => *a == Decimal::from_int(*b),
+            (Decimal(a), Bool(b)) | (Bool(b), Decimal(a)) => *a == Decimal::from_int(*b as i64),
+            (Str(a), Str(b)) => a == b,
+            (Bytes(a), Bytes(b)) => a == b,
+            (List(a), List(b)) => a == b,
+            (Deque(a), Deque(b)) => a == b,
+            // `IndexMap`/`IndexSet` equality ignores insertion order, as in Python
+            (Dict(a), Dict(b)) => a == b,
+            (Tuple(a), Tuple(b)) => a == b,
+            (Set(a), Set(b)) => a == b,
+            (Range(a, b, c), Range(d, e, f)) => (a, b, c) == (d, e, f),
+            (None, None) | (Ellipsis, Ellipsis) => true,
+            (Function(a), Function(b)) => a == b,
+            (Method(a), Method(b)) => a == b,
+            (NativeFunction(a), NativeFunction(b)) => a == b,
+            (VmFunction(a), VmFunction(b)) => Rc::ptr_eq(a, b),
+            (NativeModule(a), NativeModule(b)) => a == b,
+            (NativeClass(a), NativeClass(b)) => a == b,
+            (Type(a), Type(b)) => a == b,
+            (Class(a), Class(b)) => a == b,
+            // instances are only equal to themselves unless their class
+            // defines `__eq__`, which the VM calls before getting here
+            (Instance(a), Instance(b)) => Rc::ptr_eq(a, b),
+            (Module(a), Module(b)) => a == b,
+            (Exception(a), Exception(b)) => a == b,
+            (Iterator(a), Iterator(b)) => a == b,
+            (WeakRef(a), WeakRef(b)) => a == b,
+            (Kwargs(a), Kwargs(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
 impl Eq for PyObject {}
 
 #[derive(Clone)]
@@ -197,6 +586,58 @@ impl PartialEq for PyNativeClass {
     }
 }
 
+/// A reference that does not keep its target alive. Only mutable containers
+/// and instances can be referenced weakly.
+#[derive(Clone)]
+pub enum PyWeakRef {
+    List(Weak<RefCell<Vec<PyObject>>>),
+    Dict(Weak<RefCell<IndexMap<PyObject, PyObject>>>),
+    Set(Weak<RefCell<IndexSet<PyObject>>>),
+    Instance(Weak<RefCell<PyInstance>>),
+}
+
+impl PyWeakRef {
+    pub fn new(obj: &PyObject) -> Option<PyWeakRef> {
+        match obj {
+            PyObject::List(l) => Some(PyWeakRef::List(Rc::downgrade(l))),
+            PyObject::Dict(d) => Some(PyWeakRef::Dict(Rc::downgrade(d))),
+            PyObject::Set(s) => Some(PyWeakRef::Set(Rc::downgrade(s))),
+            PyObject::Instance(i) => Some(PyWeakRef::Instance(Rc::downgrade(i))),
+            _ => None,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        match self {
+            PyWeakRef::List(w) => w.strong_count() > 0,
+            PyWeakRef::Dict(w) => w.strong_count() > 0,
+            PyWeakRef::Set(w) => w.strong_count() > 0,
+            PyWeakRef::Instance(w) => w.strong_count() > 0,
+        }
+    }
+
+    pub fn upgrade(&self) -> Option<PyObject> {
+        match self {
+            PyWeakRef::List(w) => w.upgrade().map(PyObject::List),
+            PyWeakRef::Dict(w) => w.upgrade().map(PyObject::Dict),
+            PyWeakRef::Set(w) => w.upgrade().map(PyObject::Set),
+            PyWeakRef::Instance(w) => w.upgrade().map(PyObject::Instance),
+        }
+    }
+}
+
+impl PartialEq for PyWeakRef {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PyWeakRef::List(a), PyWeakRef::List(b)) => a.ptr_eq(b),
+            (PyWeakRef::Dict(a), PyWeakRef::Dict(b)) => a.ptr_eq(b),
+            (PyWeakRef::Set(a), PyWeakRef::Set(b)) => a.ptr_eq(b),
+            (PyWeakRef::Instance(a), PyWeakRef::Instance(b)) => a.ptr_eq(b),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct PyNativeFunction {
     pub name: String,
@@ -220,12 +661,21 @@ impl PartialEq for PyNativeFunction {
     }
 }
 
+#[derive(Clone)]
+pub struct PyVmFunction {
+    pub name: String,
+    pub arity: usize,
+    pub func: Rc<dyn Fn(&mut Vm, &[PyObject]) -> Result<PyObject, String>>,
+}
+
 #[derive(Clone, PartialEq)]
 pub struct PyFunction {
     pub name: String,
     pub arity: usize,
     pub code: CodeObject,
     pub globals: Env,
+    // values for the last `defaults.len()` parameters
+    pub defaults: Vec<PyObject>,
 }
 
 impl Default for PyFunction {
@@ -235,6 +685,7 @@ impl Default for PyFunction {
             arity: 0,
             code: CodeObject::default(),
             globals: Env::default(),
+            defaults: Vec::new(),
         }
     }
 }
@@ -7,7 +7,20 @@ pub enum Op {
     StoreName(usize),
     LoadGlobal(usize),
     StoreGlobal(usize),
+    /// Pushes the local `names[idx]` as a one-item tuple, or an empty tuple
+    /// if it is unbound.
+    SaveName(usize),
+    /// Pops a tuple pushed by `SaveName` and rebinds or unbinds the local.
+    RestoreName(usize),
     Pop,
+    /// Pushes a copy of the top of the stack.
+    Dup,
+    /// Exchanges the top two stack items.
+    Swap,
+    /// Pushes copies of the top two stack items, keeping their order.
+    DupTop2,
+    /// Moves the third stack item to the top, e.g. `[a, b, c]` to `[b, c, a]`.
+    RotThree,
     Return,
     Call(usize),
     BuildList(usize),
@@ -16,29 +29,57 @@ pub enum Op {
     BuildSet(usize),
     LoadIndex,
     StoreIndex,
+    ListAppend,
+    SetAdd,
+    MapAdd,
     Def {
         name: usize,
         arity: usize,
+        defaults: usize,
         code_idx: usize,
     },
     UnaryNeg,
     // ??
     UnaryPos,
+    UnaryNot,
     Add,
     Sub,
     Mul,
     Div,
+    FloorDiv,
     Eq,
     Ne,
     Lt,
     Le,
     Gt,
     Ge,
+    Contains,
     Jump(usize),
     JumpIfFalse(usize),
     JumpIfTrue(usize),
-    SetupLoop(usize),
+    /// Jumps keeping the condition on the stack if it is falsey, else pops it.
+    JumpIfFalseOrPop(usize),
+    /// Jumps keeping the condition on the stack if it is truthy, else pops it.
+    JumpIfTrueOrPop(usize),
+    SetupLoop {
+        cont: usize,
+        exit: usize,
+    },
     PopBlock,
+    UnpackSequence(usize),
+    UnpackEx {
+        before: usize,
+        after: usize,
+    },
+    SetupExcept(usize),
+    PopExcept,
+    EndExcept,
+    ExceptMatch {
+        name: usize,
+        target: usize,
+    },
+    Raise(usize),
+    Reraise,
     Break,
     Continue,
     ForIter(usize),
@@ -50,10 +91,12 @@ pub enum Op {
     LoadAttr(usize),
     StoreAttr(usize),
     CallMethod(usize),
+    CallKw(usize),
     Import(usize),
     ImportFrom {
         module: usize,
-        names: Vec<usize>,
+        // (name in the module, name to bind)
+        names: Vec<(usize, usize)>,
     },
     ImportStar(usize),
 }
@@ -66,7 +109,13 @@ impl Display for Op {
             Op::StoreName(idx) => write!(f, "StoreName({})", idx),
             Op::LoadGlobal(idx) => write!(f, "LoadGlobal({})", idx),
             Op::StoreGlobal(idx) => write!(f, "StoreGlobal({})", idx),
+            Op::SaveName(idx) => write!(f, "SaveName({})", idx),
+            Op::RestoreName(idx) => write!(f, "RestoreName({})", idx),
             Op::Pop => write!(f, "Pop"),
+            Op::Dup => write!(f, "Dup"),
+            Op::Swap => write!(f, "Swap"),
+            Op::DupTop2 => write!(f, "DupTop2"),
+            Op::RotThree => write!(f, "RotThree"),
             Op::Return => write!(f, "Return"),
             Op::Call(argc) => write!(f, "Call({})", argc),
             Op::BuildList(count) => write!(f, "BuildList({})", count),
@@ -75,32 +124,53 @@ impl Display for Op {
             Op::BuildSet(count) => write!(f, "BuildSet({})", count),
             Op::LoadIndex => write!(f, "LoadIndex"),
             Op::StoreIndex => write!(f, "StoreIndex"),
+            Op::ListAppend => write!(f, "ListAppend"),
+            Op::SetAdd => write!(f, "SetAdd"),
+            Op::MapAdd => write!(f, "MapAdd"),
             Op::Def {
                 name,
                 arity,
+                defaults,
                 code_idx,
             } => write!(
                 f,
-                "Def(name={}, arity={}, code_idx={})",
-                name, arity, code_idx
+                "Def(name={}, arity={}, defaults={}, code_idx={})",
+                name, arity, defaults, code_idx
             ),
             Op::UnaryNeg => write!(f, "UnaryMinus"),
             Op::UnaryPos => write!(f, "UnaryPlus"),
+            Op::UnaryNot => write!(f, "UnaryNot"),
             Op::Add => write!(f, "Add"),
             Op::Sub => write!(f, "Sub"),
             Op::Mul => write!(f, "Mul"),
             Op::Div => write!(f, "Div"),
+            Op::FloorDiv => write!(f, "FloorDiv"),
             Op::Eq => write!(f, "Eq"),
             Op::Ne => write!(f, "Ne"),
             Op::Lt => write!(f, "Lt"),
             Op::Le => write!(f, "Le"),
             Op::Gt => write!(f, "Gt"),
             Op::Ge => write!(f, "Ge"),
+            Op::Contains => write!(f, "Contains"),
             Op::Jump(target) => write!(f, "Jump({})", target),
             Op::JumpIfTrue(target) => write!(f, "JumpIfTrue({})", target),
             Op::JumpIfFalse(target) => write!(f, "JumpIfFalse({})", target),
-            Op::SetupLoop(exit) => write!(f, "SetupLoop({})", exit),
+            Op::JumpIfFalseOrPop(target) => write!(f, "JumpIfFalseOrPop({})", target),
+            Op::JumpIfTrueOrPop(target) => write!(f, "JumpIfTrueOrPop({})", target),
+            Op::SetupLoop { cont, exit } => write!(f, "SetupLoop(cont={}, exit={})", cont, exit),
             Op::PopBlock => write!(f, "PopBlock"),
+            Op::UnpackSequence(count) => write!(f, "UnpackSequence({})", count),
+            Op::UnpackEx { before, after } => {
+                write!(f, "UnpackEx(before={}, after={})", before, after)
+            }
+            Op::SetupExcept(handler) => write!(f, "SetupExcept({})", handler),
+            Op::PopExcept => write!(f, "PopExcept"),
+            Op::EndExcept => write!(f, "EndExcept"),
+            Op::ExceptMatch { name, target } => {
+                write!(f, "ExceptMatch(name={}, target={})", name, target)
+            }
+            Op::Raise(argc) => write!(f, "Raise({})", argc),
+            Op::Reraise => write!(f, "Reraise"),
             Op::Break => write!(f, "Break"),
             Op::Continue => write!(f, "Continue"),
             Op::GetIter => write!(f, "GetIter"),
@@ -111,6 +181,7 @@ impl Display for Op {
             Op::LoadAttr(idx) => write!(f, "LoadAttr({})", idx),
             Op::StoreAttr(idx) => write!(f, "StoreAttr({})", idx),
             Op::CallMethod(argc) => write!(f, "CallMethod({})", argc),
+            Op::CallKw(argc) => write!(f, "CallKw({})", argc),
             Op::Import(idx) => write!(f, "Import({})", idx),
             Op::ImportFrom { module, names } => {
                 write!(f, "ImportFrom(module={}, names={:?})", module, names)
@@ -1,38 +1,232 @@
 use crate::bytecode::*;
+use crate::core::decimal::Decimal;
 use crate::object::*;
 use crate::opcode::*;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::io::Write;
 use std::rc::Rc;
 
-#[derive(Clone, Default, PartialEq)]
+pub type Namespace = Rc<RefCell<HashMap<String, PyObject>>>;
+
+/// Name resolution context for a frame. At module scope `locals` and `globals`
+/// are the same namespace, so functions defined there see later bindings.
+#[derive(Clone)]
 pub struct Env {
-    pub locals: HashMap<String, PyObject>,
-    pub globals: HashMap<String, PyObject>,
+    pub locals: Namespace,
+    pub globals: Namespace,
     pub builtins: HashMap<String, PyObject>,
 }
 
+impl Default for Env {
+    fn default() -> Self {
+        let globals = Namespace::default();
+        Self {
+            locals: globals.clone(),
+            globals,
+            builtins: HashMap::new(),
+        }
+    }
+}
+
+impl PartialEq for Env {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.locals, &other.locals) && Rc::ptr_eq(&self.globals, &other.globals)
+    }
+}
+
+impl Env {
+    /// A fresh local scope sharing this env's globals and builtins.
+    pub fn child(&self) -> Env {
+        Env {
+            locals: Namespace::default(),
+            globals: self.globals.clone(),
+            builtins: self.builtins.clone(),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Vm {
     pub stack: Vec<PyObject>,
     pub env: Env,
-    pub loop_stack: Vec<(usize, usize)>,
+    pub loop_stack: Vec<(usize, usize, usize)>,
     pub iter_stack: Vec<(usize, PyObject)>,
+    pub handler_stack: Vec<(usize, usize, usize, usize, usize)>,
     pub modules: HashMap<String, PyObject>,
+    handling: Option<PyObject>,
+    // what `handling` was before each `except` block still running
+    handled: Vec<Option<PyObject>>,
+    tracked: Vec<crate::gc::Tracked>,
+    // `sys.argv` for scripts; the process args when unset
+    argv: Option<Vec<String>>,
+    stats: Option<ExecStats>,
+    // instruction of the run code object an uncaught error came from
+    error_ip: Option<usize>,
+    // sinks swapped in for the process streams while `run` executes
+    stdout: Option<Box<dyn Write>>,
+    stderr: Option<Box<dyn Write>>,
+}
+
+/// Counters gathered by `Vm::run` once `Vm::enable_stats` has been called.
+#[derive(Clone, Debug, Default)]
+pub struct ExecStats {
+    pub instructions: u64,
+    pub per_op: HashMap<String, u64>,
+    pub elapsed: std::time::Duration,
+}
+
+struct Frame {
+    ret_ip: usize,
+    code: CodeObject,
+    env: Env,
+    stack_len: usize,
+    loop_stack: Vec<(usize, usize, usize)>,
+    iter_stack: Vec<(usize, PyObject)>,
+    handler_stack: Vec<(usize, usize, usize, usize, usize)>,
+    // set when the frame runs `__init__`; the call evaluates to this instead
+    instance: Option<PyObject>,
+}
+
+struct ExecState {
+    ip: usize,
+    cur: CodeObject,
+    frames: Vec<Frame>,
+}
+
+const LOCATION_MARKER: &str = "\n  at instruction ";
+
+/// Strips a location suffix from `err`, if it carries one.
+pub fn error_message(err: &str) -> &str {
+    err.split(LOCATION_MARKER).next().unwrap_or(err)
+}
+
+/// Exit status requested by an uncaught `SystemExit`, if that is what `err` is.
+/// Non-integer payloads map to 1, like CPython.
+pub fn exit_code(err: &str) -> Option<i32> {
+    let payload = error_message(err).strip_prefix("SystemExit")?;
+    let payload = payload.trim_start_matches(':').trim();
+
+    if payload.is_empty() {
+        Some(0)
+    } else {
+        Some(payload.parse().unwrap_or(1))
+    }
+}
+
+/// Renders an error returned by `Vm::run` as a Python-style traceback, using
+/// the line table of the code object that was run and the instruction from
+/// `Vm::last_error_ip`.
+pub fn format_traceback(err: &str, code: &CodeObject, ip: Option<usize>) -> String {
+    let mut out = String::from("Traceback (most recent call last):\n");
+
+    let line = ip.and_then(|ip| code.lines.get(ip).or(code.lines.last()).copied());
+
+    if let Some(line) = line {
+        out.push_str(&format!(
+            "  File \"<string>\", line {}, in <module>\n",
+            line
+        ));
+    }
+
+    out.push_str(error_message(err));
+    out
 }
 
 impl Vm {
     pub fn with_builtins(mut self) -> Self {
         self.register_native_module("os", crate::core::os::os_module());
-        self.register_native_module("sys", crate::core::sys::sys_module());
+        let argv = self
+            .argv
+            .clone()
+            .unwrap_or_else(|| std::env::args().collect());
+        self.register_native_module("sys", crate::core::sys::sys_module(argv));
         self.register_native_module("io", crate::core::io::io_module());
         self.register_native_module("time", crate::core::time::time_module());
         self.register_native_module("math", crate::core::math::math_module());
+        self.register_native_module("decimal", crate::core::decimal::decimal_module());
+        self.register_native_module("weakref", crate::core::weakref::weakref_module());
+        self.register_native_module("pprint", crate::core::pprint::pprint_module());
+        self.register_native_module("datetime", crate::core::datetime::datetime_module());
+        self.register_native_module("operator", crate::core::operator::operator_module());
+        self.register_native_module("heapq", crate::core::heapq::heapq_module());
+        self.register_native_module(
+            "collections",
+            crate::core::collections::collections_module(),
+        );
+        self.register_native_module("_functools", crate::core::functools::functools_module());
+        self.register_native_module("string", crate::core::string::string_module());
+        self.register_native_module("re", crate::core::re::re_module());
+        self.register_native_module("statistics", crate::core::statistics::statistics_module());
+        self.register_native_module("bisect", crate::core::bisect::bisect_module());
+        self.register_native_module("gc", crate::core::gc::gc_module());
+        self.register_native_module("typing", crate::core::typing::typing_module());
         crate::core::globs::apply(&mut self.env.builtins);
+        crate::core::exceptions::apply(&mut self.env.builtins);
         self
     }
 
+    /// Sends `print` and `sys.stdout` output to `out` instead of the process stdout.
+    pub fn with_stdout(mut self, out: impl Write + 'static) -> Self {
+        self.stdout = Some(Box::new(out));
+        self
+    }
+
+    /// Sends `sys.stderr` output to `err` instead of the process stderr.
+    pub fn with_stderr(mut self, err: impl Write + 'static) -> Self {
+        self.stderr = Some(Box::new(err));
+        self
+    }
+
+    /// Replaces `sys.argv` as seen by scripts run on this VM.
+    pub fn set_argv(&mut self, argv: Vec<String>) {
+        // scripts may already hold the list, so it is refilled in place
+        let current = match self.modules.get("sys") {
+            Some(PyObject::NativeModule(sys)) => sys.dict.get("argv").cloned(),
+            _ => None,
+        };
+        if let Some(PyObject::List(list)) = current {
+            *list.borrow_mut() = argv.iter().cloned().map(PyObject::Str).collect();
+        }
+        self.argv = Some(argv);
+    }
+
+    pub fn enable_stats(&mut self) {
+        self.stats.get_or_insert_with(ExecStats::default);
+    }
+
+    /// Stats accumulated over every `run` since they were enabled.
+    pub fn stats(&self) -> Option<&ExecStats> {
+        self.stats.as_ref()
+    }
+
+    /// Instruction of the top-level code the last failed `run` stopped at.
+    pub fn last_error_ip(&self) -> Option<usize> {
+        self.error_ip
+    }
+
+    /// Breaks reference cycles between containers created by this VM that are
+    /// no longer reachable, returning how many containers were freed.
+    pub fn collect_cycles(&mut self) -> usize {
+        crate::gc::collect(&mut self.tracked)
+    }
+
+    /// How many containers created by this VM are still alive.
+    pub(crate) fn tracked_count(&self) -> usize {
+        self.tracked.iter().filter(|t| t.is_alive()).count()
+    }
+
+    fn track(&mut self, obj: &PyObject) {
+        // drop entries for freed objects before the list would have to grow
+        if self.tracked.len() == self.tracked.capacity() {
+            self.tracked.retain(|t| t.is_alive());
+        }
+
+        self.tracked.extend(crate::gc::Tracked::new(obj));
+    }
+
     pub fn register_native_module(&mut self, name: &str, dict: HashMap<String, PyObject>) {
         let module = PyNativeModule {
             name: name.to_string(),
@@ -50,6 +244,14 @@ impl Vm {
     ) where
         F: Fn(&[PyObject]) -> Result<PyObject, String> + 'static,
     {
+        // instances the constructor returns get `methods` as their class's
+        // method table, so native methods are found like Python ones
+        let instance_class = Rc::new(PyClass {
+            name: name.to_string(),
+            methods: RefCell::new(methods.clone()),
+            bases: Vec::new(),
+        });
+
         let class = PyNativeClass {
             name: name.to_string(),
             methods,
@@ -61,7 +263,13 @@ impl Vm {
             arity: usize::MAX,
             func: {
                 let class_rc = Rc::new(class);
-                Rc::new(move |args| (class_rc.constructor)(args))
+                Rc::new(move |args| {
+                    let obj = (class_rc.constructor)(args)?;
+                    if let PyObject::Instance(inst) = &obj {
+                        inst.borrow_mut().class = instance_class.clone();
+                    }
+                    Ok(obj)
+                })
             },
         };
 
@@ -75,7 +283,7 @@ impl Vm {
     where
         F: Fn(&[PyObject]) -> Result<PyObject, String> + 'static,
     {
-        self.env.globals.insert(
+        self.env.builtins.insert(
             name.to_string(),
             PyObject::NativeFunction(Rc::new(PyNativeFunction {
                 name: name.to_string(),
@@ -85,24 +293,60 @@ impl Vm {
         );
     }
 
+    /// Discards all state from previous runs so the VM can execute an
+    /// unrelated script. Builtins, registered natives and native modules are
+    /// kept; modules imported from source are dropped.
+    pub fn reset(&mut self) {
+        self.stack.clear();
+        self.loop_stack.clear();
+        self.iter_stack.clear();
+        self.handler_stack.clear();
+        self.handling = None;
+        self.handled.clear();
+        self.tracked.clear();
+        self.modules
+            .retain(|_, m| matches!(m, PyObject::NativeModule(_)));
+
+        let builtins = std::mem::take(&mut self.env.builtins);
+        self.env = Env {
+            builtins,
+            ..Env::default()
+        };
+    }
+
     fn load_module(&mut self, name: &str) -> Result<PyObject, String> {
         if let Some(module) = self.modules.get(name) {
             return Ok(module.clone());
         }
 
-        let filename = format!("{}.py", name);
-        let source = std::fs::read_to_string(&filename)
-            .map_err(|_| format!("ModuleNotFoundError: No module named '{}'", name))?;
+        // `a.b` lives in `a/b.py` and is also exposed as an attribute of `a`
+        let parent = match name.rsplit_once('.') {
+            Some((parent, _)) => Some(self.load_module(parent)?),
+            None => None,
+        };
+
+        let path = name.replace('.', "/");
+        let not_found = || format!("ModuleNotFoundError: No module named '{}'", name);
+        let source = match crate::core::source_module(name) {
+            Some(source) => Ok(source.to_string()),
+            None => std::fs::read_to_string(format!("{}.py", path)),
+        };
+        let source = match source {
+            Ok(source) => source,
+            Err(_) if std::path::Path::new(&path).is_dir() => {
+                // a package, with or without an __init__.py
+                std::fs::read_to_string(format!("{}/__init__.py", path)).unwrap_or_default()
+            }
+            Err(_) => return Err(not_found()),
+        };
 
         let mut compiler = crate::ast::Compiler::default();
         let code = compiler.compile(&source)?;
 
         let mut module_vm = Vm {
-            stack: Vec::new(),
-            env: Env::default(),
-            loop_stack: Vec::new(),
-            iter_stack: Vec::new(),
             modules: self.modules.clone(),
+            argv: self.argv.clone(),
+            ..Default::default()
         }
         .with_builtins();
 
@@ -116,959 +360,2138 @@ impl Vm {
         let module_obj = PyObject::Module(Rc::new(RefCell::new(module)));
         self.modules.insert(name.to_string(), module_obj.clone());
 
+        if let (Some(PyObject::Module(parent)), Some((_, attr))) = (parent, name.rsplit_once('.')) {
+            parent
+                .borrow()
+                .dict
+                .borrow_mut()
+                .insert(attr.to_string(), module_obj.clone());
+        }
+
         Ok(module_obj)
     }
 
     pub fn run(&mut self, code: &CodeObject) -> Result<PyObject, String> {
-        let mut ip = 0usize;
-        let mut frames: Vec<(usize, CodeObject, Env)> = Vec::new();
-        let mut cur = code.clone();
+        let mut st = ExecState {
+            ip: 0,
+            cur: code.clone(),
+            frames: Vec::new(),
+        };
 
-        // dbg!(cur.instructions.clone());
-        cur.debug_print();
+        // dbg!(st.cur.instructions.clone());
+        st.cur.debug_print();
 
-        loop {
-            if ip >= cur.instructions.len() {
-                return Ok(PyObject::None);
-            }
+        self.error_ip = None;
+        self.with_sinks(|vm| vm.run_state(&mut st))
+    }
 
-            match cur.instructions[ip] {
-                Op::LoadConst(idx) => {
-                    self.stack.push(cur.consts[idx].clone());
-                    ip += 1;
-                }
-                Op::LoadName(idx) => {
-                    let name = &cur.names[idx];
-                    if let Some(v) = self.env.locals.get(name) {
-                        self.stack.push(v.clone());
-                    } else if let Some(v) = self.env.globals.get(name) {
-                        self.stack.push(v.clone());
-                    } else if let Some(v) = self.env.builtins.get(name) {
-                        self.stack.push(v.clone());
-                    } else {
-                        return Err(format!("NameError: name '{}' is not defined", name));
-                    }
+    /// Calls a Python function from Rust, e.g. a handler a script registered,
+    /// and runs it to completion.
+    pub fn call_function(
+        &mut self,
+        func: &PyObject,
+        args: &[PyObject],
+    ) -> Result<PyObject, String> {
+        self.with_sinks(|vm| vm.call_value(func.clone(), args.to_vec()))
+    }
 
-                    ip += 1;
-                }
-                Op::StoreName(idx) => {
-                    let name = cur.names[idx].clone();
-                    let v = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.env.locals.insert(name, v);
-                    ip += 1;
+    /// Runs `f` with `print` and `sys.std*` writing to this VM's sinks.
+    fn with_sinks<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let stdout = self.stdout.take().map(crate::core::io::set_stdout);
+        let stderr = self.stderr.take().map(crate::core::io::set_stderr);
+
+        let result = f(self);
+
+        // hand the process streams back, keeping the sinks for the next run
+        self.stdout = stdout.map(crate::core::io::set_stdout);
+        self.stderr = stderr.map(crate::core::io::set_stderr);
+
+        result
+    }
+
+    fn run_state(&mut self, st: &mut ExecState) -> Result<PyObject, String> {
+        let started = std::time::Instant::now();
+        let result = loop {
+            if let Some(stats) = &mut self.stats {
+                if let Some(op) = st.cur.instructions.get(st.ip) {
+                    let name = format!("{:?}", op);
+                    let name = name
+                        .split(|c: char| !c.is_alphanumeric())
+                        .next()
+                        .unwrap_or("");
+                    stats.instructions += 1;
+                    *stats.per_op.entry(name.to_string()).or_default() += 1;
                 }
-                Op::LoadGlobal(idx) => {
-                    let name = &cur.names[idx];
-                    if let Some(v) = self
-                        .env
-                        .globals
-                        .get(name)
-                        .cloned()
-                        .or_else(|| self.env.builtins.get(name).cloned())
-                    {
-                        self.stack.push(v);
-                        ip += 1;
-                    } else {
-                        return Err(format!("NameError: global '{}' is not defined", name));
+            }
+
+            match self.step(st) {
+                Ok(Some(ret)) => break Ok(ret),
+                Ok(None) => {}
+                Err(e) => {
+                    let top_ip = st.frames.first().map_or(st.ip, |f| f.ret_ip - 1);
+                    if !self.unwind(st, &e) {
+                        self.error_ip = Some(top_ip);
+                        break Err(e);
                     }
                 }
-                Op::StoreGlobal(idx) => {
-                    let name = cur.names[idx].clone();
-                    let v = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.env.globals.insert(name, v);
-                    ip += 1;
-                }
-                Op::Pop => {
-                    self.stack.pop();
-                    ip += 1;
+            }
+        };
+
+        if let Some(stats) = &mut self.stats {
+            stats.elapsed += started.elapsed();
+        }
+
+        result
+    }
+
+    /// Transfers control to the innermost active `except` handler, popping
+    /// call frames that have none. Returns false if nothing catches `err`.
+    fn unwind(&mut self, st: &mut ExecState, err: &str) -> bool {
+        loop {
+            if let Some((handler, stack_len, loop_depth, iter_depth, handled_depth)) =
+                self.handler_stack.pop()
+            {
+                let exc = crate::core::exceptions::from_error(error_message(err));
+                self.stack.truncate(stack_len);
+                self.loop_stack.truncate(loop_depth);
+                self.iter_stack.truncate(iter_depth);
+                // except blocks the error escaped from are over; the oldest
+                // of them knows what was being handled at `try` time
+                let outer = if self.handled.len() > handled_depth {
+                    self.handled.drain(handled_depth..).next().flatten()
+                } else {
+                    self.handling.take()
+                };
+                self.handled.push(outer);
+                self.handling = Some(exc.clone());
+                self.stack.push(exc);
+                st.ip = handler;
+                return true;
+            }
+
+            match st.frames.pop() {
+                Some(frame) => {
+                    self.env = frame.env;
+                    self.loop_stack = frame.loop_stack;
+                    self.iter_stack = frame.iter_stack;
+                    self.handler_stack = frame.handler_stack;
+                    self.stack.truncate(frame.stack_len);
+                    st.cur = frame.code;
                 }
-                Op::Return => {
-                    let ret = self.stack.pop().unwrap_or(PyObject::None);
-                    if let Some((rip, parent, saved_env)) = frames.pop() {
-                        self.env = saved_env;
-                        cur = parent;
-                        ip = rip;
-                        self.stack.push(ret);
-                    } else {
-                        return Ok(ret);
+                None => return false,
+            }
+        }
+    }
+
+    fn pop_args(&mut self, argc: usize) -> Result<Vec<PyObject>, String> {
+        if self.stack.len() < argc {
+            return Err("stack underflow".to_string());
+        }
+
+        Ok(self.stack.split_off(self.stack.len() - argc))
+    }
+
+    /// Calls `callee`, either pushing a frame for a Python function or pushing
+    /// the result of a native one. Natives taking any number of arguments
+    /// receive keyword arguments as a trailing `PyObject::Kwargs`.
+    fn call_object(
+        &mut self,
+        st: &mut ExecState,
+        callee: PyObject,
+        args: Vec<PyObject>,
+        kwargs: Option<IndexMap<String, PyObject>>,
+    ) -> Result<(), String> {
+        match callee {
+            PyObject::Function(fobj) => {
+                let new_env = bind_args(&fobj, args, kwargs.unwrap_or_default())?;
+                self.push_frame(st, &fobj.code, new_env, None);
+            }
+            PyObject::Class(cls) => {
+                let instance = PyObject::Instance(Rc::new(RefCell::new(PyInstance {
+                    class: cls.clone(),
+                    attrs: HashMap::new(),
+                })));
+                self.track(&instance);
+
+                let init = cls.methods.borrow().get("__init__").cloned();
+                match init {
+                    Some(PyObject::Function(f)) => {
+                        let mut full_args = Vec::with_capacity(args.len() + 1);
+                        full_args.push(instance.clone());
+                        full_args.extend(args);
+                        let new_env = bind_args(&f, full_args, kwargs.unwrap_or_default())?;
+                        self.push_frame(st, &f.code, new_env, Some(instance));
                     }
-                }
-                Op::Call(argc) => {
-                    let mut args = Vec::with_capacity(argc);
-
-                    for _ in 0..argc {
-                        args.push(
-                            self.stack
-                                .pop()
-                                .ok_or_else(|| "stack underflow".to_string())?,
-                        );
+                    _ if !args.is_empty() || kwargs.as_ref().is_some_and(|kw| !kw.is_empty()) => {
+                        return Err(format!("TypeError: {}() takes no arguments", cls.name));
                     }
+                    _ => {
+                        self.stack.push(instance);
+                        st.ip += 1;
+                    }
+                }
+            }
+            PyObject::NativeFunction(nf) => {
+                let args = native_args(&nf.name, nf.arity, args, kwargs)?;
+                let r = (nf.func)(&args)?;
+                self.track(&r);
+                self.stack.push(r);
+                st.ip += 1;
+            }
+            PyObject::VmFunction(vf) => {
+                let args = native_args(&vf.name, vf.arity, args, kwargs)?;
+                let r = (vf.func)(self, &args)?;
+                self.track(&r);
+                self.stack.push(r);
+                st.ip += 1;
+            }
+            PyObject::Type(t) => {
+                let args = native_args(&t.name, usize::MAX, args, kwargs)?;
+                let r = crate::core::globs::construct(&t.name, &args)?;
+                self.track(&r);
+                self.stack.push(r);
+                st.ip += 1;
+            }
+            PyObject::WeakRef(w) => {
+                if !args.is_empty() || kwargs.is_some() {
+                    return Err("TypeError: weakref() takes no arguments".to_string());
+                }
 
-                    args.reverse();
-
-                    let callee = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-
-                    match callee {
-                        PyObject::Function(fobj) => {
-                            if fobj.arity != argc {
-                                return Err(format!(
-                                    "TypeError: {}() expected {} args, got {}",
-                                    fobj.name, fobj.arity, argc
-                                ));
-                            }
+                self.stack.push(w.upgrade().unwrap_or(PyObject::None));
+                st.ip += 1;
+            }
+            PyObject::Method(m) => {
+                let mut full_args = Vec::with_capacity(args.len() + 1);
+                full_args.push(m.receiver.clone());
+                full_args.extend(args);
+                return self.call_object(st, PyObject::Function(m.func.clone()), full_args, kwargs);
+            }
+            _ => return Err("TypeError: object not callable".to_string()),
+        }
 
-                            let mut new_env = Env::default();
+        Ok(())
+    }
 
-                            for (i, name) in fobj.code.names.iter().take(argc).enumerate() {
-                                new_env.locals.insert(name.clone(), args[i].clone());
-                            }
+    /// Calls `callee` and runs it to completion, for natives that call back
+    /// into Python code. A trailing `PyObject::Kwargs` in `args` is passed as
+    /// keyword arguments. Exceptions the callee doesn't catch are returned.
+    pub fn call_value(
+        &mut self,
+        callee: PyObject,
+        mut args: Vec<PyObject>,
+    ) -> Result<PyObject, String> {
+        let kwargs = match args.last() {
+            Some(PyObject::Kwargs(kw)) => {
+                let kw = (**kw).clone();
+                args.pop();
+                Some(kw)
+            }
+            _ => None,
+        };
 
-                            new_env.globals = fobj.globals.clone().globals;
-                            new_env.builtins = self.env.builtins.clone();
-                            frames.push((
-                                ip + 1,
-                                cur.clone(),
-                                std::mem::replace(&mut self.env, new_env),
-                            ));
-                            cur = fobj.code.clone();
-                            ip = 0;
-                        }
-                        PyObject::NativeFunction(nf) => {
-                            if nf.arity != usize::MAX && nf.arity != argc {
-                                return Err(format!(
-                                    "TypeError: {}() expected {} args, got {}",
-                                    nf.name, nf.arity, argc
-                                ));
-                            }
+        // the call returns to the second `Return`, which hands its result back
+        let mut st = ExecState {
+            ip: 0,
+            cur: CodeObject {
+                instructions: vec![Op::Return, Op::Return],
+                ..Default::default()
+            },
+            frames: Vec::new(),
+        };
 
-                            let r = (nf.func)(&args)?;
-                            self.stack.push(r);
-                            ip += 1;
+        // the caller's loops and handlers are out of the callee's reach
+        let stack_len = self.stack.len();
+        let loop_stack = std::mem::take(&mut self.loop_stack);
+        let iter_stack = std::mem::take(&mut self.iter_stack);
+        let handler_stack = std::mem::take(&mut self.handler_stack);
+        let handling = self.handling.clone();
+        let handled_depth = self.handled.len();
+
+        let result = match self.call_object(&mut st, callee, args, kwargs) {
+            Ok(()) => loop {
+                match self.step(&mut st) {
+                    Ok(Some(ret)) => break Ok(ret),
+                    Ok(None) => {}
+                    Err(e) => {
+                        if !self.unwind(&mut st, &e) {
+                            break Err(e);
                         }
-                        _ => return Err("TypeError: object not callable".to_string()),
                     }
                 }
-                Op::Def {
-                    name,
-                    arity,
-                    code_idx,
-                } => {
-                    let fname = cur.names[name].clone();
-                    let fcode = cur.nested[code_idx].clone();
-                    let f = PyFunction {
-                        name: fname.clone(),
-                        arity,
-                        code: fcode,
-                        globals: self.env.clone(),
-                    };
+            },
+            Err(e) => Err(e),
+        };
 
-                    self.env
-                        .locals
-                        .insert(fname, PyObject::Function(Rc::new(f)));
-                    ip += 1;
-                }
-                Op::UnaryNeg => {
-                    let operand = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
+        self.stack.truncate(stack_len);
+        self.loop_stack = loop_stack;
+        self.iter_stack = iter_stack;
+        self.handler_stack = handler_stack;
+        self.handling = handling;
+        self.handled.truncate(handled_depth);
 
-                    match operand {
-                        PyObject::Int(x) => self.stack.push(PyObject::Int(-x)),
-                        PyObject::Float(x) => self.stack.push(PyObject::Float(-x)),
-                        _ => {
-                            return Err(
-                                "TypeError: unsupported operand type for unary -".to_string()
-                            );
-                        }
-                    }
+        result
+    }
+
+    /// The method `name` of an instance's class, if `obj` is an instance whose
+    /// class defines it.
+    fn instance_method(&self, obj: &PyObject, name: &str) -> Option<PyObject> {
+        match obj {
+            PyObject::Instance(inst) => inst.borrow().class.methods.borrow().get(name).cloned(),
+            _ => None,
+        }
+    }
+
+    /// `a == b`, calling `__eq__` if either operand's class defines it.
+    pub(crate) fn eq_values(&mut self, a: PyObject, b: PyObject) -> Result<bool, String> {
+        if let Some(eq) = self.instance_method(&a, "__eq__") {
+            Ok(!is_falsey(&self.call_value(eq, vec![a, b])?))
+        } else if let Some(eq) = self.instance_method(&b, "__eq__") {
+            Ok(!is_falsey(&self.call_value(eq, vec![b, a])?))
+        } else {
+            Ok(a == b)
+        }
+    }
+
+    /// Calls `__next__` on an instance, returning None once it raises
+    /// `StopIteration`.
+    fn next_from_instance(
+        &mut self,
+        inst: &Rc<RefCell<PyInstance>>,
+    ) -> Result<Option<PyObject>, String> {
+        let next = inst
+            .borrow()
+            .class
+            .methods
+            .borrow()
+            .get("__next__")
+            .cloned();
+        let Some(next) = next else {
+            return Err(format!(
+                "TypeError: '{}' object is not an iterator",
+                inst.borrow().class.name
+            ));
+        };
+
+        match self.call_value(next, vec![PyObject::Instance(inst.clone())]) {
+            Ok(item) => Ok(Some(item)),
+            Err(e) if error_message(&e).split(':').next() == Some("StopIteration") => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
 
-                    ip += 1;
+    fn push_frame(
+        &mut self,
+        st: &mut ExecState,
+        code: &CodeObject,
+        env: Env,
+        instance: Option<PyObject>,
+    ) {
+        st.frames.push(Frame {
+            ret_ip: st.ip + 1,
+            code: std::mem::replace(&mut st.cur, code.clone()),
+            env: std::mem::replace(&mut self.env, env),
+            stack_len: self.stack.len(),
+            loop_stack: std::mem::take(&mut self.loop_stack),
+            iter_stack: std::mem::take(&mut self.iter_stack),
+            handler_stack: std::mem::take(&mut self.handler_stack),
+            instance,
+        });
+        st.ip = 0;
+    }
+
+    fn step(&mut self, st: &mut ExecState) -> Result<Option<PyObject>, String> {
+        if st.ip >= st.cur.instructions.len() {
+            return Ok(Some(PyObject::None));
+        }
+
+        match st.cur.instructions[st.ip] {
+            Op::LoadConst(idx) => {
+                self.stack.push(st.cur.constant(idx)?.clone());
+                st.ip += 1;
+            }
+            Op::LoadName(idx) => {
+                let name = st.cur.name(idx)?;
+                let value = self
+                    .env
+                    .locals
+                    .borrow()
+                    .get(name)
+                    .cloned()
+                    .or_else(|| self.env.globals.borrow().get(name).cloned())
+                    .or_else(|| self.env.builtins.get(name).cloned());
+
+                if let Some(v) = value {
+                    self.stack.push(v);
+                } else {
+                    return Err(format!("NameError: name '{}' is not defined", name));
                 }
-                Op::UnaryPos => {
-                    let operand = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
 
-                    match operand {
-                        PyObject::Int(x) => self.stack.push(PyObject::Int(x)),
-                        PyObject::Float(x) => self.stack.push(PyObject::Float(x)),
-                        _ => {
-                            return Err(
-                                "TypeError: unsupported operand type for unary +".to_string()
-                            );
+                st.ip += 1;
+            }
+            Op::StoreName(idx) => {
+                let name = st.cur.name(idx)?.clone();
+                let v = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.env.locals.borrow_mut().insert(name, v);
+                st.ip += 1;
+            }
+            Op::SaveName(idx) => {
+                let saved = self.env.locals.borrow().get(st.cur.name(idx)?).cloned();
+                self.stack
+                    .push(PyObject::Tuple(saved.into_iter().collect()));
+                st.ip += 1;
+            }
+            Op::RestoreName(idx) => {
+                let name = st.cur.name(idx)?;
+                match self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?
+                {
+                    PyObject::Tuple(saved) => match saved.into_iter().next() {
+                        Some(v) => {
+                            self.env.locals.borrow_mut().insert(name.clone(), v);
                         }
+                        None => {
+                            self.env.locals.borrow_mut().remove(name);
+                        }
+                    },
+                    _ => {
+                        return Err("RuntimeError: RestoreName expects a saved binding".to_string());
                     }
-
-                    ip += 1;
-                }
-                Op::Add => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(arith_add(a, b)?);
-                    ip += 1;
-                }
-                Op::Sub => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(arith_sub(a, b)?);
-                    ip += 1;
-                }
-                Op::Mul => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(arith_mul(a, b)?);
-                    ip += 1;
-                }
-                Op::Div => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(arith_div(a, b)?);
-                    ip += 1;
-                }
-                Op::Eq => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(PyObject::Bool(a == b));
-                    ip += 1;
-                }
-                Op::Ne => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(PyObject::Bool(a != b));
-                    ip += 1;
-                }
-                Op::Lt => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(cmp_lt(a, b)?);
-                    ip += 1;
                 }
-                Op::Le => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(cmp_le(a, b)?);
-                    ip += 1;
+                st.ip += 1;
+            }
+            Op::LoadGlobal(idx) => {
+                let name = st.cur.name(idx)?;
+                if let Some(v) = self
+                    .env
+                    .globals
+                    .borrow()
+                    .get(name)
+                    .cloned()
+                    .or_else(|| self.env.builtins.get(name).cloned())
+                {
+                    self.stack.push(v);
+                    st.ip += 1;
+                } else {
+                    return Err(format!("NameError: name '{}' is not defined", name));
                 }
-                Op::Gt => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(cmp_gt(a, b)?);
-                    ip += 1;
+            }
+            Op::StoreGlobal(idx) => {
+                let name = st.cur.name(idx)?.clone();
+                let v = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.env.globals.borrow_mut().insert(name, v);
+                st.ip += 1;
+            }
+            Op::Pop => {
+                self.stack.pop();
+                st.ip += 1;
+            }
+            Op::Dup => {
+                let top = self
+                    .stack
+                    .last()
+                    .cloned()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(top);
+                st.ip += 1;
+            }
+            Op::Swap => {
+                let len = self.stack.len();
+                if len < 2 {
+                    return Err("stack underflow".to_string());
                 }
-                Op::Ge => {
-                    let b = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let a = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    self.stack.push(cmp_ge(a, b)?);
-                    ip += 1;
+                self.stack.swap(len - 1, len - 2);
+                st.ip += 1;
+            }
+            Op::DupTop2 => {
+                let len = self.stack.len();
+                if len < 2 {
+                    return Err("stack underflow".to_string());
                 }
-                Op::Jump(target) => {
-                    ip = target;
+                self.stack.extend_from_within(len - 2..);
+                st.ip += 1;
+            }
+            Op::RotThree => {
+                let len = self.stack.len();
+                if len < 3 {
+                    return Err("stack underflow".to_string());
                 }
-                Op::JumpIfTrue(target) => {
-                    let v = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    if !is_falsey(&v) {
-                        ip = target;
-                    } else {
-                        ip += 1;
+                self.stack[len - 3..].rotate_left(1);
+                st.ip += 1;
+            }
+            Op::Return => {
+                let ret = self.stack.pop().unwrap_or(PyObject::None);
+                if let Some(frame) = st.frames.pop() {
+                    self.env = frame.env;
+                    self.loop_stack = frame.loop_stack;
+                    self.iter_stack = frame.iter_stack;
+                    self.handler_stack = frame.handler_stack;
+                    self.stack.truncate(frame.stack_len);
+                    st.cur = frame.code;
+                    st.ip = frame.ret_ip;
+
+                    match frame.instance {
+                        Some(_) if ret != PyObject::None => {
+                            return Err(format!(
+                                "TypeError: __init__() should return None, not '{}'",
+                                ret.type_name()
+                            ));
+                        }
+                        Some(instance) => self.stack.push(instance),
+                        None => self.stack.push(ret),
                     }
+                } else {
+                    return Ok(Some(ret));
                 }
-                Op::JumpIfFalse(target) => {
-                    let v = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    if is_falsey(&v) {
-                        ip = target;
-                    } else {
-                        ip += 1;
+            }
+            Op::Call(argc) => {
+                let args = self.pop_args(argc)?;
+                let callee = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.call_object(st, callee, args, None)?;
+            }
+            Op::CallKw(argc) => {
+                let kwargs = match self.stack.pop() {
+                    Some(PyObject::Dict(d)) => d
+                        .borrow()
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.clone()))
+                        .collect(),
+                    _ => return Err("RuntimeError: CallKw expects a dict".to_string()),
+                };
+                let args = self.pop_args(argc)?;
+                let callee = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.call_object(st, callee, args, Some(kwargs))?;
+            }
+            Op::Def {
+                name,
+                arity,
+                defaults,
+                code_idx,
+            } => {
+                let fname = st.cur.name(name)?.clone();
+                let fcode = st.cur.nested_code(code_idx)?.clone();
+                let defaults = self.pop_args(defaults)?;
+                let f = PyFunction {
+                    name: fname.clone(),
+                    arity,
+                    code: fcode,
+                    globals: self.env.clone(),
+                    defaults,
+                };
+
+                self.env
+                    .locals
+                    .borrow_mut()
+                    .insert(fname, PyObject::Function(Rc::new(f)));
+                st.ip += 1;
+            }
+            Op::UnaryNeg => {
+                let operand = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+
+                match operand {
+                    PyObject::Int(x) => self.stack.push(int_op(x.checked_neg(), '-')?),
+                    PyObject::Float(x) => self.stack.push(PyObject::Float(-x)),
+                    PyObject::Complex(re, im) => self.stack.push(PyObject::Complex(-re, -im)),
+                    PyObject::Decimal(d) => self.stack.push(PyObject::Decimal(d.neg())),
+                    _ => {
+                        return Err("TypeError: unsupported operand type for unary -".to_string());
                     }
                 }
-                Op::SetupLoop(exit_addr) => {
-                    self.loop_stack.push((ip + 1, exit_addr));
-                    ip += 1;
-                }
-                Op::PopBlock => {
-                    self.loop_stack.pop();
-                    ip += 1;
-                }
-                Op::Break => {
-                    if let Some((_, exit_addr)) = self.loop_stack.pop() {
-                        ip = exit_addr;
-                    } else {
-                        return Err("SyntaxError: 'break' outside loop".to_string());
+
+                st.ip += 1;
+            }
+            Op::UnaryPos => {
+                let operand = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+
+                match operand {
+                    PyObject::Int(x) => self.stack.push(PyObject::Int(x)),
+                    PyObject::Float(x) => self.stack.push(PyObject::Float(x)),
+                    PyObject::Complex(re, im) => self.stack.push(PyObject::Complex(re, im)),
+                    _ => {
+                        return Err("TypeError: unsupported operand type for unary +".to_string());
                     }
                 }
-                Op::Continue => {
-                    if let Some((continue_addr, _)) = self.loop_stack.last() {
-                        ip = *continue_addr;
-                    } else {
-                        return Err("SyntaxError: 'continue' not properly in loop".to_string());
-                    }
+
+                st.ip += 1;
+            }
+            Op::Add => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(arith_add(a, b)?);
+                st.ip += 1;
+            }
+            Op::Sub => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(arith_sub(a, b)?);
+                st.ip += 1;
+            }
+            Op::Mul => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(arith_mul(a, b)?);
+                st.ip += 1;
+            }
+            Op::Div => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(arith_div(a, b)?);
+                st.ip += 1;
+            }
+            Op::FloorDiv => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(arith_floordiv(a, b)?);
+                st.ip += 1;
+            }
+            Op::Eq => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let eq = self.eq_values(a, b)?;
+                self.stack.push(PyObject::Bool(eq));
+                st.ip += 1;
+            }
+            Op::Ne => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let ne = match self.instance_method(&a, "__ne__") {
+                    Some(ne) => !is_falsey(&self.call_value(ne, vec![a, b])?),
+                    None => !self.eq_values(a, b)?,
+                };
+                self.stack.push(PyObject::Bool(ne));
+                st.ip += 1;
+            }
+            Op::Lt => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(cmp_lt(a, b)?);
+                st.ip += 1;
+            }
+            Op::Le => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(cmp_le(a, b)?);
+                st.ip += 1;
+            }
+            Op::Gt => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(cmp_gt(a, b)?);
+                st.ip += 1;
+            }
+            Op::Ge => {
+                let b = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let a = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(cmp_ge(a, b)?);
+                st.ip += 1;
+            }
+            Op::Contains => {
+                let container = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let item = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack
+                    .push(PyObject::Bool(contains(&container, &item)?));
+                st.ip += 1;
+            }
+            Op::UnaryNot => {
+                let operand = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(PyObject::Bool(is_falsey(&operand)));
+                st.ip += 1;
+            }
+            Op::Jump(target) => {
+                st.ip = target;
+            }
+            Op::JumpIfTrue(target) => {
+                let v = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                if !is_falsey(&v) {
+                    st.ip = target;
+                } else {
+                    st.ip += 1;
                 }
-                Op::GetIter => {
-                    let obj = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    match obj {
-                        PyObject::List(l) => {
-                            self.iter_stack.push((0, PyObject::List(l.clone())));
-                            ip += 1;
-                        }
-                        PyObject::Tuple(t) => {
-                            self.iter_stack.push((0, PyObject::Tuple(t.clone())));
-                            ip += 1;
-                        }
-                        _ => return Err("TypeError: object is not iterable".to_string()),
-                    }
+            }
+            Op::JumpIfFalse(target) => {
+                let v = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                if is_falsey(&v) {
+                    st.ip = target;
+                } else {
+                    st.ip += 1;
                 }
-                Op::ForIter(exit_addr) => {
-                    if let Some((index, iter_obj)) = self.iter_stack.last_mut() {
-                        let has_next = match iter_obj {
-                            PyObject::List(l) => {
-                                let list = l.borrow();
-                                if *index < list.len() {
-                                    self.stack.push(list[*index].clone());
-                                    *index += 1;
-                                    true
-                                } else {
-                                    false
-                                }
-                            }
-                            PyObject::Tuple(t) => {
-                                if *index < t.len() {
-                                    self.stack.push(t[*index].clone());
-                                    *index += 1;
-                                    true
-                                } else {
-                                    false
-                                }
-                            }
-                            _ => false,
-                        };
-
-                        if has_next {
-                            ip += 1;
-                        } else {
-                            self.iter_stack.pop();
-                            ip = exit_addr;
-                        }
-                    } else {
-                        return Err("RuntimeError: no iterator on stack".to_string());
-                    }
+            }
+            Op::JumpIfFalseOrPop(target) => {
+                let v = self
+                    .stack
+                    .last()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                if is_falsey(v) {
+                    st.ip = target;
+                } else {
+                    self.stack.pop();
+                    st.ip += 1;
                 }
-                Op::BuildList(count) => {
-                    let mut items = Vec::with_capacity(count);
-
-                    for _ in 0..count {
-                        items.push(
-                            self.stack
-                                .pop()
-                                .ok_or_else(|| "stack underflow".to_string())?,
-                        );
-                    }
-
-                    items.reverse();
-                    self.stack
-                        .push(PyObject::List(Rc::new(RefCell::new(items))));
-                    ip += 1;
+            }
+            Op::JumpIfTrueOrPop(target) => {
+                let v = self
+                    .stack
+                    .last()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                if !is_falsey(v) {
+                    st.ip = target;
+                } else {
+                    self.stack.pop();
+                    st.ip += 1;
+                }
+            }
+            Op::SetupLoop { cont, exit } => {
+                self.loop_stack.push((cont, exit, self.iter_stack.len()));
+                st.ip += 1;
+            }
+            Op::PopBlock => {
+                // a `break` out of a `for` leaves its iterator behind
+                if let Some((_, _, iter_depth)) = self.loop_stack.pop() {
+                    self.iter_stack.truncate(iter_depth);
+                }
+                st.ip += 1;
+            }
+            Op::UnpackSequence(count) => {
+                let seq = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let items = iter_items(&seq)?;
+
+                if items.len() < count {
+                    return Err(format!(
+                        "ValueError: not enough values to unpack (expected {}, got {})",
+                        count,
+                        items.len()
+                    ));
+                } else if items.len() > count {
+                    return Err(format!(
+                        "ValueError: too many values to unpack (expected {})",
+                        count
+                    ));
                 }
-                Op::BuildDict(count) => {
-                    let mut pairs = Vec::new();
 
-                    for _ in 0..count {
-                        let value = self
-                            .stack
-                            .pop()
-                            .ok_or_else(|| "stack underflow".to_string())?;
-                        let key = self
-                            .stack
-                            .pop()
-                            .ok_or_else(|| "stack underflow".to_string())?;
-                        if let PyObject::Str(k) = key {
-                            pairs.push((k, value));
-                        } else {
-                            return Err("TypeError: dict keys must be strings".to_string());
-                        }
-                    }
+                self.stack.extend(items.into_iter().rev());
+                st.ip += 1;
+            }
+            Op::UnpackEx { before, after } => {
+                let seq = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let mut items = iter_items(&seq)?;
+
+                if items.len() < before + after {
+                    return Err(format!(
+                        "ValueError: not enough values to unpack (expected at least {}, got {})",
+                        before + after,
+                        items.len()
+                    ));
+                }
 
-                    let mut dict = IndexMap::new();
+                let tail = items.split_off(items.len() - after);
+                let rest = items.split_off(before);
+                let rest = PyObject::List(Rc::new(RefCell::new(rest)));
+                self.track(&rest);
 
-                    for (k, v) in pairs.into_iter().rev() {
-                        dict.insert(k, v);
+                self.stack.extend(tail.into_iter().rev());
+                self.stack.push(rest);
+                self.stack.extend(items.into_iter().rev());
+                st.ip += 1;
+            }
+            Op::SetupExcept(handler) => {
+                self.handler_stack.push((
+                    handler,
+                    self.stack.len(),
+                    self.loop_stack.len(),
+                    self.iter_stack.len(),
+                    self.handled.len(),
+                ));
+                st.ip += 1;
+            }
+            Op::PopExcept => {
+                self.handler_stack.pop();
+                st.ip += 1;
+            }
+            Op::EndExcept => {
+                self.handling = self.handled.pop().flatten();
+                st.ip += 1;
+            }
+            Op::ExceptMatch { name, target } => {
+                let matched = match self.stack.last() {
+                    Some(PyObject::Exception(e)) => {
+                        crate::core::exceptions::matches(&e.kind, st.cur.name(name)?)
                     }
+                    _ => false,
+                };
 
-                    self.stack.push(PyObject::Dict(Rc::new(RefCell::new(dict))));
-                    ip += 1;
+                if matched {
+                    st.ip = target;
+                } else {
+                    st.ip += 1;
                 }
-                Op::LoadIndex => {
-                    let index = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let obj = self
+            }
+            Op::Raise(argc) => {
+                let exc = if argc == 0 {
+                    self.handling
+                        .clone()
+                        .ok_or_else(|| "RuntimeError: No active exception to reraise".to_string())?
+                } else {
+                    match self
                         .stack
                         .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    match (obj, index) {
-                        (PyObject::List(l), PyObject::Int(i)) => {
-                            let list = l.borrow();
-                            let idx = if i < 0 { list.len() as i64 + i } else { i } as usize;
-                            if idx < list.len() {
-                                self.stack.push(list[idx].clone());
-                            } else {
-                                return Err("IndexError: list index out of range".to_string());
-                            }
-                        }
-                        (PyObject::Dict(d), PyObject::Str(k)) => {
-                            if let Some(v) = d.borrow().get(&k) {
-                                self.stack.push(v.clone());
-                            } else {
-                                return Err(format!("KeyError: '{}'", k));
-                            }
-                        }
-                        (PyObject::Tuple(t), PyObject::Int(i)) => {
-                            let idx = if i < 0 { t.len() as i64 + i } else { i } as usize;
-                            if idx < t.len() {
-                                self.stack.push(t[idx].clone());
-                            } else {
-                                return Err("IndexError: tuple index out of range".to_string());
-                            }
+                        .ok_or_else(|| "stack underflow".to_string())?
+                    {
+                        // `raise ValueError` instantiates the class
+                        PyObject::NativeFunction(nf)
+                            if crate::core::exceptions::is_exception_kind(&nf.name) =>
+                        {
+                            (nf.func)(&[])?
                         }
-                        _ => return Err("TypeError: invalid indexing operation".to_string()),
+                        other => other,
                     }
+                };
 
-                    ip += 1;
-                }
-                Op::StoreIndex => {
-                    let value = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let index = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let obj = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-
-                    match (&obj, index) {
-                        (PyObject::List(l), PyObject::Int(i)) => {
-                            let mut list = l.borrow_mut();
-                            let idx = if i < 0 { list.len() as i64 + i } else { i } as usize;
-                            if idx < list.len() {
-                                list[idx] = value;
-                            } else {
-                                return Err(
-                                    "IndexError: list assignment index out of range".to_string()
-                                );
-                            }
-                        }
-                        (PyObject::Dict(d), PyObject::Str(k)) => {
-                            d.borrow_mut().insert(k, value);
-                        }
-                        _ => return Err("TypeError: invalid indexing assignment".to_string()),
+                match exc {
+                    PyObject::Exception(e) => {
+                        return Err(crate::core::exceptions::to_error(&e));
                     }
-
-                    ip += 1;
-                }
-                Op::BuildTuple(count) => {
-                    let mut items = Vec::with_capacity(count);
-
-                    for _ in 0..count {
-                        items.push(
-                            self.stack
-                                .pop()
-                                .ok_or_else(|| "stack underflow".to_string())?,
+                    _ => {
+                        return Err(
+                            "TypeError: exceptions must derive from BaseException".to_string()
                         );
                     }
+                }
+            }
+            Op::Reraise => {
+                match self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?
+                {
+                    PyObject::Exception(e) => {
+                        return Err(crate::core::exceptions::to_error(&e));
+                    }
+                    _ => return Err("RuntimeError: Reraise expects an exception".to_string()),
+                }
+            }
+            Op::Break => {
+                // handlers set up inside the loop body are abandoned
+                while self
+                    .handler_stack
+                    .last()
+                    .is_some_and(|h| h.2 >= self.loop_stack.len())
+                {
+                    self.handler_stack.pop();
+                }
 
-                    items.reverse();
-                    self.stack.push(PyObject::Tuple(items));
-                    ip += 1;
+                if let Some(&(_, exit_addr, _)) = self.loop_stack.last() {
+                    st.ip = exit_addr;
+                } else {
+                    return Err("SyntaxError: 'break' outside loop".to_string());
+                }
+            }
+            Op::Continue => {
+                while self
+                    .handler_stack
+                    .last()
+                    .is_some_and(|h| h.2 >= self.loop_stack.len())
+                {
+                    self.handler_stack.pop();
                 }
-                Op::BuildSet(count) => {
-                    let mut set = std::collections::HashSet::new();
 
-                    for _ in 0..count {
-                        let item = self
-                            .stack
-                            .pop()
-                            .ok_or_else(|| "stack underflow".to_string())?;
-                        set.insert(item);
+                if let Some((continue_addr, _, _)) = self.loop_stack.last() {
+                    st.ip = *continue_addr;
+                } else {
+                    return Err("SyntaxError: 'continue' not properly in loop".to_string());
+                }
+            }
+            Op::GetIter => {
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                match obj {
+                    PyObject::List(l) => {
+                        self.iter_stack.push((0, PyObject::List(l.clone())));
+                        st.ip += 1;
+                    }
+                    PyObject::Tuple(t) => {
+                        self.iter_stack.push((0, PyObject::Tuple(t.clone())));
+                        st.ip += 1;
+                    }
+                    // a dict iterates over its keys, in insertion order
+                    seq @ (PyObject::Range(..)
+                    | PyObject::Dict(_)
+                    | PyObject::Set(_)
+                    | PyObject::Str(_)
+                    | PyObject::Bytes(_)
+                    | PyObject::Deque(_)) => {
+                        self.iter_stack.push((0, seq));
+                        st.ip += 1;
+                    }
+                    it @ PyObject::Iterator(_) => {
+                        self.iter_stack.push((0, it));
+                        st.ip += 1;
+                    }
+                    PyObject::Instance(inst) => {
+                        let iter = inst
+                            .borrow()
+                            .class
+                            .methods
+                            .borrow()
+                            .get("__iter__")
+                            .cloned();
+                        let Some(iter) = iter else {
+                            return Err(format!(
+                                "TypeError: '{}' object is not iterable",
+                                inst.borrow().class.name
+                            ));
+                        };
+                        let it = self.call_value(iter, vec![PyObject::Instance(inst)])?;
+                        self.iter_stack.push((0, it));
+                        st.ip += 1;
+                    }
+                    other => {
+                        return Err(format!(
+                            "TypeError: '{}' object is not iterable",
+                            other.type_name()
+                        ));
+                    }
+                }
+            }
+            Op::ForIter(exit_addr) => {
+                let item = match self.iter_stack.last() {
+                    Some((_, PyObject::Instance(inst))) => {
+                        let inst = inst.clone();
+                        self.next_from_instance(&inst)?
                     }
+                    Some((_, PyObject::Iterator(it))) => it.borrow_mut().next(),
+                    Some((index, seq)) => seq_item(seq, *index),
+                    None => return Err("RuntimeError: no iterator on stack".to_string()),
+                };
+
+                if let Some(item) = item {
+                    if let Some((index, _)) = self.iter_stack.last_mut() {
+                        *index += 1;
+                    }
+                    self.stack.push(item);
+                    st.ip += 1;
+                } else {
+                    self.iter_stack.pop();
+                    st.ip = exit_addr;
+                }
+            }
+            Op::BuildList(count) => {
+                // `pop_args` checks the depth first, so a bogus count from
+                // malformed bytecode can't reserve a huge buffer
+                let items = self.pop_args(count)?;
+                let list = PyObject::List(Rc::new(RefCell::new(items)));
+                self.track(&list);
+                self.stack.push(list);
+                st.ip += 1;
+            }
+            Op::BuildDict(count) => {
+                let depth = count
+                    .checked_mul(2)
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let items = self.pop_args(depth)?;
+                let mut dict = IndexMap::new();
+
+                for pair in items.chunks(2) {
+                    check_hashable(&pair[0])?;
+                    dict.insert(pair[0].clone(), pair[1].clone());
+                }
 
-                    self.stack.push(PyObject::Set(Rc::new(RefCell::new(set))));
-                    ip += 1;
+                let dict = PyObject::Dict(Rc::new(RefCell::new(dict)));
+                self.track(&dict);
+                self.stack.push(dict);
+                st.ip += 1;
+            }
+            Op::LoadIndex => {
+                let index = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.stack.push(load_index(obj, index)?);
+                st.ip += 1;
+            }
+            Op::StoreIndex => {
+                let value = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let index = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+
+                match (&obj, index) {
+                    (PyObject::List(l), PyObject::Int(i)) => {
+                        let mut list = l.borrow_mut();
+                        let idx = if i < 0 { list.len() as i64 + i } else { i } as usize;
+                        if idx < list.len() {
+                            list[idx] = value;
+                        } else {
+                            return Err(
+                                "IndexError: list assignment index out of range".to_string()
+                            );
+                        }
+                    }
+                    (PyObject::Dict(d), k) => {
+                        check_hashable(&k)?;
+                        d.borrow_mut().insert(k, value);
+                    }
+                    (PyObject::List(_), i) => {
+                        return Err(format!(
+                            "TypeError: list indices must be integers or slices, not {}",
+                            i.type_name()
+                        ));
+                    }
+                    _ => {
+                        return Err(format!(
+                            "TypeError: '{}' object does not support item assignment",
+                            obj.type_name()
+                        ));
+                    }
                 }
-                Op::ClassDef { name, code_idx } => {
-                    let class_name = cur.names[name].clone();
-                    let class_code = cur.nested[code_idx].clone();
-
-                    #[allow(unused_mut)]
-                    let mut class_env = self.env.clone();
-                    let mut class_vm = Vm {
-                        stack: Vec::new(),
-                        env: class_env,
-                        loop_stack: Vec::new(),
-                        iter_stack: Vec::new(),
-                        ..Default::default()
-                    };
 
-                    class_vm.run(&class_code)?;
+                st.ip += 1;
+            }
+            Op::BuildTuple(count) => {
+                let items = self.pop_args(count)?;
+                self.stack.push(PyObject::Tuple(items));
+                st.ip += 1;
+            }
+            Op::BuildSet(count) => {
+                let items = self.pop_args(count)?;
+                items.iter().try_for_each(check_hashable)?;
 
-                    let mut methods = HashMap::new();
+                // the first occurrence of a duplicate keeps its position
+                let set: IndexSet<PyObject> = items.into_iter().collect();
 
-                    for (k, v) in class_vm.env.locals {
-                        methods.insert(k, v);
+                let set = PyObject::Set(Rc::new(RefCell::new(set)));
+                self.track(&set);
+                self.stack.push(set);
+                st.ip += 1;
+            }
+            Op::ListAppend => {
+                let item = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                match self.stack.last() {
+                    Some(PyObject::List(l)) => l.borrow_mut().push(item),
+                    _ => return Err("RuntimeError: ListAppend expects a list".to_string()),
+                }
+                st.ip += 1;
+            }
+            Op::SetAdd => {
+                let item = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                check_hashable(&item)?;
+                match self.stack.last() {
+                    Some(PyObject::Set(s)) => {
+                        s.borrow_mut().insert(item);
                     }
+                    _ => return Err("RuntimeError: SetAdd expects a set".to_string()),
+                }
+                st.ip += 1;
+            }
+            Op::MapAdd => {
+                let value = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let key = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                check_hashable(&key)?;
+                match self.stack.last() {
+                    Some(PyObject::Dict(d)) => {
+                        d.borrow_mut().insert(key, value);
+                    }
+                    _ => return Err("RuntimeError: MapAdd expects a dict".to_string()),
+                }
+                st.ip += 1;
+            }
+            Op::ClassDef { name, code_idx } => {
+                let class_name = st.cur.name(name)?.clone();
+                let class_code = st.cur.nested_code(code_idx)?.clone();
 
-                    let class = PyClass {
-                        name: class_name.clone(),
-                        methods,
-                        bases: Vec::new(),
-                    };
+                let class_env = self.env.child();
+                let mut class_vm = Vm {
+                    stack: Vec::new(),
+                    env: class_env,
+                    loop_stack: Vec::new(),
+                    iter_stack: Vec::new(),
+                    ..Default::default()
+                };
 
-                    let constructor = PyNativeFunction {
-                        name: class_name.clone(),
-                        arity: usize::MAX,
-                        func: {
-                            let class_rc = Rc::new(class.clone());
-                            Rc::new(move |args| {
-                                let instance = PyInstance {
-                                    class: class_rc.clone(),
-                                    attrs: HashMap::new(),
-                                };
-                                let inst_obj = PyObject::Instance(Rc::new(RefCell::new(instance)));
-
-                                if let Some(init_method) = class_rc.methods.get("__init__") {
-                                    match init_method {
-                                        PyObject::Function(f) => {
-                                            let mut init_args = vec![inst_obj.clone()];
-                                            init_args.extend_from_slice(args);
-
-                                            let mut init_vm = Vm::default();
-                                            let mut new_env = Env::default();
-
-                                            for (i, name) in f
-                                                .code
-                                                .names
-                                                .iter()
-                                                .take(init_args.len())
-                                                .enumerate()
-                                            {
-                                                new_env
-                                                    .locals
-                                                    .insert(name.clone(), init_args[i].clone());
-                                            }
-
-                                            new_env.globals = f.globals.clone().globals;
-                                            init_vm.env = new_env;
-                                            init_vm.run(&f.code)?;
-                                        }
-                                        _ => {}
-                                    }
-                                }
+                class_vm.run(&class_code)?;
 
-                                Ok(inst_obj)
-                            })
-                        },
-                    };
+                let mut methods = HashMap::new();
 
-                    self.env
-                        .locals
-                        .insert(class_name, PyObject::NativeFunction(Rc::new(constructor)));
-                    ip += 1;
+                for (k, v) in class_vm.env.locals.borrow().iter() {
+                    methods.insert(k.clone(), v.clone());
                 }
-                Op::LoadAttr(idx) => {
-                    let attr_name = &cur.names[idx];
-                    let obj = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-
-                    match obj {
-                        PyObject::Instance(inst) => {
-                            let instance = inst.borrow();
-                            if let Some(value) = instance.attrs.get(attr_name) {
-                                self.stack.push(value.clone());
-                            } else if let Some(method) = instance.class.methods.get(attr_name) {
-                                match method {
-                                    PyObject::Function(f) => {
-                                        let bound_method = PyNativeFunction {
-                                            name: format!("{}.{}", instance.class.name, attr_name),
-                                            arity: f.arity - 1,
-                                            func: {
-                                                let f_clone = f.clone();
-                                                let inst_clone = PyObject::Instance(inst.clone());
-                                                Rc::new(move |args| {
-                                                    let mut full_args = vec![inst_clone.clone()];
-                                                    full_args.extend_from_slice(args);
-
-                                                    let mut method_vm = Vm::default();
-                                                    let mut new_env = Env::default();
-
-                                                    for (i, name) in f_clone
-                                                        .code
-                                                        .names
-                                                        .iter()
-                                                        .take(full_args.len())
-                                                        .enumerate()
-                                                    {
-                                                        new_env.locals.insert(
-                                                            name.clone(),
-                                                            full_args[i].clone(),
-                                                        );
-                                                    }
-
-                                                    new_env.globals =
-                                                        f_clone.globals.clone().globals;
-                                                    method_vm.env = new_env;
-                                                    method_vm.run(&f_clone.code)
-                                                })
-                                            },
-                                        };
-                                        self.stack
-                                            .push(PyObject::NativeFunction(Rc::new(bound_method)));
-                                    }
-                                    _ => self.stack.push(method.clone()),
+
+                let class = PyClass {
+                    name: class_name.clone(),
+                    methods: RefCell::new(methods),
+                    bases: Vec::new(),
+                };
+
+                self.env
+                    .locals
+                    .borrow_mut()
+                    .insert(class_name, PyObject::Class(Rc::new(class)));
+                st.ip += 1;
+            }
+            Op::LoadAttr(idx) => {
+                let attr_name = st.cur.name(idx)?;
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+
+                match obj {
+                    PyObject::Instance(inst) => {
+                        let instance = inst.borrow();
+                        if let Some(value) = instance.attrs.get(attr_name) {
+                            self.stack.push(value.clone());
+                        } else if let Some(method) =
+                            instance.class.methods.borrow().get(attr_name).cloned()
+                        {
+                            match &method {
+                                PyObject::Function(f) => {
+                                    self.stack.push(PyObject::Method(Rc::new(PyMethod {
+                                        receiver: PyObject::Instance(inst.clone()),
+                                        func: f.clone(),
+                                    })));
                                 }
-                            } else {
-                                return Err(format!(
-                                    "AttributeError: '{}' object has no attribute '{}'",
-                                    instance.class.name, attr_name
-                                ));
+                                // native methods take the instance as their first argument
+                                PyObject::NativeFunction(nf) => {
+                                    let receiver = PyObject::Instance(inst.clone());
+                                    let nf = nf.clone();
+                                    let arity = match nf.arity {
+                                        usize::MAX => usize::MAX,
+                                        n => n.saturating_sub(1),
+                                    };
+                                    self.stack.push(PyObject::native_fn(
+                                        &nf.name,
+                                        arity,
+                                        move |args| {
+                                            let mut full_args = Vec::with_capacity(args.len() + 1);
+                                            full_args.push(receiver.clone());
+                                            full_args.extend_from_slice(args);
+                                            (nf.func)(&full_args)
+                                        },
+                                    ));
+                                }
+                                _ => self.stack.push(method.clone()),
                             }
+                        } else {
+                            return Err(format!(
+                                "AttributeError: '{}' object has no attribute '{}'",
+                                instance.class.name, attr_name
+                            ));
                         }
-                        PyObject::Module(m) => {
-                            let module = m.borrow();
-                            if let Some(value) = module.dict.get(attr_name) {
-                                self.stack.push(value.clone());
-                            } else {
-                                return Err(format!(
-                                    "AttributeError: module '{}' has no attribute '{}'",
-                                    module.name, attr_name
-                                ));
-                            }
+                    }
+                    PyObject::Module(m) => {
+                        let module = m.borrow();
+                        if let Some(value) = module.dict.borrow().get(attr_name) {
+                            self.stack.push(value.clone());
+                        } else {
+                            return Err(format!(
+                                "AttributeError: module '{}' has no attribute '{}'",
+                                module.name, attr_name
+                            ));
                         }
-                        PyObject::NativeModule(m) => {
-                            if let Some(value) = m.dict.get(attr_name) {
-                                self.stack.push(value.clone());
-                            } else {
-                                return Err(format!(
-                                    "AttributeError: module '{}' has no attribute '{}'",
-                                    m.name, attr_name
-                                ));
-                            }
+                    }
+                    PyObject::NativeModule(m) => {
+                        if let Some(value) = m.dict.get(attr_name) {
+                            self.stack.push(value.clone());
+                        } else {
+                            return Err(format!(
+                                "AttributeError: module '{}' has no attribute '{}'",
+                                m.name, attr_name
+                            ));
                         }
-                        PyObject::NativeClass(c) => {
-                            if let Some(method) = c.methods.get(attr_name) {
-                                self.stack.push(method.clone());
-                            } else {
-                                return Err(format!(
-                                    "AttributeError: type '{}' has no attribute '{}'",
-                                    c.name, attr_name
-                                ));
-                            }
+                    }
+                    PyObject::Complex(re, im) => match attr_name.as_str() {
+                        "real" => self.stack.push(PyObject::Float(re)),
+                        "imag" => self.stack.push(PyObject::Float(im)),
+                        _ => {
+                            return Err(format!(
+                                "AttributeError: 'complex' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Dict(d) => match crate::core::dicts::method(&d, attr_name) {
+                        Some(method) => self.stack.push(method),
+                        None => {
+                            return Err(format!(
+                                "AttributeError: 'dict' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Deque(d) => match crate::core::collections::method(&d, attr_name) {
+                        Some(method) => self.stack.push(method),
+                        None => {
+                            return Err(format!(
+                                "AttributeError: 'deque' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Bytes(b) => match crate::core::bytes::method(&b, attr_name) {
+                        Some(method) => self.stack.push(method),
+                        None => {
+                            return Err(format!(
+                                "AttributeError: 'bytes' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::List(l) => match crate::core::lists::method(&l, attr_name) {
+                        Some(method) => self.stack.push(method),
+                        None => {
+                            return Err(format!(
+                                "AttributeError: 'list' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Tuple(t) => match crate::core::tuples::method(&t, attr_name) {
+                        Some(method) => self.stack.push(method),
+                        None => {
+                            return Err(format!(
+                                "AttributeError: 'tuple' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Str(s) => match crate::core::strings::method(&s, attr_name) {
+                        Some(method) => self.stack.push(method),
+                        None => {
+                            return Err(format!(
+                                "AttributeError: 'str' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Type(t) => match attr_name.as_str() {
+                        "__name__" => self.stack.push(PyObject::Str(t.name)),
+                        _ => {
+                            return Err(format!(
+                                "AttributeError: type object '{}' has no attribute '{}'",
+                                t.name, attr_name
+                            ));
+                        }
+                    },
+                    PyObject::Class(c) => {
+                        if let Some(value) = c.methods.borrow().get(attr_name) {
+                            self.stack.push(value.clone());
+                        } else if attr_name == "__name__" {
+                            self.stack.push(PyObject::Str(c.name.clone()));
+                        } else {
+                            return Err(format!(
+                                "AttributeError: type object '{}' has no attribute '{}'",
+                                c.name, attr_name
+                            ));
                         }
-                        _ => return Err("AttributeError: object has no attributes".to_string()),
                     }
-
-                    ip += 1;
+                    PyObject::NativeClass(c) => {
+                        if let Some(method) = c.methods.get(attr_name) {
+                            self.stack.push(method.clone());
+                        } else {
+                            return Err(format!(
+                                "AttributeError: type '{}' has no attribute '{}'",
+                                c.name, attr_name
+                            ));
+                        }
+                    }
+                    PyObject::Function(f) => match attr_name.as_str() {
+                        "__name__" => self.stack.push(PyObject::Str(f.name.clone())),
+                        "__doc__" => self
+                            .stack
+                            .push(f.code.doc.clone().map_or(PyObject::None, PyObject::Str)),
+                        _ => {
+                            return Err(format!(
+                                "AttributeError: 'function' object has no attribute '{}'",
+                                attr_name
+                            ));
+                        }
+                    },
+                    other => return Err(no_attribute(&other, attr_name)),
                 }
-                Op::StoreAttr(idx) => {
-                    let attr_name = cur.names[idx].clone();
-                    let value = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
-                    let obj = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
 
-                    match &obj {
-                        PyObject::Instance(inst) => {
-                            inst.borrow_mut().attrs.insert(attr_name, value);
-                        }
-                        _ => return Err("AttributeError: cannot set attribute".to_string()),
+                st.ip += 1;
+            }
+            Op::StoreAttr(idx) => {
+                let attr_name = st.cur.name(idx)?.clone();
+                let value = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                let obj = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+
+                match &obj {
+                    PyObject::Instance(inst) => {
+                        inst.borrow_mut().attrs.insert(attr_name, value);
+                    }
+                    PyObject::Module(m) => {
+                        m.borrow().dict.borrow_mut().insert(attr_name, value);
                     }
+                    PyObject::Class(c) => {
+                        c.methods.borrow_mut().insert(attr_name, value);
+                    }
+                    other => return Err(no_attribute(other, &attr_name)),
+                }
 
-                    ip += 1;
+                st.ip += 1;
+            }
+            Op::CallMethod(argc) => {
+                let args = self.pop_args(argc)?;
+                let method = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| "stack underflow".to_string())?;
+                self.call_object(st, method, args, None)?;
+            }
+            Op::Import(idx) => {
+                let module_name = st.cur.name(idx)?.clone();
+                self.load_module(&module_name)?;
+
+                // like Python, `import a.b` evaluates to the top-level package
+                let top = module_name.split('.').next().unwrap_or_default();
+                let module = self.load_module(top)?;
+                self.stack.push(module);
+                st.ip += 1;
+            }
+            Op::ImportFrom { module, ref names } => {
+                let module_name = st.cur.name(module)?.clone();
+                let module_obj = self.load_module(&module_name)?;
+
+                for &(name_idx, bound_idx) in names {
+                    let name = st.cur.name(name_idx)?;
+                    let value = match &module_obj {
+                        PyObject::Module(m) => m.borrow().dict.borrow().get(name).cloned(),
+                        PyObject::NativeModule(m) => m.dict.get(name).cloned(),
+                        _ => None,
+                    };
+
+                    let Some(value) = value else {
+                        return Err(format!(
+                            "ImportError: cannot import name '{}' from '{}'",
+                            name, module_name
+                        ));
+                    };
+
+                    self.env
+                        .locals
+                        .borrow_mut()
+                        .insert(st.cur.name(bound_idx)?.clone(), value);
                 }
-                Op::CallMethod(argc) => {
-                    let mut args = Vec::with_capacity(argc);
-
-                    for _ in 0..argc {
-                        args.push(
-                            self.stack
-                                .pop()
-                                .ok_or_else(|| "stack underflow".to_string())?,
-                        );
+
+                st.ip += 1;
+            }
+            Op::ImportStar(idx) => {
+                let module_name = st.cur.name(idx)?.clone();
+                let module_obj = self.load_module(&module_name)?;
+
+                let exports = match module_obj {
+                    PyObject::Module(m) => {
+                        let module_dict = m.borrow().dict.clone();
+                        star_exports(&module_name, &module_dict.borrow())?
                     }
+                    PyObject::NativeModule(m) => star_exports(&module_name, &m.dict)?,
+                    _ => Vec::new(),
+                };
+                self.env.locals.borrow_mut().extend(exports);
 
-                    args.reverse();
+                st.ip += 1;
+            }
+        }
 
-                    let method = self
-                        .stack
-                        .pop()
-                        .ok_or_else(|| "stack underflow".to_string())?;
+        Ok(None)
+    }
+}
 
-                    match method {
-                        PyObject::NativeFunction(nf) => {
-                            let result = (nf.func)(&args)?;
-                            self.stack.push(result);
-                        }
-                        _ => return Err("TypeError: object not callable".to_string()),
-                    }
+/// Checks a native's arity, passing keyword arguments to variadic natives as
+/// a trailing `PyObject::Kwargs`.
+fn native_args(
+    name: &str,
+    arity: usize,
+    mut args: Vec<PyObject>,
+    kwargs: Option<IndexMap<String, PyObject>>,
+) -> Result<Vec<PyObject>, String> {
+    if arity != usize::MAX && arity != args.len() {
+        return Err(format!(
+            "TypeError: {}() expected {} args, got {}",
+            name,
+            arity,
+            args.len()
+        ));
+    }
+
+    if let Some(kwargs) = kwargs {
+        if arity != usize::MAX {
+            return Err(format!("TypeError: {}() takes no keyword arguments", name));
+        }
+
+        args.push(PyObject::Kwargs(Rc::new(kwargs)));
+    }
+
+    Ok(args)
+}
+
+/// Builds the local scope for a call to `fobj`, binding positional and
+/// keyword arguments to its parameters.
+fn bind_args(
+    fobj: &PyFunction,
+    args: Vec<PyObject>,
+    kwargs: IndexMap<String, PyObject>,
+) -> Result<Env, String> {
+    let argc = args.len();
+    let params = fobj
+        .code
+        .names
+        .get(..fobj.arity)
+        .ok_or_else(|| format!("RuntimeError: malformed function {}()", fobj.name))?;
+
+    if argc > params.len() {
+        return Err(format!(
+            "TypeError: {}() takes {} positional argument{} but {} {} given",
+            fobj.name,
+            params.len(),
+            if params.len() == 1 { "" } else { "s" },
+            argc,
+            if argc == 1 { "was" } else { "were" }
+        ));
+    }
+
+    let env = fobj.globals.child();
 
-                    ip += 1;
+    {
+        let mut locals = env.locals.borrow_mut();
+
+        for (name, arg) in params.iter().zip(args) {
+            locals.insert(name.clone(), arg);
+        }
+
+        for (name, value) in kwargs {
+            match params.iter().position(|p| *p == name) {
+                Some(i) if i < argc => {
+                    return Err(format!(
+                        "TypeError: {}() got multiple values for argument '{}'",
+                        fobj.name, name
+                    ));
                 }
-                Op::Import(idx) => {
-                    let module_name = &cur.names[idx];
-                    let module = self.load_module(module_name)?;
-                    self.env.locals.insert(module_name.clone(), module);
-                    ip += 1;
+                Some(_) => {
+                    locals.insert(name, value);
                 }
-                Op::ImportFrom { module, ref names } => {
-                    let module_name = cur.names[module].clone();
-                    let module_obj = self.load_module(&module_name)?;
-
-                    match module_obj {
-                        PyObject::Module(m) => {
-                            let module_dict = &m.borrow().dict;
-                            for name_idx in names {
-                                let name = cur.names[*name_idx].clone();
-                                if let Some(value) = module_dict.get(&name) {
-                                    self.env.locals.insert(name.clone(), value.clone());
-                                } else {
-                                    return Err(format!(
-                                        "ImportError: cannot import name '{}' from '{}'",
-                                        name, module_name
-                                    ));
-                                }
-                            }
-                        }
-                        PyObject::NativeModule(m) => {
-                            for name_idx in names {
-                                let name = cur.names[*name_idx].clone();
-                                if let Some(value) = m.dict.get(&name) {
-                                    self.env.locals.insert(name.clone(), value.clone());
-                                } else {
-                                    return Err(format!(
-                                        "ImportError: cannot import name '{}' from '{}'",
-                                        name, module_name
-                                    ));
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-
-                    ip += 1;
+                None => {
+                    return Err(format!(
+                        "TypeError: {}() got an unexpected keyword argument '{}'",
+                        fobj.name, name
+                    ));
                 }
-                Op::ImportStar(idx) => {
-                    let module_name = cur.names[idx].clone();
-                    let module_obj = self.load_module(&module_name)?;
-
-                    match module_obj {
-                        PyObject::Module(m) => {
-                            let module_dict = &m.borrow().dict;
-                            for (name, value) in module_dict {
-                                if !name.starts_with('_') {
-                                    self.env.locals.insert(name.clone(), value.clone());
-                                }
-                            }
-                        }
-                        PyObject::NativeModule(m) => {
-                            for (name, value) in &m.dict {
-                                if !name.starts_with('_') {
-                                    self.env.locals.insert(name.clone(), value.clone());
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
+            }
+        }
 
-                    ip += 1;
-                }
+        let first_default = params.len().saturating_sub(fobj.defaults.len());
+        for (name, value) in params[first_default..].iter().zip(&fobj.defaults) {
+            if !locals.contains_key(name) {
+                locals.insert(name.clone(), value.clone());
             }
         }
+
+        let missing: Vec<String> = params
+            .iter()
+            .filter(|p| !locals.contains_key(*p))
+            .map(|p| format!("'{}'", p))
+            .collect();
+
+        if !missing.is_empty() {
+            let names = match missing.split_last() {
+                Some((last, [])) => last.clone(),
+                Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+                None => unreachable!(),
+            };
+            return Err(format!(
+                "TypeError: {}() missing {} required positional argument{}: {}",
+                fobj.name,
+                missing.len(),
+                if missing.len() == 1 { "" } else { "s" },
+                names
+            ));
+        }
     }
+
+    Ok(env)
+}
+
+/// The names `from module import *` binds: those listed in the module's
+/// `__all__` if it has one, else every name not starting with an underscore.
+fn star_exports(
+    module: &str,
+    dict: &HashMap<String, PyObject>,
+) -> Result<Vec<(String, PyObject)>, String> {
+    let names = match dict.get("__all__") {
+        None => {
+            return Ok(dict
+                .iter()
+                .filter(|(name, _)| !name.starts_with('_'))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect());
+        }
+        Some(PyObject::List(l)) => l.borrow().clone(),
+        Some(PyObject::Tuple(t)) => t.clone(),
+        Some(other) => {
+            return Err(format!(
+                "TypeError: {}.__all__ must be a list or tuple, not {}",
+                module,
+                other.type_name()
+            ));
+        }
+    };
+
+    names
+        .into_iter()
+        .map(|name| match name {
+            PyObject::Str(name) => match dict.get(&name) {
+                Some(value) => Ok((name, value.clone())),
+                None => Err(format!(
+                    "AttributeError: module '{}' has no attribute '{}'",
+                    module, name
+                )),
+            },
+            other => Err(format!(
+                "TypeError: Item in {}.__all__ must be str, not {}",
+                module,
+                other.type_name()
+            )),
+        })
+        .collect()
 }
 
-fn is_falsey(v: &PyObject) -> bool {
+fn no_attribute(obj: &PyObject, name: &str) -> String {
+    format!(
+        "AttributeError: '{}' object has no attribute '{}'",
+        obj.type_name(),
+        name
+    )
+}
+
+pub(crate) fn check_hashable(v: &PyObject) -> Result<(), String> {
+    if v.is_hashable() {
+        Ok(())
+    } else {
+        Err(format!("TypeError: unhashable type: '{}'", v.type_name()))
+    }
+}
+
+pub(crate) fn is_falsey(v: &PyObject) -> bool {
     match v {
         PyObject::Bool(b) => !b,
         PyObject::None => true,
         PyObject::Int(i) => *i == 0,
         PyObject::Float(x) => *x == 0.0,
+        PyObject::Complex(re, im) => *re == 0.0 && *im == 0.0,
+        PyObject::Decimal(d) => d.is_zero(),
         PyObject::Str(s) => s.is_empty(),
+        PyObject::Bytes(b) => b.is_empty(),
         PyObject::List(l) => l.borrow().is_empty(),
+        PyObject::Deque(d) => d.borrow().is_empty(),
         PyObject::Dict(d) => d.borrow().is_empty(),
         PyObject::Tuple(t) => t.is_empty(),
         PyObject::Set(s) => s.borrow().is_empty(),
+        PyObject::Range(start, stop, step) => range_len(*start, *stop, *step) == 0,
         _ => false,
     }
 }
 
-fn arith_add(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
-        (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Int(x + y)),
+/// Drains an iterable into a vector, as unpacking assignments need.
+fn iter_items(obj: &PyObject) -> Result<Vec<PyObject>, String> {
+    match obj {
+        PyObject::Iterator(it) => Ok(it.borrow_mut().by_ref().collect()),
+        PyObject::List(_)
+        | PyObject::Tuple(_)
+        | PyObject::Str(_)
+        | PyObject::Bytes(_)
+        | PyObject::Deque(_)
+        | PyObject::Set(_)
+        | PyObject::Dict(_)
+        | PyObject::Range(..) => Ok(PyIterator::new(obj.clone()).collect()),
+        _ => Err(format!(
+            "TypeError: cannot unpack non-iterable {} object",
+            obj.type_name()
+        )),
+    }
+}
+
+pub(crate) fn range_len(start: i64, stop: i64, step: i64) -> i128 {
+    // the span of an i64 range can exceed i64
+    let (start, stop, step) = (start as i128, stop as i128, step as i128);
+
+    if step > 0 && start < stop {
+        (stop - start + step - 1) / step
+    } else if step < 0 && start > stop {
+        (start - stop - step - 1) / -step
+    } else {
+        0
+    }
+}
+
+fn contains(container: &PyObject, item: &PyObject) -> Result<bool, String> {
+    match container {
+        PyObject::List(l) => Ok(l.borrow().iter().any(|x| x == item)),
+        PyObject::Tuple(t) => Ok(t.iter().any(|x| x == item)),
+        PyObject::Set(s) => {
+            check_hashable(item)?;
+            Ok(s.borrow().contains(item))
+        }
+        PyObject::Dict(d) => {
+            check_hashable(item)?;
+            Ok(d.borrow().contains_key(item))
+        }
+        PyObject::Str(s) => match item {
+            PyObject::Str(sub) => Ok(s.contains(sub.as_str())),
+            _ => Err(format!(
+                "TypeError: 'in <string>' requires string as left operand, not {}",
+                item.type_name()
+            )),
+        },
+        // arithmetic membership test instead of walking the range
+        PyObject::Range(start, stop, step) => match item {
+            PyObject::Int(v) => {
+                let in_bounds = if *step > 0 {
+                    v >= start && v < stop
+                } else {
+                    v <= start && v > stop
+                };
+                Ok(in_bounds && (*v as i128 - *start as i128) % *step as i128 == 0)
+            }
+            _ => Ok(false),
+        },
+        _ => Err(format!(
+            "TypeError: argument of type '{}' is not iterable",
+            container.type_name()
+        )),
+    }
+}
+
+fn as_complex(v: &PyObject) -> Option<(f64, f64)> {
+    match v {
+        PyObject::Int(n) => Some((*n as f64, 0.0)),
+        PyObject::Float(f) => Some((*f, 0.0)),
+        PyObject::Complex(re, im) => Some((*re, *im)),
+        _ => None,
+    }
+}
+
+fn complex_op(a: &PyObject, b: &PyObject, op: char) -> Result<PyObject, String> {
+    let (Some((ar, ai)), Some((br, bi))) = (as_complex(a), as_complex(b)) else {
+        return Err(unsupported_operands(
+            &op.to_string(),
+            &a.type_name(),
+            &b.type_name(),
+        ));
+    };
+
+    let (re, im) = match op {
+        '+' => (ar + br, ai + bi),
+        '-' => (ar - br, ai - bi),
+        '*' => (ar * br - ai * bi, ar * bi + ai * br),
+        _ => {
+            let denom = br * br + bi * bi;
+            if denom == 0.0 {
+                return Err("ZeroDivisionError: complex division by zero".to_string());
+            }
+            ((ar * br + ai * bi) / denom, (ai * br - ar * bi) / denom)
+        }
+    };
+
+    Ok(PyObject::Complex(re, im))
+}
+
+/// `bool` is a subclass of `int`, so arithmetic and ordering treat it as 0/1.
+fn bool_as_int(v: PyObject) -> PyObject {
+    match v {
+        PyObject::Bool(b) => PyObject::Int(b as i64),
+        v => v,
+    }
+}
+
+/// `obj[index]`, shared by `LoadIndex` and `operator.getitem`.
+pub(crate) fn load_index(obj: PyObject, index: PyObject) -> Result<PyObject, String> {
+    match (obj, index) {
+        (PyObject::List(l), PyObject::Int(i)) => {
+            let list = l.borrow();
+            let idx = if i < 0 { list.len() as i64 + i } else { i } as usize;
+            if idx < list.len() {
+                Ok(list[idx].clone())
+            } else {
+                Err("IndexError: list index out of range".to_string())
+            }
+        }
+        (PyObject::Dict(d), k) => {
+            check_hashable(&k)?;
+            d.borrow()
+                .get(&k)
+                .cloned()
+                .ok_or_else(|| format!("KeyError: {}", k.repr()))
+        }
+        (PyObject::Tuple(t), PyObject::Int(i)) => {
+            let idx = if i < 0 { t.len() as i64 + i } else { i } as usize;
+            if idx < t.len() {
+                Ok(t[idx].clone())
+            } else {
+                Err("IndexError: tuple index out of range".to_string())
+            }
+        }
+        (PyObject::Bytes(b), PyObject::Int(i)) => {
+            let idx = if i < 0 { b.len() as i64 + i } else { i } as usize;
+            if idx < b.len() {
+                Ok(PyObject::Int(b[idx] as i64))
+            } else {
+                Err("IndexError: index out of range".to_string())
+            }
+        }
+        (PyObject::Str(s), PyObject::Int(i)) => {
+            // index by char, not byte, so non-ASCII text behaves
+            let len = s.chars().count() as i64;
+            let idx = if i < 0 { len + i } else { i };
+            match (0..len).contains(&idx).then(|| s.chars().nth(idx as usize)) {
+                Some(Some(c)) => Ok(PyObject::Str(c.to_string())),
+                _ => Err("IndexError: string index out of range".to_string()),
+            }
+        }
+        // `List[int]` and the like evaluate to the placeholder itself
+        (PyObject::Type(t), _) if crate::core::typing::is_special_form(&t) => Ok(PyObject::Type(t)),
+        _ => Err("TypeError: invalid indexing operation".to_string()),
+    }
+}
+
+/// Which operands were bools, recorded before `bool_as_int` turns them into
+/// ints so error messages can still name them.
+fn bool_operands(a: &PyObject, b: &PyObject) -> (bool, bool) {
+    (
+        matches!(a, PyObject::Bool(_)),
+        matches!(b, PyObject::Bool(_)),
+    )
+}
+
+fn operand_types(a: &PyObject, b: &PyObject, bools: (bool, bool)) -> (String, String) {
+    let name = |v: &PyObject, was_bool: bool| {
+        if was_bool {
+            "bool".to_string()
+        } else {
+            v.type_name()
+        }
+    };
+    (name(a, bools.0), name(b, bools.1))
+}
+
+fn unsupported_operands(op: &str, a: &str, b: &str) -> String {
+    format!(
+        "TypeError: unsupported operand type(s) for {}: '{}' and '{}'",
+        op, a, b
+    )
+}
+
+/// Ints are 64-bit here, so results that don't fit raise instead of wrapping.
+fn int_op(result: Option<i64>, op: char) -> Result<PyObject, String> {
+    result
+        .map(PyObject::Int)
+        .ok_or_else(|| format!("OverflowError: integer overflow in {}", op))
+}
+
+/// `n` copies of `items`; a negative count gives none, as for `seq * n`.
+/// `s * n`, failing instead of aborting when the result can't be allocated.
+fn repeat_str(s: &str, n: i64) -> Result<String, String> {
+    let n = n.max(0) as usize;
+    if s.is_empty() {
+        return Ok(String::new());
+    }
+
+    let len = s
+        .len()
+        .checked_mul(n)
+        .filter(|&len| len <= isize::MAX as usize)
+        .ok_or_else(|| "OverflowError: repeated string is too long".to_string())?;
+    let mut out = String::new();
+    out.try_reserve_exact(len)
+        .map_err(|_| "MemoryError".to_string())?;
+    for _ in 0..n {
+        out.push_str(s);
+    }
+    Ok(out)
+}
+
+/// `items * n` for lists and tuples, with the same allocation checks.
+fn repeat_items(items: &[PyObject], n: i64) -> Result<Vec<PyObject>, String> {
+    let n = n.max(0) as usize;
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut out = Vec::new();
+    items
+        .len()
+        .checked_mul(n)
+        .and_then(|len| out.try_reserve_exact(len).ok())
+        .ok_or_else(|| "MemoryError".to_string())?;
+    for _ in 0..n {
+        out.extend_from_slice(items);
+    }
+    Ok(out)
+}
+
+pub(crate) fn arith_add(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    let bools = bool_operands(&a, &b);
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Complex(..), b) | (a, b @ PyObject::Complex(..)) => complex_op(&a, &b, '+'),
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => decimal_op(&a, &b, '+'),
+        (PyObject::Int(x), PyObject::Int(y)) => int_op(x.checked_add(y), '+'),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Float(x + y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Float(x as f64 + y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Float(x + y as f64)),
         (PyObject::Str(a), PyObject::Str(b)) => Ok(PyObject::Str(a + &b)),
-        _ => Err("TypeError: unsupported operand type(s) for +".to_string()),
+        (PyObject::List(x), PyObject::List(y)) => {
+            let items = x
+                .borrow()
+                .iter()
+                .chain(y.borrow().iter())
+                .cloned()
+                .collect();
+            Ok(PyObject::List(Rc::new(RefCell::new(items))))
+        }
+        (PyObject::Tuple(x), PyObject::Tuple(y)) => Ok(PyObject::Tuple([x, y].concat())),
+        (a @ (PyObject::Str(_) | PyObject::List(_) | PyObject::Tuple(_)), b) => {
+            let (a, b) = operand_types(&a, &b, bools);
+            Err(format!(
+                "TypeError: can only concatenate {} (not \"{}\") to {}",
+                a, b, a
+            ))
+        }
+        (a, b) => {
+            let (a, b) = operand_types(&a, &b, bools);
+            Err(unsupported_operands("+", &a, &b))
+        }
     }
 }
 
-fn arith_sub(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
-        (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Int(x - y)),
+pub(crate) fn arith_sub(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    let bools = bool_operands(&a, &b);
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Complex(..), b) | (a, b @ PyObject::Complex(..)) => complex_op(&a, &b, '-'),
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => decimal_op(&a, &b, '-'),
+        (PyObject::Int(x), PyObject::Int(y)) => int_op(x.checked_sub(y), '-'),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Float(x - y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Float(x as f64 - y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Float(x - y as f64)),
-        _ => Err("TypeError: unsupported operand type(s) for -".to_string()),
+        (a, b) => {
+            let (a, b) = operand_types(&a, &b, bools);
+            Err(unsupported_operands("-", &a, &b))
+        }
     }
 }
 
-fn arith_mul(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
-        (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Int(x * y)),
+pub(crate) fn arith_mul(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    let bools = bool_operands(&a, &b);
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Complex(..), b) | (a, b @ PyObject::Complex(..)) => complex_op(&a, &b, '*'),
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => decimal_op(&a, &b, '*'),
+        (PyObject::Int(x), PyObject::Int(y)) => int_op(x.checked_mul(y), '*'),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Float(x * y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Float(x as f64 * y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Float(x * y as f64)),
-        _ => Err("TypeError: unsupported operand type(s) for *".to_string()),
+        (PyObject::Str(s), PyObject::Int(n)) | (PyObject::Int(n), PyObject::Str(s)) => {
+            Ok(PyObject::Str(repeat_str(&s, n)?))
+        }
+        (PyObject::List(l), PyObject::Int(n)) | (PyObject::Int(n), PyObject::List(l)) => {
+            let items = repeat_items(&l.borrow(), n)?;
+            Ok(PyObject::List(Rc::new(RefCell::new(items))))
+        }
+        (PyObject::Tuple(t), PyObject::Int(n)) | (PyObject::Int(n), PyObject::Tuple(t)) => {
+            Ok(PyObject::Tuple(repeat_items(&t, n)?))
+        }
+        (a, b) => {
+            let (a, b) = operand_types(&a, &b, bools);
+            Err(unsupported_operands("*", &a, &b))
+        }
+    }
+}
+
+pub(crate) fn arith_div(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    let bools = bool_operands(&a, &b);
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Complex(..), b) | (a, b @ PyObject::Complex(..)) => complex_op(&a, &b, '/'),
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => decimal_op(&a, &b, '/'),
+        (PyObject::Int(x), PyObject::Int(y)) => float_div(x as f64, y as f64),
+        (PyObject::Float(x), PyObject::Float(y)) => float_div(x, y),
+        (PyObject::Int(x), PyObject::Float(y)) => float_div(x as f64, y),
+        (PyObject::Float(x), PyObject::Int(y)) => float_div(x, y as f64),
+        (a, b) => {
+            let (a, b) = operand_types(&a, &b, bools);
+            Err(unsupported_operands("/", &a, &b))
+        }
     }
 }
 
-fn arith_div(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
-        (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Float(x as f64 / y as f64)),
-        (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Float(x / y)),
-        (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Float(x as f64 / y)),
-        (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Float(x / y as f64)),
-        _ => Err("TypeError: unsupported operand type(s) for /".to_string()),
+pub(crate) fn arith_floordiv(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    match (bool_as_int(a), bool_as_int(b)) {
+        (PyObject::Int(_), PyObject::Int(0)) => {
+            Err("ZeroDivisionError: integer division or modulo by zero".to_string())
+        }
+        (PyObject::Int(x), PyObject::Int(y)) => x
+            .checked_div_euclid(y)
+            .map(|q| {
+                // div_euclid rounds towards -inf only for positive divisors
+                if y < 0 && x.rem_euclid(y) != 0 {
+                    q - 1
+                } else {
+                    q
+                }
+            })
+            .map(PyObject::Int)
+            .ok_or_else(|| "OverflowError: integer overflow in //".to_string()),
+        (PyObject::Int(x), PyObject::Float(y)) => float_floordiv(x as f64, y),
+        (PyObject::Float(x), PyObject::Int(y)) => float_floordiv(x, y as f64),
+        (PyObject::Float(x), PyObject::Float(y)) => float_floordiv(x, y),
+        _ => Err("TypeError: unsupported operand type(s) for //".to_string()),
+    }
+}
+
+fn float_div(x: f64, y: f64) -> Result<PyObject, String> {
+    if y == 0.0 {
+        return Err("ZeroDivisionError: division by zero".to_string());
     }
+    Ok(PyObject::Float(x / y))
 }
 
-fn cmp_lt(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
+fn float_floordiv(x: f64, y: f64) -> Result<PyObject, String> {
+    if y == 0.0 {
+        return Err("ZeroDivisionError: float floor division by zero".to_string());
+    }
+    Ok(PyObject::Float((x / y).floor()))
+}
+
+fn as_decimal(v: &PyObject) -> Option<Decimal> {
+    match v {
+        PyObject::Int(n) => Some(Decimal::from_int(*n)),
+        PyObject::Decimal(d) => Some(*d),
+        _ => None,
+    }
+}
+
+fn decimal_op(a: &PyObject, b: &PyObject, op: char) -> Result<PyObject, String> {
+    let (Some(x), Some(y)) = (as_decimal(a), as_decimal(b)) else {
+        return Err(unsupported_operands(
+            &op.to_string(),
+            &a.type_name(),
+            &b.type_name(),
+        ));
+    };
+
+    let result = match op {
+        '+' => x.add(y),
+        '-' => x.sub(y),
+        '*' => x.mul(y),
+        _ if y.is_zero() => return Err("ZeroDivisionError: division by zero".to_string()),
+        _ => x.div(y),
+    };
+
+    result
+        .map(PyObject::Decimal)
+        .ok_or_else(|| "OverflowError: decimal result out of range".to_string())
+}
+
+fn decimal_cmp(a: &PyObject, b: &PyObject, op: &str) -> Result<Ordering, String> {
+    match (as_decimal(a), as_decimal(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).ok_or_else(|| unorderable(op, a, b)),
+        _ => Err(unorderable(op, a, b)),
+    }
+}
+
+fn unorderable(op: &str, a: &PyObject, b: &PyObject) -> String {
+    format!(
+        "TypeError: '{}' not supported between instances of '{}' and '{}'",
+        op,
+        a.type_name(),
+        b.type_name()
+    )
+}
+
+pub(crate) fn cmp_lt(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => {
+            decimal_cmp(&a, &b, "<").map(|o| PyObject::Bool(matches!(o, Ordering::Less)))
+        }
         (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Bool(x < y)),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Bool(x < y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Bool((x as f64) < y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Bool(x < y as f64)),
         (PyObject::Str(a), PyObject::Str(b)) => Ok(PyObject::Bool(a < b)),
-        _ => Err("TypeError: unsupported comparison".to_string()),
+        (a, b) => Err(unorderable("<", &a, &b)),
     }
 }
 
-fn cmp_le(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
+pub(crate) fn cmp_le(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => decimal_cmp(&a, &b, "<=")
+            .map(|o| PyObject::Bool(matches!(o, Ordering::Less | Ordering::Equal))),
         (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Bool(x <= y)),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Bool(x <= y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Bool((x as f64) <= y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Bool(x <= y as f64)),
         (PyObject::Str(a), PyObject::Str(b)) => Ok(PyObject::Bool(a <= b)),
-        _ => Err("TypeError: unsupported comparison".to_string()),
+        (a, b) => Err(unorderable("<=", &a, &b)),
     }
 }
 
-fn cmp_gt(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
+pub(crate) fn cmp_gt(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => {
+            decimal_cmp(&a, &b, ">").map(|o| PyObject::Bool(matches!(o, Ordering::Greater)))
+        }
         (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Bool(x > y)),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Bool(x > y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Bool((x as f64) > y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Bool(x > y as f64)),
         (PyObject::Str(a), PyObject::Str(b)) => Ok(PyObject::Bool(a > b)),
-        _ => Err("TypeError: unsupported comparison".to_string()),
+        (a, b) => Err(unorderable(">", &a, &b)),
     }
 }
 
-fn cmp_ge(a: PyObject, b: PyObject) -> Result<PyObject, String> {
-    match (a, b) {
+pub(crate) fn cmp_ge(a: PyObject, b: PyObject) -> Result<PyObject, String> {
+    match (bool_as_int(a), bool_as_int(b)) {
+        (a @ PyObject::Decimal(_), b) | (a, b @ PyObject::Decimal(_)) => decimal_cmp(&a, &b, ">=")
+            .map(|o| PyObject::Bool(matches!(o, Ordering::Greater | Ordering::Equal))),
         (PyObject::Int(x), PyObject::Int(y)) => Ok(PyObject::Bool(x >= y)),
         (PyObject::Float(x), PyObject::Float(y)) => Ok(PyObject::Bool(x >= y)),
         (PyObject::Int(x), PyObject::Float(y)) => Ok(PyObject::Bool((x as f64) >= y)),
         (PyObject::Float(x), PyObject::Int(y)) => Ok(PyObject::Bool(x >= y as f64)),
         (PyObject::Str(a), PyObject::Str(b)) => Ok(PyObject::Bool(a >= b)),
-        _ => Err("TypeError: unsupported comparison".to_string()),
+        (a, b) => Err(unorderable(">=", &a, &b)),
     }
 }
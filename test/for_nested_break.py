@@ -0,0 +1,7 @@
+count = 0
+for i in range(3):
+  for j in range(5):
+    if j == 2:
+      break
+    count = count + 1
+count
@@ -0,0 +1,9 @@
+def find(xs):
+  for x in xs:
+    if x > 1:
+      return x
+  return 0
+total = 0
+for i in range(3):
+  total = total + find([1, 2, 3])
+total
@@ -0,0 +1,10 @@
+x = 100
+def bump(x):
+  x = x + 1
+  return x
+def touch(x):
+  x = x * 2
+a = bump(1)
+b = bump(a)
+touch(5)
+(a, b, x)
@@ -0,0 +1,9 @@
+d = {'a': 1}
+r = 'none'
+try:
+  d['b']
+except ValueError:
+  r = 'value'
+except KeyError as e:
+  r = 'key'
+r
@@ -0,0 +1,8 @@
+i = 0
+total = 0
+while i < 6:
+  i = i + 1
+  if i == 3:
+    continue
+  total = total + i
+(i, total)